@@ -0,0 +1,260 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::GlobalState;
+
+use super::{
+    castle::CastleWall,
+    damage::EnemyDamageEvent,
+    enemies::{Enemy, EnemyFreeze},
+    replay::ReplayPlayback,
+    East, GameState, North, Side, South, West,
+};
+
+const ARROW_VOLLEY_DAMAGE: i32 = 40;
+const ARROW_VOLLEY_RANGE: f32 = 500.0;
+const ARROW_VOLLEY_COOLDOWN: f32 = 20.0;
+
+const BOILING_OIL_DAMAGE: i32 = 150;
+/// Distance from the wall line
+const BOILING_OIL_RANGE: f32 = 80.0;
+const BOILING_OIL_COOLDOWN: f32 = 30.0;
+
+const REPAIR_HEAL: i32 = 50;
+const REPAIR_COOLDOWN: f32 = 45.0;
+
+const FREEZE_DURATION: f32 = 5.0;
+const FREEZE_COOLDOWN: f32 = 40.0;
+
+pub struct AbilitiesPlugin;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<UseCastleAbilityEvent>()
+            .add_event::<CastleAbilityEvent<North>>()
+            .add_event::<CastleAbilityEvent<South>>()
+            .add_event::<CastleAbilityEvent<West>>()
+            .add_event::<CastleAbilityEvent<East>>()
+            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_systems(
+                (
+                    // abilities come from the replay during playback
+                    ability_key_input.run_if(not(resource_exists::<ReplayPlayback>())),
+                    ability_cooldowns,
+                    dispatch_abilities,
+                    apply_ability_to_side::<North>,
+                    apply_ability_to_side::<South>,
+                    apply_ability_to_side::<West>,
+                    apply_ability_to_side::<East>,
+                )
+                    .chain()
+                    .in_set(OnUpdate(GameState::InGame)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Serialize, Deserialize)]
+pub enum CastleAbility {
+    ArrowVolley,
+    BoilingOil,
+    Repair,
+    Freeze,
+}
+
+impl CastleAbility {
+    pub const ALL: [CastleAbility; 4] = [
+        CastleAbility::ArrowVolley,
+        CastleAbility::BoilingOil,
+        CastleAbility::Repair,
+        CastleAbility::Freeze,
+    ];
+
+    pub fn cooldown(&self) -> f32 {
+        match self {
+            CastleAbility::ArrowVolley => ARROW_VOLLEY_COOLDOWN,
+            CastleAbility::BoilingOil => BOILING_OIL_COOLDOWN,
+            CastleAbility::Repair => REPAIR_COOLDOWN,
+            CastleAbility::Freeze => FREEZE_COOLDOWN,
+        }
+    }
+}
+
+impl std::fmt::Display for CastleAbility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastleAbility::ArrowVolley => f.write_str("Volley"),
+            CastleAbility::BoilingOil => f.write_str("Oil"),
+            CastleAbility::Repair => f.write_str("Repair"),
+            CastleAbility::Freeze => f.write_str("Freeze"),
+        }
+    }
+}
+
+/// Side castle abilities are used on
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource, Serialize, Deserialize)]
+pub enum CastleAbilityTarget {
+    #[default]
+    North,
+    South,
+    West,
+    East,
+}
+
+#[derive(Debug, Resource)]
+pub struct CastleAbilityCooldowns {
+    timers: [Timer; 4],
+}
+
+impl Default for CastleAbilityCooldowns {
+    fn default() -> Self {
+        // all abilities are ready at the start
+        let timers = CastleAbility::ALL.map(|ability| {
+            let mut timer = Timer::from_seconds(ability.cooldown(), TimerMode::Once);
+            timer.tick(timer.duration());
+            timer
+        });
+        Self { timers }
+    }
+}
+
+impl CastleAbilityCooldowns {
+    pub fn is_ready(&self, ability: CastleAbility) -> bool {
+        self.timers[ability as usize].finished()
+    }
+
+    /// Seconds left until ability can be used again
+    pub fn remaining(&self, ability: CastleAbility) -> f32 {
+        self.timers[ability as usize].remaining_secs()
+    }
+}
+
+/// Event to use ability on the current `CastleAbilityTarget`
+pub struct UseCastleAbilityEvent {
+    pub ability: CastleAbility,
+}
+
+/// Event to use ability on specific side
+pub struct CastleAbilityEvent<S: Side> {
+    pub ability: CastleAbility,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> CastleAbilityEvent<S> {
+    pub fn new(ability: CastleAbility) -> Self {
+        Self {
+            ability,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(CastleAbilityCooldowns::default());
+    commands.insert_resource(CastleAbilityTarget::default());
+}
+
+/// Arrows select the side, number keys use abilities
+fn ability_key_input(
+    keyboard: Res<Input<KeyCode>>,
+    mut target: ResMut<CastleAbilityTarget>,
+    mut ability_events: EventWriter<UseCastleAbilityEvent>,
+) {
+    if keyboard.just_pressed(KeyCode::Up) {
+        *target = CastleAbilityTarget::North;
+    }
+    if keyboard.just_pressed(KeyCode::Down) {
+        *target = CastleAbilityTarget::South;
+    }
+    if keyboard.just_pressed(KeyCode::Left) {
+        *target = CastleAbilityTarget::West;
+    }
+    if keyboard.just_pressed(KeyCode::Right) {
+        *target = CastleAbilityTarget::East;
+    }
+
+    let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+    for (key, ability) in keys.into_iter().zip(CastleAbility::ALL) {
+        if keyboard.just_pressed(key) {
+            ability_events.send(UseCastleAbilityEvent { ability });
+        }
+    }
+}
+
+fn ability_cooldowns(time: Res<Time>, mut cooldowns: ResMut<CastleAbilityCooldowns>) {
+    for timer in cooldowns.timers.iter_mut() {
+        timer.tick(time.delta());
+    }
+}
+
+/// Sends abilities which are not on cooldown
+/// to the currently targeted side
+pub fn dispatch_abilities(
+    target: Res<CastleAbilityTarget>,
+    mut cooldowns: ResMut<CastleAbilityCooldowns>,
+    mut use_ability_events: EventReader<UseCastleAbilityEvent>,
+    mut north_events: EventWriter<CastleAbilityEvent<North>>,
+    mut south_events: EventWriter<CastleAbilityEvent<South>>,
+    mut west_events: EventWriter<CastleAbilityEvent<West>>,
+    mut east_events: EventWriter<CastleAbilityEvent<East>>,
+) {
+    for event in use_ability_events.iter() {
+        if !cooldowns.is_ready(event.ability) {
+            continue;
+        }
+        cooldowns.timers[event.ability as usize].reset();
+
+        match *target {
+            CastleAbilityTarget::North => north_events.send(CastleAbilityEvent::new(event.ability)),
+            CastleAbilityTarget::South => south_events.send(CastleAbilityEvent::new(event.ability)),
+            CastleAbilityTarget::West => west_events.send(CastleAbilityEvent::new(event.ability)),
+            CastleAbilityTarget::East => east_events.send(CastleAbilityEvent::new(event.ability)),
+        }
+    }
+}
+
+fn apply_ability_to_side<S: Side>(
+    enemies: Query<(Entity, &Transform), With<Enemy<S>>>,
+    mut freeze: ResMut<EnemyFreeze<S>>,
+    mut wall: Query<(&Transform, &mut CastleWall<S>)>,
+    mut ability_events: EventReader<CastleAbilityEvent<S>>,
+    mut damage_events: EventWriter<EnemyDamageEvent<S>>,
+) {
+    let (wall_transform, mut wall) = wall.single_mut();
+    for event in ability_events.iter() {
+        match event.ability {
+            CastleAbility::ArrowVolley => {
+                for (enemy, transform) in enemies.iter() {
+                    let distance = (transform.translation - wall_transform.translation)
+                        .truncate()
+                        .length();
+                    if distance < ARROW_VOLLEY_RANGE {
+                        damage_events.send(EnemyDamageEvent::new(
+                            enemy,
+                            ARROW_VOLLEY_DAMAGE,
+                            false,
+                        ));
+                    }
+                }
+            }
+            CastleAbility::BoilingOil => {
+                for (enemy, transform) in enemies.iter() {
+                    // distance to the wall line, same as for enemy attacks
+                    let distance = (wall_transform
+                        .translation
+                        .truncate()
+                        .dot(S::DIRECTION.abs())
+                        - transform.translation.truncate().dot(S::DIRECTION.abs()))
+                    .abs()
+                        - wall.half_thickness;
+                    if distance < BOILING_OIL_RANGE {
+                        damage_events.send(EnemyDamageEvent::new(enemy, BOILING_OIL_DAMAGE, false));
+                    }
+                }
+            }
+            CastleAbility::Repair => wall.heal(REPAIR_HEAL),
+            CastleAbility::Freeze => freeze.freeze(FREEZE_DURATION),
+        }
+    }
+}
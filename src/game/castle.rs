@@ -1,37 +1,66 @@
 use std::marker::PhantomData;
 
-use bevy::{prelude::*, sprite::Anchor};
+use bevy::{prelude::*, reflect::TypeUuid, sprite::Anchor};
 use bevy_asset_loader::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{utils::remove_all_with, GlobalState};
 
 use super::{
-    weapons::{crossbow::CrossbowBundle, molotov::MolotovBundle},
+    data::{data_modified, RonAssetLoader},
+    profile::Profile,
+    weapons::{
+        crossbow::{AnyCrossbowMut, Crossbow, CrossbowBundle, CrossbowStats},
+        molotov::{AnyMolotovMut, Molotov, MolotovBundle, MolotovStats},
+        WeaponStats, WeaponsAssets,
+    },
     East, GameState, North, Side, South, West,
 };
 
-const WALL_HEALTH: i32 = 100;
+const FORTRESS_UNLOCK_KILLS: u32 = 1000;
+const TOWER_UNLOCK_TIME: f32 = 60.0 * 10.0;
+const ALCHEMIST_UNLOCK_LEVEL: u32 = 25;
 
-const CASTLE_FIRST_LEVEL_EXP: u32 = 10;
-const CASTLE_NEXT_LEVEL_EXP_GROWTH: f32 = 1.2;
+/// Cap for missile block and interception chances
+const MAX_MISSILE_CHANCE: f32 = 0.75;
 
 pub struct CastlePlugin;
 
 impl Plugin for CastlePlugin {
     fn build(&self, app: &mut App) {
-        app.add_collection_to_loading_state::<_, CastleAssets>(GlobalState::AssetLoading)
+        app.add_asset::<CastleDefinitions>()
+            .add_asset_loader(RonAssetLoader::<CastleDefinitions>::new(&["castles.ron"]))
+            .add_collection_to_loading_state::<_, CastleAssets>(GlobalState::AssetLoading)
+            .init_resource::<CastleType>()
+            .add_event::<SpendLevelUpsEvent>()
             .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
             .add_systems(
                 (
+                    spend_level_ups.before(castle_level_up),
                     castle_level_up,
                     check_wall_destroyed::<North>,
                     check_wall_destroyed::<South>,
                     check_wall_destroyed::<West>,
                     check_wall_destroyed::<East>,
+                    drain_wall_max_hp::<North>,
+                    drain_wall_max_hp::<South>,
+                    drain_wall_max_hp::<West>,
+                    drain_wall_max_hp::<East>,
                 )
                     .in_set(OnUpdate(GameState::InGame)),
             )
+            // data can change while the game is paused
+            .add_systems(
+                (
+                    reload_castle,
+                    reload_walls::<North>,
+                    reload_walls::<South>,
+                    reload_walls::<West>,
+                    reload_walls::<East>,
+                )
+                    .in_set(OnUpdate(GlobalState::InGame)),
+            )
             .add_system(remove_all_with::<CastleMarker>.in_schedule(OnExit(GlobalState::InGame)))
             .add_system(
                 remove_all_with::<CastleWallMarker>.in_schedule(OnExit(GlobalState::InGame)),
@@ -39,7 +68,7 @@ impl Plugin for CastlePlugin {
     }
 }
 
-#[derive(AssetCollection, Resource)]
+#[derive(AssetCollection, Resource, Default)]
 pub struct CastleAssets {
     #[asset(path = "sprites/castle.png")]
     pub castle: Handle<Image>,
@@ -51,6 +80,136 @@ pub struct CastleAssets {
     pub wall_west: Handle<Image>,
     #[asset(path = "sprites/wall_east.png")]
     pub wall_east: Handle<Image>,
+    #[asset(path = "data/game.castles.ron")]
+    pub definitions: Handle<CastleDefinitions>,
+}
+
+impl CastleAssets {
+    /// Used to read data when asset loading is skipped
+    pub const DEFINITIONS_PATH: &str = "data/game.castles.ron";
+}
+
+/// Stats of all castle types.
+/// Loaded from `assets/data/game.castles.ron`
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "7f1d2c94-6b3e-4a58-8c0f-5e9a1b3d7c26"]
+pub struct CastleDefinitions {
+    pub first_level_exp: u32,
+    pub castles: Vec<CastleDefinition>,
+}
+
+impl Default for CastleDefinitions {
+    /// Used only if castle data is not loaded
+    fn default() -> Self {
+        Self {
+            first_level_exp: 10,
+            castles: vec![],
+        }
+    }
+}
+
+impl CastleDefinitions {
+    pub fn get(&self, castle_type: CastleType) -> Option<&CastleDefinition> {
+        self.castles
+            .iter()
+            .find(|castle| castle.castle_type == castle_type)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CastleDefinition {
+    pub castle_type: CastleType,
+    pub wall_health: i32,
+    /// Multiplier for the wall thickness
+    pub wall_thickness: f32,
+    pub next_level_exp_growth: f32,
+    /// Replaces crossbow stats from `WeaponStats`
+    #[serde(default)]
+    pub crossbow: Option<CrossbowStats>,
+    /// Replaces molotov stats from `WeaponStats`
+    #[serde(default)]
+    pub molotov: Option<MolotovStats>,
+}
+
+impl Default for CastleDefinition {
+    /// Used only if castle data is not loaded
+    fn default() -> Self {
+        Self {
+            castle_type: CastleType::Stone,
+            wall_health: 100,
+            wall_thickness: 1.0,
+            next_level_exp_growth: 1.2,
+            crossbow: None,
+            molotov: None,
+        }
+    }
+}
+
+impl CastleDefinition {
+    pub fn crossbow<S: Side>(&self, weapon_stats: &WeaponStats) -> Crossbow<S> {
+        self.crossbow
+            .as_ref()
+            .unwrap_or(&weapon_stats.crossbow)
+            .crossbow()
+    }
+
+    pub fn molotov<S: Side>(&self, weapon_stats: &WeaponStats) -> Molotov<S> {
+        self.molotov
+            .as_ref()
+            .unwrap_or(&weapon_stats.molotov)
+            .molotov()
+    }
+}
+
+/// Castle archetypes unlocked across the sessions
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Component, Resource, Serialize, Deserialize,
+)]
+pub enum CastleType {
+    #[default]
+    Stone,
+    // Thick walls, slow leveling
+    Fortress,
+    // Long range crossbows, fragile walls
+    Tower,
+    // Strong molotovs, weak crossbows
+    Alchemist,
+}
+
+impl CastleType {
+    pub const ALL: [CastleType; 4] = [
+        CastleType::Stone,
+        CastleType::Fortress,
+        CastleType::Tower,
+        CastleType::Alchemist,
+    ];
+
+    pub fn is_unlocked(&self, profile: &Profile) -> bool {
+        match self {
+            CastleType::Stone => true,
+            CastleType::Fortress => FORTRESS_UNLOCK_KILLS <= profile.total_kills,
+            CastleType::Tower => TOWER_UNLOCK_TIME <= profile.best_time,
+            CastleType::Alchemist => ALCHEMIST_UNLOCK_LEVEL <= profile.best_level,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            CastleType::Stone => "Balanced walls",
+            CastleType::Fortress => "Thick walls\nSlow leveling",
+            CastleType::Tower => "Long range\nFragile walls",
+            CastleType::Alchemist => "Strong molotovs\nWeak crossbows",
+        }
+    }
+
+    pub fn unlock_requirement(&self) -> String {
+        match self {
+            CastleType::Stone => "Always available".to_string(),
+            CastleType::Fortress => format!("Kill {FORTRESS_UNLOCK_KILLS} enemies in total"),
+            CastleType::Tower => format!("Survive {TOWER_UNLOCK_TIME:.0}s in one run"),
+            CastleType::Alchemist => format!("Reach level {ALCHEMIST_UNLOCK_LEVEL}"),
+        }
+    }
 }
 
 #[derive(Component)]
@@ -59,36 +218,67 @@ pub struct Castle {
     pub exp: u32,
     pub next_level_exp: u32,
     pub next_level_exp_growth: f32,
+    pub kills: u32,
+    /// Levels reached without a picked upgrade
+    pub pending_level_ups: u32,
+    /// Pending level ups wait until the player spends them
+    pub bank_level_ups: bool,
 }
 
+impl Castle {
+    /// Level of the upgrade picked now.
+    /// Pending level ups are spent from the oldest one.
+    pub fn upgrade_level(&self) -> u32 {
+        self.level + 1 - self.pending_level_ups.max(1)
+    }
+}
+
+/// Sent to spend banked level ups
+pub struct SpendLevelUpsEvent;
+
 #[derive(Component)]
 pub struct CastleMarker;
 
 #[derive(Bundle)]
 pub struct CastleBundle {
     castle: Castle,
+    castle_type: CastleType,
     marker: CastleMarker,
 }
 
-impl Default for CastleBundle {
-    fn default() -> Self {
+impl CastleBundle {
+    pub fn new(definition: &CastleDefinition, first_level_exp: u32) -> Self {
         Self {
             castle: Castle {
                 level: 0,
                 exp: 0,
-                next_level_exp: CASTLE_FIRST_LEVEL_EXP,
-                next_level_exp_growth: CASTLE_NEXT_LEVEL_EXP_GROWTH,
+                next_level_exp: first_level_exp,
+                next_level_exp_growth: definition.next_level_exp_growth,
+                kills: 0,
+                pending_level_ups: 0,
+                bank_level_ups: false,
             },
+            castle_type: definition.castle_type,
             marker: CastleMarker,
         }
     }
 }
 
-#[derive(Component)]
+#[derive(Clone, Component)]
 pub struct CastleWall<S: Side> {
     pub health: i32,
     pub max_health: i32,
+    /// Max health from castle data without upgrades
+    pub base_health: i32,
     pub half_thickness: f32,
+    /// Chance to block a missile hitting the wall
+    pub missile_block: f32,
+    /// Chance to shoot down a missile before it reaches the wall
+    pub missile_interception: f32,
+    /// Max hp lost every second
+    pub max_hp_drain: f32,
+    /// Part of the max hp drained, but not taken from the wall yet
+    drained: f32,
     _phantom: PhantomData<S>,
 }
 
@@ -97,22 +287,61 @@ impl<S: Side> CastleWall<S> {
         Self {
             health,
             max_health: health,
+            base_health: health,
             half_thickness,
+            missile_block: 0.0,
+            missile_interception: 0.0,
+            max_hp_drain: 0.0,
+            drained: 0.0,
             _phantom: PhantomData,
         }
     }
 
+    /// Keeps upgrades and damage taken,
+    /// but never destroys the wall
+    pub fn set_base_health(&mut self, hp: i32) {
+        let diff = hp - self.base_health;
+        self.base_health = hp;
+        self.add_max_hp(diff);
+        self.health = self.health.max(1);
+    }
+
     pub fn add_max_hp(&mut self, hp: i32) {
         self.health += hp;
         self.max_health += hp;
     }
 
+    /// Wall keeps at least 1 max hp
+    pub fn remove_max_hp(&mut self, hp: i32) {
+        self.max_health = (self.max_health - hp).max(1);
+        self.health = self.health.min(self.max_health);
+    }
+
     pub fn heal(&mut self, hp: i32) {
         self.health += hp;
         if self.max_health < self.health {
             self.health = self.max_health;
         }
     }
+
+    pub fn add_missile_block(&mut self, chance: f32) {
+        self.missile_block = (self.missile_block + chance).min(MAX_MISSILE_CHANCE);
+    }
+
+    pub fn add_missile_interception(&mut self, chance: f32) {
+        self.missile_interception = (self.missile_interception + chance).min(MAX_MISSILE_CHANCE);
+    }
+
+    /// Distance from the position to the outer edge of the wall
+    pub fn distance(&self, wall_transform: &Transform, position: Vec3) -> f32 {
+        (wall_transform
+            .translation
+            .truncate()
+            .dot(S::DIRECTION.abs())
+            - position.truncate().dot(S::DIRECTION.abs()))
+        .abs()
+            - self.half_thickness
+    }
 }
 
 #[derive(Component)]
@@ -131,24 +360,36 @@ pub struct CastleWallBundle<S: Side> {
 }
 
 impl<S: Side> CastleWallBundle<S> {
-    fn new_horizontal(health: i32, x_len: f32, y_len: f32) -> Self {
+    fn new_horizontal(
+        definition: &CastleDefinition,
+        weapon_stats: &WeaponStats,
+        x_len: f32,
+        y_len: f32,
+    ) -> Self {
+        let y_len = y_len * definition.wall_thickness;
         Self {
             rigid_body: RigidBody::Fixed,
             collider: Collider::cuboid(x_len / 2.0, y_len / 2.0),
-            wall: CastleWall::new(health, y_len / 2.0),
-            crossbow: Default::default(),
-            molotov: Default::default(),
+            wall: CastleWall::new(definition.wall_health, y_len / 2.0),
+            crossbow: CrossbowBundle::new(definition.crossbow(weapon_stats)),
+            molotov: MolotovBundle::new(definition.molotov(weapon_stats)),
             marker: CastleWallMarker,
         }
     }
 
-    fn new_vertical(health: i32, x_len: f32, y_len: f32) -> Self {
+    fn new_vertical(
+        definition: &CastleDefinition,
+        weapon_stats: &WeaponStats,
+        x_len: f32,
+        y_len: f32,
+    ) -> Self {
+        let x_len = x_len * definition.wall_thickness;
         Self {
             rigid_body: RigidBody::Fixed,
             collider: Collider::cuboid(x_len / 2.0, y_len / 2.0),
-            wall: CastleWall::new(health, x_len / 2.0),
-            crossbow: Default::default(),
-            molotov: Default::default(),
+            wall: CastleWall::new(definition.wall_health, x_len / 2.0),
+            crossbow: CrossbowBundle::new(definition.crossbow(weapon_stats)),
+            molotov: MolotovBundle::new(definition.molotov(weapon_stats)),
             marker: CastleWallMarker,
         }
     }
@@ -156,14 +397,38 @@ impl<S: Side> CastleWallBundle<S> {
 
 /// Sets up castle in the center of the map
 /// with 4 walls
-fn setup(castle_assets: Res<CastleAssets>, mut commands: Commands) {
+fn setup(
+    castle_type: Res<CastleType>,
+    castle_assets: Res<CastleAssets>,
+    castle_definitions: Res<Assets<CastleDefinitions>>,
+    weapons_assets: Res<WeaponsAssets>,
+    weapon_stats: Res<Assets<WeaponStats>>,
+    mut commands: Commands,
+) {
+    let default_definitions = CastleDefinitions::default();
+    let definitions = castle_definitions
+        .get(&castle_assets.definitions)
+        .unwrap_or_else(|| {
+            warn!("Castle definitions are not loaded");
+            &default_definitions
+        });
+    let definition = definitions.get(*castle_type).cloned().unwrap_or_else(|| {
+        warn!("No castle definition for {:?}", *castle_type);
+        CastleDefinition::default()
+    });
+    let default_weapon_stats = WeaponStats::default();
+    let weapon_stats = weapon_stats.get(&weapons_assets.stats).unwrap_or_else(|| {
+        warn!("Weapon stats are not loaded");
+        &default_weapon_stats
+    });
+
     commands
         .spawn(SpriteBundle {
             transform: Transform::from_translation(Vec3::new(0.0, 0.0, 3.0)),
             texture: castle_assets.castle.clone(),
             ..default()
         })
-        .insert(CastleBundle::default());
+        .insert(CastleBundle::new(&definition, definitions.first_level_exp));
 
     // TODO: Refactor hardcoded values
     // North
@@ -179,7 +444,8 @@ fn setup(castle_assets: Res<CastleAssets>, mut commands: Commands) {
             ..default()
         })
         .insert(CastleWallBundle::<North>::new_horizontal(
-            WALL_HEALTH,
+            &definition,
+            weapon_stats,
             386.0,
             // we need custom value for north wall, so that
             // enemies don't go behind it
@@ -198,7 +464,8 @@ fn setup(castle_assets: Res<CastleAssets>, mut commands: Commands) {
             ..default()
         })
         .insert(CastleWallBundle::<South>::new_horizontal(
-            WALL_HEALTH,
+            &definition,
+            weapon_stats,
             386.0,
             24.0,
         ));
@@ -214,7 +481,8 @@ fn setup(castle_assets: Res<CastleAssets>, mut commands: Commands) {
             ..default()
         })
         .insert(CastleWallBundle::<West>::new_vertical(
-            WALL_HEALTH,
+            &definition,
+            weapon_stats,
             24.0,
             386.0,
         ));
@@ -230,25 +498,42 @@ fn setup(castle_assets: Res<CastleAssets>, mut commands: Commands) {
             ..default()
         })
         .insert(CastleWallBundle::<East>::new_vertical(
-            WALL_HEALTH,
+            &definition,
+            weapon_stats,
             24.0,
             386.0,
         ));
 }
 
+/// Turns all exp into pending level ups.
+/// Level up menu opens until all of them are spent.
 fn castle_level_up(mut castle: Query<&mut Castle>, mut game_state: ResMut<NextState<GameState>>) {
     let mut castle = castle.single_mut();
 
-    if castle.exp >= castle.next_level_exp {
+    while castle.next_level_exp != 0 && castle.exp >= castle.next_level_exp {
         castle.level += 1;
+        castle.pending_level_ups += 1;
         castle.exp -= castle.next_level_exp;
         castle.next_level_exp =
             (castle.next_level_exp as f32 * castle.next_level_exp_growth) as u32;
+    }
 
+    if castle.pending_level_ups != 0 && !castle.bank_level_ups {
         game_state.set(GameState::LevelUp);
     }
 }
 
+pub fn spend_level_ups(
+    mut castle: Query<&mut Castle>,
+    mut spend_events: EventReader<SpendLevelUpsEvent>,
+) {
+    if spend_events.is_empty() {
+        return;
+    }
+    spend_events.clear();
+    castle.single_mut().bank_level_ups = false;
+}
+
 fn check_wall_destroyed<S: Side>(
     wall: Query<&CastleWall<S>>,
     mut game_state: ResMut<NextState<GameState>>,
@@ -258,3 +543,83 @@ fn check_wall_destroyed<S: Side>(
         game_state.set(GameState::GameOver);
     }
 }
+
+fn drain_wall_max_hp<S: Side>(time: Res<Time>, mut wall: Query<&mut CastleWall<S>>) {
+    let mut wall = wall.single_mut();
+    if wall.max_hp_drain <= 0.0 {
+        return;
+    }
+    wall.drained += wall.max_hp_drain * time.delta_seconds();
+    let hp = wall.drained.floor();
+    if 1.0 <= hp {
+        wall.drained -= hp;
+        wall.remove_max_hp(hp as i32);
+    }
+}
+
+/// Applies changes of castle data to the running castle
+fn reload_castle(
+    castle_assets: Res<CastleAssets>,
+    castle_definitions: Res<Assets<CastleDefinitions>>,
+    mut castle_events: EventReader<AssetEvent<CastleDefinitions>>,
+    mut castle: Query<(&CastleType, &mut Castle)>,
+) {
+    if !data_modified(&mut castle_events, &castle_assets.definitions) {
+        return;
+    }
+    let Some(definitions) = castle_definitions.get(&castle_assets.definitions) else {
+        return;
+    };
+
+    for (castle_type, mut castle) in castle.iter_mut() {
+        if let Some(definition) = definitions.get(*castle_type) {
+            castle.next_level_exp_growth = definition.next_level_exp_growth;
+        }
+    }
+}
+
+/// Applies changes of castle and weapon data to the wall on the side.
+/// Wall thickness only changes in the next run.
+fn reload_walls<S: Side>(
+    castle_type: Res<CastleType>,
+    castle_assets: Res<CastleAssets>,
+    castle_definitions: Res<Assets<CastleDefinitions>>,
+    weapons_assets: Res<WeaponsAssets>,
+    weapon_stats: Res<Assets<WeaponStats>>,
+    mut castle_events: EventReader<AssetEvent<CastleDefinitions>>,
+    mut weapon_events: EventReader<AssetEvent<WeaponStats>>,
+    mut walls: Query<(&mut CastleWall<S>, AnyCrossbowMut<S>, AnyMolotovMut<S>)>,
+) {
+    let castle_modified = data_modified(&mut castle_events, &castle_assets.definitions);
+    let weapons_modified = data_modified(&mut weapon_events, &weapons_assets.stats);
+    if !castle_modified && !weapons_modified {
+        return;
+    }
+
+    let Some(definition) = castle_definitions
+        .get(&castle_assets.definitions)
+        .and_then(|definitions| definitions.get(*castle_type))
+    else {
+        warn!("Castle definition for {:?} is not loaded", *castle_type);
+        return;
+    };
+    let Some(weapon_stats) = weapon_stats.get(&weapons_assets.stats) else {
+        warn!("Weapon stats are not loaded");
+        return;
+    };
+
+    for (mut wall, crossbow, molotov) in walls.iter_mut() {
+        wall.set_base_health(definition.wall_health);
+        // evolved weapons keep their evolution
+        match crossbow {
+            (Some(mut crossbow), _) => *crossbow = definition.crossbow(weapon_stats),
+            (None, Some(mut repeating)) => repeating.crossbow = definition.crossbow(weapon_stats),
+            (None, None) => {}
+        }
+        match molotov {
+            (Some(mut molotov), _) => *molotov = definition.molotov(weapon_stats),
+            (None, Some(mut napalm)) => napalm.molotov = definition.molotov(weapon_stats),
+            (None, None) => {}
+        }
+    }
+}
@@ -6,8 +6,8 @@ use rand::Rng;
 
 use crate::{
     game::{
-        animation::AnimationBundle, damage::EnemyDamageEvent, East, GameState, North, Side, South,
-        West,
+        animation::AnimationBundle, damage::EnemyDamageEvent, rng::SideRng, East, GameState, North,
+        Side, South, West,
     },
     utils::remove_all_with,
     GlobalState,
@@ -41,9 +41,20 @@ pub struct DamageArea<S: Side> {
     crit_chance: f32,
     attack_timer: Timer,
     lifespan: Timer,
+    /// Spawns new areas when the lifespan ends
+    pub spread: Option<AreaSpread>,
     _phatom: PhantomData<S>,
 }
 
+/// New areas are placed evenly around the burnt out area.
+/// Spread areas do not spread further.
+#[derive(Debug, Clone, Copy)]
+pub struct AreaSpread {
+    pub areas: u32,
+    /// Distance as a multiplier of the area size
+    pub distance: f32,
+}
+
 impl<S: Side> DamageArea<S> {
     pub fn new(
         size: f32,
@@ -60,6 +71,7 @@ impl<S: Side> DamageArea<S> {
             crit_chance,
             attack_timer: Timer::from_seconds(1.0 / attack_speed, TimerMode::Repeating),
             lifespan: Timer::from_seconds(lifespan, TimerMode::Once),
+            spread: None,
             _phatom: PhantomData,
         }
     }
@@ -93,13 +105,35 @@ fn damage_area_update<S: Side>(
     time: Res<Time>,
     rapier_context: Res<RapierContext>,
     mut commands: Commands,
-    mut areas: Query<(Entity, &Transform, &mut DamageArea<S>)>,
+    mut side_rng: ResMut<SideRng<S>>,
+    mut areas: Query<(
+        Entity,
+        &Transform,
+        &Handle<TextureAtlas>,
+        &mut DamageArea<S>,
+    )>,
     mut damage_event: EventWriter<EnemyDamageEvent<S>>,
 ) {
-    let mut rng = rand::thread_rng();
-    for (area_entity, area_transform, mut area) in areas.iter_mut() {
+    let rng = &mut side_rng.areas;
+    for (area_entity, area_transform, texture_atlas, mut area) in areas.iter_mut() {
         if area.lifespan.tick(time.delta()).finished() {
             commands.entity(area_entity).despawn();
+
+            if let Some(spread) = area.spread {
+                let mut spread_area = area.clone();
+                spread_area.spread = None;
+                spread_area.lifespan.reset();
+                spread_area.attack_timer.reset();
+                for i in 0..spread.areas {
+                    let angle = std::f32::consts::TAU * i as f32 / spread.areas as f32;
+                    let offset = Vec2::from_angle(angle) * area.size * spread.distance;
+                    commands.spawn(DamageAreaBundle::<S>::new(
+                        texture_atlas.clone(),
+                        area_transform.translation + offset.extend(0.0),
+                        spread_area.clone(),
+                    ));
+                }
+            }
         } else {
             if !area.attack_timer.tick(time.delta()).finished() {
                 continue;
@@ -4,7 +4,11 @@ use bevy::prelude::*;
 
 use crate::{utils::remove_all_with, GameAssets, GlobalState};
 
-use super::{castle::CastleWall, enemies::Enemy, East, GameState, North, Side, South, West};
+use super::{
+    castle::CastleWall,
+    enemies::{elites::EliteShield, Enemy},
+    East, GameState, North, Side, South, West,
+};
 
 pub mod area;
 pub mod projectile;
@@ -97,11 +101,14 @@ fn damage_enemy<S: Side>(
     game_assets: Res<GameAssets>,
     mut commands: Commands,
     mut events: EventReader<EnemyDamageEvent<S>>,
-    mut enemies: Query<(&Transform, &mut Enemy<S>)>,
+    mut enemies: Query<(&Transform, &mut Enemy<S>, Option<&mut EliteShield>)>,
 ) {
     for event in events.iter() {
-        if let Ok((transform, mut enemy)) = enemies.get_mut(event.target) {
-            enemy.health -= event.damage;
+        if let Ok((transform, mut enemy, shield)) = enemies.get_mut(event.target) {
+            enemy.health -= match shield {
+                Some(mut shield) => shield.absorb(event.damage),
+                None => event.damage,
+            };
 
             let mut damage_text_transform = *transform;
             damage_text_transform.translation.y += 5.0;
@@ -5,13 +5,24 @@ use bevy_rapier2d::prelude::*;
 use rand::Rng;
 
 use crate::{
-    game::{damage::EnemyDamageEvent, enemies::Enemy, East, GameState, North, Side, South, West},
+    game::{
+        castle::CastleWall,
+        damage::{EnemyDamageEvent, WallDamageEvent},
+        enemies::Enemy,
+        rng::SideRng,
+        East, GameState, North, Side, South, West,
+    },
     utils::remove_all_with,
     GlobalState,
 };
 
 const DEFAULT_ARROW_LIFESPAN: f32 = 10.0;
 
+const DEFAULT_MISSILE_LIFESPAN: f32 = 10.0;
+const MISSILE_COLOR: Color = Color::DARK_GRAY;
+/// Missiles closer to the wall can be shot down
+const MISSILE_INTERCEPTION_DISTANCE: f32 = 100.0;
+
 pub struct ProjectilePlugin;
 
 impl Plugin for ProjectilePlugin {
@@ -22,6 +33,10 @@ impl Plugin for ProjectilePlugin {
                 projectile_update::<South>,
                 projectile_update::<West>,
                 projectile_update::<East>,
+                wall_projectile_update::<North>,
+                wall_projectile_update::<South>,
+                wall_projectile_update::<West>,
+                wall_projectile_update::<East>,
             )
                 .in_set(OnUpdate(GameState::InGame)),
         )
@@ -98,10 +113,11 @@ fn projectile_update<S: Side>(
     enemies: Query<Entity, With<Enemy<S>>>,
     rapier_context: Res<RapierContext>,
     mut commands: Commands,
+    mut side_rng: ResMut<SideRng<S>>,
     mut projectiles: Query<(Entity, &mut Projectile<S>)>,
     mut damage_event: EventWriter<EnemyDamageEvent<S>>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng = &mut side_rng.projectiles;
     for (projectile_entity, mut projectile) in projectiles.iter_mut() {
         if projectile.lifespan.tick(time.delta()).finished() {
             commands.entity(projectile_entity).despawn();
@@ -129,3 +145,94 @@ fn projectile_update<S: Side>(
         }
     }
 }
+
+/// Missile shot by enemies at the wall
+#[derive(Component)]
+pub struct WallProjectile<S: Side> {
+    damage: i32,
+    lifespan: Timer,
+    /// Interception is rolled only once for every missile
+    interception_rolled: bool,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> WallProjectile<S> {
+    pub fn new(damage: i32, lifespan: f32) -> Self {
+        Self {
+            damage,
+            lifespan: Timer::from_seconds(lifespan, TimerMode::Once),
+            interception_rolled: false,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Missiles fly through enemies, so they have no collider
+#[derive(Bundle)]
+pub struct WallProjectileBundle<S: Side> {
+    #[bundle]
+    sprite: SpriteBundle,
+    rigid_body: RigidBody,
+    velocity: Velocity,
+    projectile: WallProjectile<S>,
+    marker: ProjectileMarker,
+}
+
+impl<S: Side> WallProjectileBundle<S> {
+    pub fn new(size: f32, damage: i32, speed: f32, direction: Vec2, transform: Transform) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: MISSILE_COLOR,
+                    custom_size: Some(Vec2::splat(size * 2.0)),
+                    ..default()
+                },
+                transform,
+                ..default()
+            },
+            rigid_body: RigidBody::KinematicVelocityBased,
+            velocity: Velocity {
+                linvel: speed * direction,
+                ..default()
+            },
+            projectile: WallProjectile::new(damage, DEFAULT_MISSILE_LIFESPAN),
+            marker: ProjectileMarker,
+        }
+    }
+}
+
+/// Missiles can be shot down near the wall
+/// and blocked when they hit it
+fn wall_projectile_update<S: Side>(
+    time: Res<Time>,
+    wall: Query<(&Transform, &CastleWall<S>)>,
+    mut commands: Commands,
+    mut side_rng: ResMut<SideRng<S>>,
+    mut projectiles: Query<(Entity, &Transform, &mut WallProjectile<S>)>,
+    mut damage_event: EventWriter<WallDamageEvent<S>>,
+) {
+    let (wall_transform, wall) = wall.single();
+    let rng = &mut side_rng.missiles;
+    for (projectile_entity, transform, mut projectile) in projectiles.iter_mut() {
+        if projectile.lifespan.tick(time.delta()).finished() {
+            commands.entity(projectile_entity).despawn();
+            continue;
+        }
+
+        let distance = wall.distance(wall_transform, transform.translation);
+        if !projectile.interception_rolled && distance <= MISSILE_INTERCEPTION_DISTANCE {
+            projectile.interception_rolled = true;
+            if rng.gen_range(0.0..1.0) < wall.missile_interception {
+                commands.entity(projectile_entity).despawn();
+                continue;
+            }
+        }
+
+        if distance <= 0.0 {
+            if wall.missile_block <= rng.gen_range(0.0..1.0) {
+                damage_event.send(WallDamageEvent::new(projectile.damage));
+            }
+            commands.entity(projectile_entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{Asset, AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+/// Loads game data assets from RON files.
+/// Each data type gets its own extension, i.e. `enemies.ron`
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    _phantom: PhantomData<T>,
+}
+
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> AssetLoader for RonAssetLoader<T>
+where
+    T: TypeUuid + DeserializeOwned + Send + Sync + 'static,
+{
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let data = ron::de::from_bytes::<T>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(data));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+/// Checks if the data asset was changed on disk.
+/// Reads all pending events.
+pub fn data_modified<T: Asset>(
+    events: &mut EventReader<AssetEvent<T>>,
+    handle: &Handle<T>,
+) -> bool {
+    events
+        .iter()
+        .filter(|event| matches!(event, AssetEvent::Modified { handle: modified } if modified == handle))
+        .count()
+        != 0
+}
+
+/// Reads data asset straight from the assets folder.
+/// Used in headless mode where asset loading is skipped.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_data<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let path = bevy::asset::FileAssetIo::get_base_path()
+        .join("assets")
+        .join(path);
+    let data = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read data {}: {e}", path.display()))?;
+    ron::from_str(&data).map_err(|e| format!("Could not parse data {}: {e}", path.display()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_data<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    Err(format!("Could not read data {path}: not supported on wasm"))
+}
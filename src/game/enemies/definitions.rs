@@ -0,0 +1,97 @@
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
+
+use crate::game::{stats::Stat, Side};
+
+use super::{spawn::EnemyBuffs, Enemy, EnemyAttack, GlobalEnemyBuffs};
+
+/// All enemy archetypes.
+/// Loaded from `assets/data/game.enemies.ron`
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "5a4c3f0e-2d7b-4f55-9b0e-8f3b1c6e2a41"]
+pub struct EnemyDefinitions {
+    pub enemies: Vec<EnemyDefinition>,
+}
+
+impl EnemyDefinitions {
+    pub fn get(&self, name: &str) -> Option<&EnemyDefinition> {
+        self.enemies.iter().find(|enemy| enemy.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyDefinition {
+    pub name: String,
+    /// Path to the sprite sheet with all animation frames in one row
+    pub sprite_sheet: String,
+    /// Size of a single frame in the sprite sheet
+    pub tile_size: f32,
+    pub frames: usize,
+    pub fps: f64,
+    pub health: i32,
+    pub speed: f32,
+    pub exp: u32,
+    pub damage: i32,
+    pub size: f32,
+    /// Range should be bigger then enemy size / 2
+    pub range: f32,
+    /// Attacks per second
+    pub attack_speed: f32,
+    pub behaviours: Vec<EnemyBehaviour>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum EnemyBehaviour {
+    /// Attacks the wall when it is in range
+    Melee,
+    /// Stops at the range and shoots missiles at the wall
+    Ranged {
+        projectile_speed: f32,
+        projectile_size: f32,
+    },
+    /// Siege enemy charging the wall
+    Ram {
+        charge_range: f32,
+        charge_speed: f32,
+    },
+    /// Siege enemy lobbing missiles from the range
+    Catapult {
+        projectile_speed: f32,
+        projectile_size: f32,
+    },
+}
+
+impl EnemyDefinition {
+    /// Sprite sheet has at least one frame even if data says otherwise
+    pub fn frames(&self) -> usize {
+        self.frames.max(1)
+    }
+
+    pub fn enemy<S: Side>(
+        &self,
+        global_buffs: &GlobalEnemyBuffs,
+        buffs: &EnemyBuffs<S>,
+    ) -> Enemy<S> {
+        let buffs = buffs.with_global_buffs(global_buffs);
+        Enemy::new(
+            Stat::new(self.health as f32).with(&buffs.health).value() as i32,
+            Stat::new(self.speed).with(&buffs.speed).value(),
+            Stat::new(self.exp as f32).with(&buffs.exp).value() as u32,
+        )
+    }
+
+    pub fn attack<S: Side>(
+        &self,
+        global_buffs: &GlobalEnemyBuffs,
+        buffs: &EnemyBuffs<S>,
+    ) -> EnemyAttack<S> {
+        let buffs = buffs.with_global_buffs(global_buffs);
+        EnemyAttack::new(
+            Stat::new(self.damage as f32).with(&buffs.damage).value() as i32,
+            self.range,
+            Stat::new(self.attack_speed)
+                .with(&buffs.attack_speed)
+                .value(),
+        )
+    }
+}
@@ -0,0 +1,382 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, reflect::TypeUuid};
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::game::{
+    castle::CastleWall, damage::WallDamageEvent, East, GameState, North, South, West,
+};
+
+use super::{
+    definitions::{EnemyDefinition, EnemyDefinitions},
+    enemy_death,
+    spawn::{spawn_enemy, EnemyBuffs},
+    Enemy, EnemyAssets, EnemyFreeze, EnemySprites, GlobalEnemyBuffs, Side,
+};
+
+const ELITE_NAME_FONT_SIZE: f32 = 20.0;
+
+pub struct ElitesPlugin;
+
+impl Plugin for ElitesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(elite_tint.in_set(OnUpdate(GameState::InGame)))
+            .add_systems(
+                (
+                    elite_regeneration::<North>,
+                    elite_regeneration::<South>,
+                    elite_regeneration::<West>,
+                    elite_regeneration::<East>,
+                    elite_summon::<North>,
+                    elite_summon::<South>,
+                    elite_summon::<West>,
+                    elite_summon::<East>,
+                )
+                    .in_set(OnUpdate(GameState::InGame)),
+            )
+            .add_systems(
+                (
+                    elite_explosion::<North>.before(enemy_death::<North>),
+                    elite_explosion::<South>.before(enemy_death::<South>),
+                    elite_explosion::<West>.before(enemy_death::<West>),
+                    elite_explosion::<East>.before(enemy_death::<East>),
+                )
+                    .in_set(OnUpdate(GameState::InGame)),
+            );
+    }
+}
+
+/// Elite spawn chances and affixes.
+/// Loaded from `assets/data/game.elites.ron`
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "c7d41e8a-5f2b-4a93-8e06-1b9f3d7a2c64"]
+pub struct EliteDefinitions {
+    /// Chance on the first wave
+    pub base_chance: f64,
+    /// Added with every next wave
+    pub chance_per_wave: f64,
+    /// Cap for the chance with all side effects
+    pub max_chance: f64,
+    pub max_affixes: usize,
+    pub health_multiplier: f32,
+    /// Bonus exp for killing elites
+    pub exp_multiplier: f32,
+    pub affixes: Vec<EliteAffixEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EliteAffixEntry {
+    pub affix: EliteAffix,
+    /// Affix only appears starting from this wave
+    pub min_wave: u32,
+    pub weight: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum EliteAffix {
+    /// Shield absorbs damage before health.
+    /// Value is a part of the max health
+    Shielded(f32),
+    /// Movement speed bonus, 0.5 is +50%
+    Hasted(f32),
+    /// Part of the max health restored every second
+    Regenerating(f32),
+    /// Damages the wall on death if the elite is close to it
+    Explosive { radius: f32, damage: i32 },
+    /// Spawns enemies of the type around itself
+    Summoner {
+        enemy: String,
+        number: u32,
+        interval: f32,
+    },
+}
+
+impl EliteAffix {
+    fn name(&self) -> &'static str {
+        match self {
+            EliteAffix::Shielded(_) => "Shielded",
+            EliteAffix::Hasted(_) => "Hasted",
+            EliteAffix::Regenerating(_) => "Regenerating",
+            EliteAffix::Explosive { .. } => "Explosive",
+            EliteAffix::Summoner { .. } => "Summoner",
+        }
+    }
+
+    fn tint(&self) -> Color {
+        match self {
+            EliteAffix::Shielded(_) => Color::CYAN,
+            EliteAffix::Hasted(_) => Color::YELLOW,
+            EliteAffix::Regenerating(_) => Color::LIME_GREEN,
+            EliteAffix::Explosive { .. } => Color::ORANGE_RED,
+            EliteAffix::Summoner { .. } => Color::VIOLET,
+        }
+    }
+}
+
+impl EliteDefinitions {
+    /// Chance for the enemy spawned on the wave to be elite.
+    /// Side effects of upgrades raise it further.
+    pub fn chance<S: Side>(&self, wave: u32, buffs: &EnemyBuffs<S>) -> f64 {
+        let chance = self.base_chance
+            + self.chance_per_wave * wave.saturating_sub(1) as f64
+            + buffs.elite_chance.value() as f64;
+        chance.clamp(0.0, self.max_chance.clamp(0.0, 1.0))
+    }
+
+    /// Different affixes available on the wave
+    pub fn roll(&self, wave: u32, rng: &mut impl Rng) -> Elite {
+        let mut available = self
+            .affixes
+            .iter()
+            .filter(|entry| entry.min_wave <= wave)
+            .collect::<Vec<_>>();
+        let number = rng.gen_range(1..=self.max_affixes.max(1));
+
+        let mut affixes = vec![];
+        for _ in 0..number {
+            let Ok(entry) = available.choose_weighted(rng, |entry| entry.weight) else {
+                break;
+            };
+            let affix = entry.affix.clone();
+            available.retain(|entry| entry.affix != affix);
+            affixes.push(affix);
+        }
+
+        Elite {
+            affixes,
+            health_multiplier: self.health_multiplier,
+            exp_multiplier: self.exp_multiplier,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct Elite {
+    pub affixes: Vec<EliteAffix>,
+    pub health_multiplier: f32,
+    pub exp_multiplier: f32,
+}
+
+impl Elite {
+    /// Multiplies stats of the enemy.
+    /// Used on spawn and when enemy stats are refreshed.
+    pub fn apply<S: Side>(&self, enemy: &mut Enemy<S>) {
+        enemy.health = (enemy.health as f32 * self.health_multiplier) as i32;
+        enemy.max_health = (enemy.max_health as f32 * self.health_multiplier) as i32;
+        for affix in self.affixes.iter() {
+            if let EliteAffix::Hasted(value) = affix {
+                enemy.speed *= 1.0 + value;
+            }
+        }
+    }
+
+    /// Tint of the first affix
+    pub fn tint(&self) -> Color {
+        self.affixes
+            .first()
+            .map(EliteAffix::tint)
+            .unwrap_or(Color::WHITE)
+    }
+
+    /// Affixes before the enemy name
+    pub fn name(&self, enemy: &str) -> String {
+        let mut name = self
+            .affixes
+            .iter()
+            .map(EliteAffix::name)
+            .collect::<Vec<_>>();
+        name.push(enemy);
+        name.join(" ")
+    }
+
+    /// Replaces stats of the spawned enemy
+    /// and adds components of affixes and the name tag
+    pub fn insert<S: Side>(
+        self,
+        enemy: &mut EntityCommands,
+        definition: &EnemyDefinition,
+        global_buffs: &GlobalEnemyBuffs,
+        buffs: &EnemyBuffs<S>,
+        font: Handle<Font>,
+    ) {
+        let mut stats = definition.enemy(global_buffs, buffs);
+        self.apply(&mut stats);
+        let max_health = stats.max_health;
+        enemy.insert(stats);
+
+        for affix in self.affixes.iter() {
+            match affix {
+                EliteAffix::Shielded(value) => {
+                    enemy.insert(EliteShield {
+                        health: (max_health as f32 * value) as i32,
+                    });
+                }
+                EliteAffix::Hasted(_) => {}
+                EliteAffix::Regenerating(value) => {
+                    enemy.insert(EliteRegeneration {
+                        per_second: *value,
+                        regenerated: 0.0,
+                    });
+                }
+                EliteAffix::Explosive { radius, damage } => {
+                    enemy.insert(EliteExplosion {
+                        radius: *radius,
+                        damage: *damage,
+                    });
+                }
+                EliteAffix::Summoner {
+                    enemy: summoned,
+                    number,
+                    interval,
+                } => {
+                    enemy.insert(EliteSummoner {
+                        enemy: summoned.clone(),
+                        number: *number,
+                        timer: Timer::from_seconds(*interval, TimerMode::Repeating),
+                    });
+                }
+            }
+        }
+
+        let name = self.name(&definition.name);
+        let color = self.tint();
+        enemy.insert(self).with_children(|builder| {
+            builder.spawn(Text2dBundle {
+                text: Text::from_section(
+                    name,
+                    TextStyle {
+                        font,
+                        font_size: ELITE_NAME_FONT_SIZE,
+                        color,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, definition.size * 1.5, 1.0),
+                ..default()
+            });
+        });
+    }
+}
+
+/// Absorbs damage before the enemy health
+#[derive(Debug, Clone, Copy, Component)]
+pub struct EliteShield {
+    pub health: i32,
+}
+
+impl EliteShield {
+    /// Returns damage left after the shield
+    pub fn absorb(&mut self, damage: i32) -> i32 {
+        let absorbed = self.health.min(damage).max(0);
+        self.health -= absorbed;
+        damage - absorbed
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct EliteRegeneration {
+    per_second: f32,
+    /// Part of the hp restored, but not added to the health yet
+    regenerated: f32,
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct EliteExplosion {
+    radius: f32,
+    damage: i32,
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct EliteSummoner {
+    enemy: String,
+    number: u32,
+    timer: Timer,
+}
+
+/// Sprite of the new elite gets the tint of its first affix
+fn elite_tint(mut elites: Query<(&Elite, &mut TextureAtlasSprite), Added<Elite>>) {
+    for (elite, mut sprite) in elites.iter_mut() {
+        sprite.color = elite.tint();
+    }
+}
+
+fn elite_regeneration<S: Side>(
+    time: Res<Time>,
+    mut enemies: Query<(&mut Enemy<S>, &mut EliteRegeneration)>,
+) {
+    for (mut enemy, mut regeneration) in enemies.iter_mut() {
+        if enemy.health <= 0 || enemy.max_health <= enemy.health {
+            continue;
+        }
+        regeneration.regenerated +=
+            enemy.max_health as f32 * regeneration.per_second * time.delta_seconds();
+        let restored = regeneration.regenerated.floor();
+        regeneration.regenerated -= restored;
+        enemy.health = (enemy.health + restored as i32).min(enemy.max_health);
+    }
+}
+
+/// Summoned enemies are never elite
+fn elite_summon<S: Side>(
+    time: Res<Time>,
+    enemy_assets: Res<EnemyAssets>,
+    enemy_definitions: Res<Assets<EnemyDefinitions>>,
+    enemy_sprites: Res<EnemySprites>,
+    global_buffs: Res<GlobalEnemyBuffs>,
+    buffs: Res<EnemyBuffs<S>>,
+    freeze: Res<EnemyFreeze<S>>,
+    mut commands: Commands,
+    mut summoners: Query<(&Transform, &mut EliteSummoner)>,
+) {
+    if freeze.is_frozen() {
+        return;
+    }
+    let Some(definitions) = enemy_definitions.get(&enemy_assets.definitions) else {
+        return;
+    };
+
+    for (transform, mut summoner) in summoners.iter_mut() {
+        if !summoner.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let Some(definition) = definitions.get(&summoner.enemy) else {
+            warn!("Summoned enemy {} is not defined", summoner.enemy);
+            continue;
+        };
+        let texture_atlas = enemy_sprites
+            .atlases
+            .get(&definition.name)
+            .cloned()
+            .unwrap_or_default();
+
+        for n in 0..summoner.number {
+            let position = transform.translation
+                + Quat::from_rotation_z(
+                    2.0 * std::f32::consts::PI / summoner.number as f32 * n as f32,
+                )
+                .mul_vec3(Vec3::Y * definition.size * 2.0);
+            spawn_enemy(
+                &mut commands,
+                definition,
+                texture_atlas.clone(),
+                position,
+                &global_buffs,
+                &buffs,
+            );
+        }
+    }
+}
+
+fn elite_explosion<S: Side>(
+    wall: Query<(&Transform, &CastleWall<S>)>,
+    enemies: Query<(&Transform, &Enemy<S>, &EliteExplosion)>,
+    mut damage_events: EventWriter<WallDamageEvent<S>>,
+) {
+    let (wall_transform, wall) = wall.single();
+    for (transform, enemy, explosion) in enemies.iter() {
+        if 0 < enemy.health {
+            continue;
+        }
+        if wall.distance(wall_transform, transform.translation) <= explosion.radius {
+            damage_events.send(WallDamageEvent::new(explosion.damage));
+        }
+    }
+}
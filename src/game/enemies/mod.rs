@@ -1,21 +1,33 @@
-use std::{fmt::Display, marker::PhantomData};
+use std::marker::PhantomData;
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_asset_loader::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{utils::remove_all_with, GlobalState};
 
-use self::spawn::EnemyBuffs;
+use self::{
+    definitions::{EnemyDefinition, EnemyDefinitions},
+    elites::{Elite, EliteDefinitions},
+    siege::SiegeAttack,
+    spawn::EnemyBuffs,
+    waves::WaveSchedule,
+};
 
 use super::{
     animation::AnimationBundle,
     castle::{Castle, CastleWall},
-    damage::WallDamageEvent,
+    damage::{projectile::WallProjectileBundle, WallDamageEvent},
+    data::{data_modified, RonAssetLoader},
+    stats::Stat,
     East, GameState, North, Side, South, West,
 };
 
+pub mod definitions;
+pub mod elites;
+pub mod siege;
 pub mod spawn;
+pub mod waves;
 
 /// Needed to make enemies move.
 /// Otherwise we would need set enormous speeds.
@@ -24,16 +36,29 @@ const ENEMY_FORCE_MULTIPLIER: f32 = 300.0;
 #[cfg(not(target_family = "wasm"))]
 const ENEMY_FORCE_MULTIPLIER: f32 = 1000.0;
 
+/// Attack speed debuffs can not stop enemy attacks
+const MIN_ENEMY_ATTACK_SPEED: f32 = 0.05;
+
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_state::<SpawnState>()
-            .add_collection_to_loading_state::<_, EnemySprites>(GlobalState::AssetLoading)
-            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+        app.add_asset::<EnemyDefinitions>()
+            .add_asset_loader(RonAssetLoader::<EnemyDefinitions>::new(&["enemies.ron"]))
+            .add_asset::<WaveSchedule>()
+            .add_asset_loader(RonAssetLoader::<WaveSchedule>::new(&["waves.ron"]))
+            .add_asset::<EliteDefinitions>()
+            .add_asset_loader(RonAssetLoader::<EliteDefinitions>::new(&["elites.ron"]))
+            .add_collection_to_loading_state::<_, EnemyAssets>(GlobalState::AssetLoading)
+            .init_resource::<EnemySprites>()
+            // no sprites in headless mode
+            .add_system(
+                setup_sprites
+                    .run_if(resource_exists::<Assets<TextureAtlas>>())
+                    .in_schedule(OnEnter(GlobalState::Initialization)),
+            )
             .add_systems(
                 (
-                    enemy_spawn_state_progress,
                     enemy_movement::<North>,
                     enemy_movement::<South>,
                     enemy_movement::<West>,
@@ -49,7 +74,29 @@ impl Plugin for EnemyPlugin {
                 )
                     .in_set(OnUpdate(GameState::InGame)),
             )
+            .add_systems(
+                (
+                    enemy_unfreeze::<North>,
+                    enemy_unfreeze::<South>,
+                    enemy_unfreeze::<West>,
+                    enemy_unfreeze::<East>,
+                )
+                    .in_set(OnUpdate(GameState::InGame)),
+            )
+            // data can change while the game is paused
+            .add_systems(
+                (
+                    reload_enemies::<North>,
+                    reload_enemies::<South>,
+                    reload_enemies::<West>,
+                    reload_enemies::<East>,
+                )
+                    .in_set(OnUpdate(GlobalState::InGame)),
+            )
             .add_system(remove_all_with::<EnemyMarker>.in_schedule(OnExit(GlobalState::InGame)))
+            .add_plugin(waves::WavesPlugin)
+            .add_plugin(elites::ElitesPlugin)
+            .add_plugin(siege::SiegePlugin)
             .add_plugin(spawn::SpawnPlugin::<North>::default())
             .add_plugin(spawn::SpawnPlugin::<South>::default())
             .add_plugin(spawn::SpawnPlugin::<West>::default())
@@ -57,106 +104,42 @@ impl Plugin for EnemyPlugin {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, States)]
-pub enum SpawnState {
-    #[default]
-    // Bats and Gablin
-    // 1 min
-    Stage1,
-    // + Skull and Spear goblin
-    // 2 min
-    Stage2,
-    // + Huge ivy
-    // 3 min
-    Stage3,
-    // + Boss crab
-    Stage4,
+#[derive(AssetCollection, Resource, Default)]
+pub struct EnemyAssets {
+    #[asset(path = "data/game.enemies.ron")]
+    pub definitions: Handle<EnemyDefinitions>,
+    #[asset(path = "data/game.waves.ron")]
+    pub waves: Handle<WaveSchedule>,
+    #[asset(path = "data/game.elites.ron")]
+    pub elites: Handle<EliteDefinitions>,
 }
 
-impl Display for SpawnState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SpawnState::Stage1 => f.write_str("1"),
-            SpawnState::Stage2 => f.write_str("2"),
-            SpawnState::Stage3 => f.write_str("3"),
-            SpawnState::Stage4 => f.write_str("Final"),
-        }
-    }
-}
-
-#[derive(Debug, Resource)]
-pub struct SpawnStateTimer {
-    pub current_state: SpawnState,
-    pub timer: Option<Timer>,
-}
-
-impl Default for SpawnStateTimer {
-    fn default() -> Self {
-        Self {
-            current_state: Default::default(),
-            timer: Some(Timer::from_seconds(Self::FIST_STAGE, TimerMode::Once)),
-        }
-    }
-}
-
-impl SpawnStateTimer {
-    const FIST_STAGE: f32 = 60.0;
-    const SECOND_STAGE: f32 = 60.0 * 2.0;
-    const THIRD_STAGE: f32 = 60.0 * 2.0;
-
-    fn next_state(&mut self) {
-        match self.current_state {
-            SpawnState::Stage1 => {
-                self.current_state = SpawnState::Stage2;
-                self.timer = Some(Timer::from_seconds(Self::SECOND_STAGE, TimerMode::Once));
-            }
-            SpawnState::Stage2 => {
-                self.current_state = SpawnState::Stage3;
-                self.timer = Some(Timer::from_seconds(Self::THIRD_STAGE, TimerMode::Once));
-            }
-            SpawnState::Stage3 => {
-                self.current_state = SpawnState::Stage4;
-                self.timer = None;
-            }
-            _ => {}
-        }
-    }
+impl EnemyAssets {
+    /// Used to read data when asset loading is skipped
+    pub const DEFINITIONS_PATH: &str = "data/game.enemies.ron";
+    pub const WAVES_PATH: &str = "data/game.waves.ron";
+    pub const ELITES_PATH: &str = "data/game.elites.ron";
 }
 
-#[derive(AssetCollection, Resource)]
+/// Sprite sheets for each enemy from `EnemyDefinitions`
+#[derive(Debug, Default, Resource)]
 pub struct EnemySprites {
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1,))]
-    #[asset(path = "sprites/mad_crab.png")]
-    pub mad_crab: Handle<TextureAtlas>,
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1,))]
-    #[asset(path = "sprites/goblin.png")]
-    pub goblin: Handle<TextureAtlas>,
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1,))]
-    #[asset(path = "sprites/spear_goblin.png")]
-    pub spear_goblin: Handle<TextureAtlas>,
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1,))]
-    #[asset(path = "sprites/bat.png")]
-    pub bat: Handle<TextureAtlas>,
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1,))]
-    #[asset(path = "sprites/skull.png")]
-    pub skull: Handle<TextureAtlas>,
-    #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 4, rows = 1,))]
-    #[asset(path = "sprites/poison_ivy.png")]
-    pub poison_ivy: Handle<TextureAtlas>,
+    pub atlases: HashMap<String, Handle<TextureAtlas>>,
 }
 
-#[derive(Debug, Default, Resource)]
+#[derive(Debug, Default, Clone, Resource)]
 pub struct GlobalEnemyBuffs {
-    pub health: f32,
-    pub speed: f32,
-    pub exp: f32,
-    pub damage: f32,
-    pub attack_speed: f32,
+    pub health: Stat,
+    pub speed: Stat,
+    pub exp: Stat,
+    pub damage: Stat,
+    pub attack_speed: Stat,
 }
 
 #[derive(Debug, Default, Component)]
 pub struct Enemy<S: Side> {
     pub health: i32,
+    pub max_health: i32,
     pub speed: f32,
     pub exp: u32,
     _phantom: PhantomData<S>,
@@ -166,13 +149,26 @@ impl<S: Side> Enemy<S> {
     pub fn new(health: i32, speed: f32, exp: u32) -> Self {
         Self {
             health,
+            max_health: health,
             speed,
             exp,
             _phantom: PhantomData,
         }
     }
+
+    /// Takes new stats, but keeps damage taken
+    fn refresh(&mut self, enemy: Enemy<S>) {
+        self.health += enemy.max_health - self.max_health;
+        self.max_health = enemy.max_health;
+        self.speed = enemy.speed;
+        self.exp = enemy.exp;
+    }
 }
 
+/// Name of the definition enemy was spawned from
+#[derive(Debug, Default, Clone, Component)]
+pub struct EnemyType(pub String);
+
 #[derive(Debug, Default, Component)]
 pub struct EnemyAttack<S: Side> {
     damage: i32,
@@ -182,10 +178,14 @@ pub struct EnemyAttack<S: Side> {
 }
 
 impl<S: Side> EnemyAttack<S> {
+    /// Attack speed is number of attacks per second
     pub fn new(damage: i32, range: f32, attack_speed: f32) -> Self {
         // initially timer is paused
         // unpause when in attack range
-        let mut attack_timer = Timer::from_seconds(attack_speed, TimerMode::Repeating);
+        let mut attack_timer = Timer::from_seconds(
+            1.0 / attack_speed.max(MIN_ENEMY_ATTACK_SPEED),
+            TimerMode::Repeating,
+        );
         attack_timer.pause();
 
         Self {
@@ -195,13 +195,45 @@ impl<S: Side> EnemyAttack<S> {
             _phantom: PhantomData,
         }
     }
+
+    /// Takes new stats, but keeps attack progress
+    fn refresh(&mut self, attack: EnemyAttack<S>) {
+        self.damage = attack.damage;
+        self.range = attack.range;
+        self.attack_timer
+            .set_duration(attack.attack_timer.duration());
+    }
+}
+
+/// Enemy attacks the wall with missiles instead of hitting it
+#[derive(Debug, Clone, Copy, Component)]
+pub struct EnemyRanged {
+    projectile_speed: f32,
+    projectile_size: f32,
+}
+
+/// While frozen enemies on the side can not move or attack
+#[derive(Debug, Default, Resource)]
+pub struct EnemyFreeze<S: Side> {
+    timer: Option<Timer>,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> EnemyFreeze<S> {
+    pub fn freeze(&mut self, duration: f32) {
+        self.timer = Some(Timer::from_seconds(duration, TimerMode::Once));
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.timer.is_some()
+    }
 }
 
 #[derive(Debug, Default, Component)]
 pub struct EnemyMarker;
 
 #[derive(Bundle)]
-pub struct EnemyBundle<S: Side, E: EnemyType<S>> {
+pub struct EnemyBundle<S: Side> {
     #[bundle]
     animation_bundle: AnimationBundle,
     rigid_body: RigidBody,
@@ -210,14 +242,13 @@ pub struct EnemyBundle<S: Side, E: EnemyType<S>> {
     velocity: Velocity,
     damping: Damping,
     enemy: Enemy<S>,
-    attack: EnemyAttack<S>,
-    enemy_type: E,
+    enemy_type: EnemyType,
     marker: EnemyMarker,
 }
 
-impl<S: Side, E: EnemyType<S>> EnemyBundle<S, E> {
+impl<S: Side> EnemyBundle<S> {
     fn new(
-        size: f32,
+        definition: &EnemyDefinition,
         texture_atlas: Handle<TextureAtlas>,
         position: Vec3,
         global_buffs: &GlobalEnemyBuffs,
@@ -225,217 +256,120 @@ impl<S: Side, E: EnemyType<S>> EnemyBundle<S, E> {
     ) -> Self {
         Self {
             // Double side for sprites to better correlate with collider size
-            animation_bundle: AnimationBundle::new(texture_atlas, size * 2.0, 3, 5.0, position),
+            animation_bundle: AnimationBundle::new(
+                texture_atlas,
+                definition.size * 2.0,
+                definition.frames() - 1,
+                definition.fps,
+                position,
+            ),
             rigid_body: RigidBody::Dynamic,
-            collider: Collider::ball(size * 0.5),
+            collider: Collider::ball(definition.size * 0.5),
             locked_axis: LockedAxes::ROTATION_LOCKED,
             velocity: Velocity::default(),
             damping: Damping {
                 linear_damping: 5.0,
                 angular_damping: 10.0,
             },
-            enemy: E::enemy(global_buffs, buffs),
-            attack: E::attack(global_buffs, buffs),
-            enemy_type: E::default(),
+            enemy: definition.enemy(global_buffs, buffs),
+            enemy_type: EnemyType(definition.name.clone()),
             marker: EnemyMarker,
         }
     }
 }
 
-pub trait EnemyType<S: Side>: Component + Default {
-    const HEALTH: i32;
-    const SPEED: f32;
-    const EXP: u32;
-    const DAMAGE: i32;
-    const SIZE: f32;
-    // Range should be bigger then enemy size / 2
-    const RANGE: f32;
-    const ATTACK_SPEED: f32;
-    const NUMBER_PER_SPAWN: u32;
-
-    fn enemy(global_buffs: &GlobalEnemyBuffs, buffs: &EnemyBuffs<S>) -> Enemy<S> {
-        Enemy::new(
-            (Self::HEALTH as f32 * (1.0 + global_buffs.health + buffs.health)) as i32,
-            Self::SPEED * (1.0 + global_buffs.speed + buffs.speed),
-            (Self::EXP as f32 * (1.0 - (global_buffs.exp + buffs.exp))) as u32,
-        )
-    }
-
-    fn attack(global_buffs: &GlobalEnemyBuffs, buffs: &EnemyBuffs<S>) -> EnemyAttack<S> {
-        EnemyAttack::new(
-            (Self::DAMAGE as f32 * (1.0 + global_buffs.damage + buffs.damage)) as i32,
-            Self::RANGE,
-            Self::ATTACK_SPEED * (1.0 + global_buffs.attack_speed + buffs.attack_speed),
-        )
-    }
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas>;
-}
-
-#[derive(Debug, Default, Component)]
-pub struct MadCrab;
-
-impl<S: Side> EnemyType<S> for MadCrab {
-    const HEALTH: i32 = 1000;
-    const SPEED: f32 = 8.0;
-    const EXP: u32 = 200;
-    const DAMAGE: i32 = 100;
-    const SIZE: f32 = 128.0;
-    const RANGE: f32 = 200.0;
-    const ATTACK_SPEED: f32 = 1.1;
-    const NUMBER_PER_SPAWN: u32 = 3;
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas> {
-        enemy_sprites.mad_crab.clone()
-    }
-}
-
-#[derive(Debug, Default, Component)]
-pub struct Goblin;
-
-impl<S: Side> EnemyType<S> for Goblin {
-    const HEALTH: i32 = 80;
-    const SPEED: f32 = 15.0;
-    const EXP: u32 = 5;
-    const DAMAGE: i32 = 10;
-    const SIZE: f32 = 16.0;
-    const RANGE: f32 = 20.0;
-    const ATTACK_SPEED: f32 = 1.0;
-    const NUMBER_PER_SPAWN: u32 = 3;
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas> {
-        enemy_sprites.goblin.clone()
-    }
-}
-
-#[derive(Debug, Default, Component)]
-pub struct SpearGoblin;
-
-impl<S: Side> EnemyType<S> for SpearGoblin {
-    const HEALTH: i32 = 100;
-    const SPEED: f32 = 10.0;
-    const EXP: u32 = 8;
-    const DAMAGE: i32 = 15;
-    const SIZE: f32 = 16.0;
-    const RANGE: f32 = 20.0;
-    const ATTACK_SPEED: f32 = 1.2;
-    const NUMBER_PER_SPAWN: u32 = 2;
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas> {
-        enemy_sprites.spear_goblin.clone()
-    }
-}
-
-#[derive(Debug, Default, Component)]
-pub struct Bat;
-
-impl<S: Side> EnemyType<S> for Bat {
-    const HEALTH: i32 = 30;
-    const SPEED: f32 = 10.0;
-    const EXP: u32 = 5;
-    const DAMAGE: i32 = 5;
-    const SIZE: f32 = 16.0;
-    const RANGE: f32 = 20.0;
-    const ATTACK_SPEED: f32 = 1.5;
-    const NUMBER_PER_SPAWN: u32 = 5;
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas> {
-        enemy_sprites.bat.clone()
-    }
-}
-
-#[derive(Debug, Default, Component)]
-pub struct Skull;
-
-impl<S: Side> EnemyType<S> for Skull {
-    const HEALTH: i32 = 120;
-    const SPEED: f32 = 8.0;
-    const EXP: u32 = 50;
-    const DAMAGE: i32 = 15;
-    const SIZE: f32 = 32.0;
-    const RANGE: f32 = 40.0;
-    const ATTACK_SPEED: f32 = 1.0;
-    const NUMBER_PER_SPAWN: u32 = 1;
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas> {
-        enemy_sprites.skull.clone()
-    }
-}
-
-#[derive(Debug, Default, Component)]
-pub struct PoisonIvy;
-
-impl<S: Side> EnemyType<S> for PoisonIvy {
-    const HEALTH: i32 = 250;
-    const SPEED: f32 = 12.0;
-    const EXP: u32 = 100;
-    const DAMAGE: i32 = 20;
-    const SIZE: f32 = 64.0;
-    const RANGE: f32 = 70.0;
-    const ATTACK_SPEED: f32 = 1.0;
-    const NUMBER_PER_SPAWN: u32 = 2;
-
-    fn texture_atlas(enemy_sprites: &EnemySprites) -> Handle<TextureAtlas> {
-        enemy_sprites.poison_ivy.clone()
-    }
-}
-
-fn setup(mut spawn_state: ResMut<NextState<SpawnState>>, mut commands: Commands) {
-    spawn_state.set(SpawnState::Stage1);
-    commands.insert_resource(GlobalEnemyBuffs::default());
-    commands.insert_resource(SpawnStateTimer::default());
-}
-
-fn enemy_spawn_state_progress(
-    time: Res<Time>,
-    mut spawn_state_timer: ResMut<SpawnStateTimer>,
-    mut enemy_state: ResMut<NextState<SpawnState>>,
+/// Creates sprite sheets for all enemies
+fn setup_sprites(
+    asset_server: Res<AssetServer>,
+    enemy_assets: Res<EnemyAssets>,
+    enemy_definitions: Res<Assets<EnemyDefinitions>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut enemy_sprites: ResMut<EnemySprites>,
 ) {
-    if let Some(timer) = &mut spawn_state_timer.timer {
-        if timer.tick(time.delta()).finished() {
-            spawn_state_timer.next_state();
-            enemy_state.set(spawn_state_timer.current_state);
-        }
+    let Some(definitions) = enemy_definitions.get(&enemy_assets.definitions) else {
+        return;
+    };
+    for definition in definitions.enemies.iter() {
+        let texture_atlas = TextureAtlas::from_grid(
+            asset_server.load(definition.sprite_sheet.as_str()),
+            Vec2::splat(definition.tile_size),
+            definition.frames(),
+            1,
+            None,
+            None,
+        );
+        enemy_sprites
+            .atlases
+            .insert(definition.name.clone(), texture_atlases.add(texture_atlas));
     }
 }
 
 /// Moved enemies in direction of the wall
-/// Keeps them pointed at the wall
+/// Keeps them pointed at the wall.
+/// Ranged enemies stop once the wall is in range.
+/// Siege enemies have their own movement.
 fn enemy_movement<S: Side>(
     time: Res<Time>,
-    wall: Query<&Transform, With<CastleWall<S>>>,
-    mut enemies: Query<(&Transform, &Enemy<S>, &mut Velocity)>,
+    wall: Query<(&Transform, &CastleWall<S>)>,
+    freeze: Res<EnemyFreeze<S>>,
+    mut enemies: Query<
+        (
+            &Transform,
+            &Enemy<S>,
+            &mut Velocity,
+            Option<(&EnemyAttack<S>, &EnemyRanged)>,
+        ),
+        Without<SiegeAttack<S>>,
+    >,
 ) {
-    let wall_transform = wall.single();
+    let (wall_transform, wall) = wall.single();
 
-    for (enemy_transform, enemy, mut enemy_velocity) in enemies.iter_mut() {
-        let vector = (wall_transform.translation - enemy_transform.translation).truncate();
-        let direction = vector.normalize();
+    for (enemy_transform, enemy, mut enemy_velocity, ranged) in enemies.iter_mut() {
+        if freeze.is_frozen() {
+            enemy_velocity.linvel = Vec2::ZERO;
+            continue;
+        }
 
-        let movement = direction * time.delta().as_secs_f32();
-        enemy_velocity.linvel = movement * enemy.speed * ENEMY_FORCE_MULTIPLIER;
+        if let Some((attack, _)) = ranged {
+            if wall.distance(wall_transform, enemy_transform.translation) <= attack.range {
+                enemy_velocity.linvel = Vec2::ZERO;
+                continue;
+            }
+        }
+
+        enemy_velocity.linvel = wall_velocity(
+            &time,
+            wall_transform.translation,
+            enemy_transform.translation,
+            enemy.speed,
+        );
     }
 }
 
+/// Velocity of the enemy moving straight to the wall
+fn wall_velocity(time: &Time, wall_position: Vec3, enemy_position: Vec3, speed: f32) -> Vec2 {
+    let direction = (wall_position - enemy_position).truncate().normalize();
+    let movement = direction * time.delta().as_secs_f32();
+    movement * speed * ENEMY_FORCE_MULTIPLIER
+}
+
+/// Ranged enemies shoot missiles, others damage the wall directly
 fn enemy_attack<S: Side>(
     time: Res<Time>,
     wall: Query<(&Transform, &CastleWall<S>)>,
-    mut enemies: Query<(&Transform, &mut EnemyAttack<S>)>,
+    freeze: Res<EnemyFreeze<S>>,
+    mut commands: Commands,
+    mut enemies: Query<(&Transform, &mut EnemyAttack<S>, Option<&EnemyRanged>)>,
     mut damage_events: EventWriter<WallDamageEvent<S>>,
 ) {
+    if freeze.is_frozen() {
+        return;
+    }
+
     let (wall_transform, wall) = wall.single();
 
-    for (enemy_transform, mut enemy_attack) in enemies.iter_mut() {
-        let distance = (wall_transform
-            .translation
-            .truncate()
-            .dot(S::DIRECTION.abs())
-            - enemy_transform
-                .translation
-                .truncate()
-                .dot(S::DIRECTION.abs()))
-        .abs()
-            - wall.half_thickness;
+    for (enemy_transform, mut enemy_attack, ranged) in enemies.iter_mut() {
+        let distance = wall.distance(wall_transform, enemy_transform.translation);
 
         if enemy_attack.range < distance {
             continue;
@@ -447,20 +381,103 @@ fn enemy_attack<S: Side>(
             continue;
         }
 
-        damage_events.send(WallDamageEvent::new(enemy_attack.damage));
+        match ranged {
+            Some(ranged) => {
+                commands.spawn(WallProjectileBundle::<S>::new(
+                    ranged.projectile_size,
+                    enemy_attack.damage,
+                    ranged.projectile_speed,
+                    -S::DIRECTION,
+                    Transform::from_translation(enemy_transform.translation),
+                ));
+            }
+            None => damage_events.send(WallDamageEvent::new(enemy_attack.damage)),
+        }
     }
 }
 
+/// Elites give bonus exp
 fn enemy_death<S: Side>(
-    enemies: Query<(Entity, &Enemy<S>)>,
+    enemies: Query<(Entity, &Enemy<S>, Option<&Elite>)>,
     mut commands: Commands,
     mut castle: Query<&mut Castle>,
 ) {
     let mut castle = castle.single_mut();
-    for (enemy_entity, enemy) in enemies.iter() {
+    for (enemy_entity, enemy, elite) in enemies.iter() {
         if enemy.health <= 0 {
-            castle.exp += enemy.exp;
-            commands.entity(enemy_entity).despawn();
+            castle.exp += match elite {
+                Some(elite) => (enemy.exp as f32 * elite.exp_multiplier) as u32,
+                None => enemy.exp,
+            };
+            castle.kills += 1;
+            // elites have name tags
+            commands.entity(enemy_entity).despawn_recursive();
+        }
+    }
+}
+
+fn enemy_unfreeze<S: Side>(time: Res<Time>, mut freeze: ResMut<EnemyFreeze<S>>) {
+    if let Some(timer) = &mut freeze.timer {
+        if timer.tick(time.delta()).finished() {
+            freeze.timer = None;
+        }
+    }
+}
+
+/// Applies changes of enemy data to the enemies on the field
+fn reload_enemies<S: Side>(
+    enemy_assets: Res<EnemyAssets>,
+    enemy_definitions: Res<Assets<EnemyDefinitions>>,
+    global_buffs: Res<GlobalEnemyBuffs>,
+    buffs: Res<EnemyBuffs<S>>,
+    mut definition_events: EventReader<AssetEvent<EnemyDefinitions>>,
+    mut enemies: RefreshedEnemies<S>,
+) {
+    if !data_modified(&mut definition_events, &enemy_assets.definitions) {
+        return;
+    }
+    let Some(definitions) = enemy_definitions.get(&enemy_assets.definitions) else {
+        warn!("Enemy definitions are not loaded");
+        return;
+    };
+
+    refresh_enemies(definitions, &global_buffs, &buffs, &mut enemies);
+}
+
+/// Enemies on the field with stats from the definitions
+pub type RefreshedEnemies<'w, 's, S> = Query<
+    'w,
+    's,
+    (
+        &'static EnemyType,
+        &'static mut Enemy<S>,
+        Option<&'static mut EnemyAttack<S>>,
+        Option<&'static Elite>,
+        Option<&'static mut SiegeAttack<S>>,
+    ),
+>;
+
+/// Recalculates stats of the enemies on the field with current buffs
+pub fn refresh_enemies<S: Side>(
+    definitions: &EnemyDefinitions,
+    global_buffs: &GlobalEnemyBuffs,
+    buffs: &EnemyBuffs<S>,
+    enemies: &mut RefreshedEnemies<S>,
+) {
+    for (enemy_type, mut enemy, attack, elite, siege) in enemies.iter_mut() {
+        let Some(definition) = definitions.get(&enemy_type.0) else {
+            continue;
+        };
+        let mut refreshed = definition.enemy(global_buffs, buffs);
+        if let Some(elite) = elite {
+            elite.apply(&mut refreshed);
+        }
+        enemy.refresh(refreshed);
+        if let Some(mut attack) = attack {
+            attack.refresh(definition.attack(global_buffs, buffs));
+        }
+        if let Some(mut siege) = siege {
+            siege.refresh(definition.attack(global_buffs, buffs));
         }
     }
 }
@@ -0,0 +1,221 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::game::{
+    castle::CastleWall,
+    damage::{projectile::WallProjectileBundle, WallDamageEvent},
+    East, GameState, North, South, West,
+};
+
+use super::{elites::Elite, wall_velocity, Enemy, EnemyAttack, EnemyFreeze, Side};
+
+/// Siege enemies are not pushed by bolts or other enemies
+pub const SIEGE_DOMINANCE: i8 = 10;
+
+pub struct SiegePlugin;
+
+impl Plugin for SiegePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                siege_tint::<North>,
+                siege_tint::<South>,
+                siege_tint::<West>,
+                siege_tint::<East>,
+                siege_movement::<North>,
+                siege_movement::<South>,
+                siege_movement::<West>,
+                siege_movement::<East>,
+                siege_attack::<North>,
+                siege_attack::<South>,
+                siege_attack::<West>,
+                siege_attack::<East>,
+            )
+                .in_set(OnUpdate(GameState::InGame)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SiegeKind {
+    /// Waits for the reload at the charge range, charges the wall,
+    /// hits it and backs off for the next charge
+    Ram {
+        charge_range: f32,
+        /// Speed multiplier while charging
+        charge_speed: f32,
+    },
+    /// Stops at the range and lobs missiles at the wall
+    Catapult {
+        projectile_speed: f32,
+        projectile_size: f32,
+    },
+}
+
+impl SiegeKind {
+    /// Siege enemies reuse sprites of other enemies
+    fn tint(&self) -> Color {
+        match self {
+            SiegeKind::Ram { .. } => Color::rgb(0.8, 0.55, 0.3),
+            SiegeKind::Catapult { .. } => Color::rgb(0.55, 0.55, 0.65),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum RamState {
+    #[default]
+    Approach,
+    Charge,
+    Retreat,
+}
+
+/// Used by siege enemies instead of `EnemyAttack`
+#[derive(Debug, Component)]
+pub struct SiegeAttack<S: Side> {
+    kind: SiegeKind,
+    damage: i32,
+    range: f32,
+    /// Time between charges or shots
+    reload_timer: Timer,
+    ram_state: RamState,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> SiegeAttack<S> {
+    /// Takes stats of the attack from the enemy definition
+    pub fn new(kind: SiegeKind, attack: EnemyAttack<S>) -> Self {
+        // stays finished until the next charge or shot
+        let mut reload_timer = attack.attack_timer;
+        reload_timer.set_mode(TimerMode::Once);
+        reload_timer.unpause();
+        Self {
+            kind,
+            damage: attack.damage,
+            range: attack.range,
+            reload_timer,
+            ram_state: RamState::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Takes new stats, but keeps reload progress
+    pub fn refresh(&mut self, attack: EnemyAttack<S>) {
+        self.damage = attack.damage;
+        self.range = attack.range;
+        self.reload_timer
+            .set_duration(attack.attack_timer.duration());
+    }
+}
+
+/// Elites keep the tint of their affix
+fn siege_tint<S: Side>(
+    mut enemies: Query<
+        (&SiegeAttack<S>, &mut TextureAtlasSprite),
+        (Added<SiegeAttack<S>>, Without<Elite>),
+    >,
+) {
+    for (siege, mut sprite) in enemies.iter_mut() {
+        sprite.color = siege.kind.tint();
+    }
+}
+
+/// Moves siege enemies instead of `enemy_movement`
+fn siege_movement<S: Side>(
+    time: Res<Time>,
+    wall: Query<(&Transform, &CastleWall<S>)>,
+    freeze: Res<EnemyFreeze<S>>,
+    mut enemies: Query<(&Transform, &Enemy<S>, &mut Velocity, &SiegeAttack<S>)>,
+) {
+    let (wall_transform, wall) = wall.single();
+
+    for (transform, enemy, mut velocity, siege) in enemies.iter_mut() {
+        if freeze.is_frozen() {
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        }
+
+        let distance = wall.distance(wall_transform, transform.translation);
+        let to_wall = wall_velocity(
+            &time,
+            wall_transform.translation,
+            transform.translation,
+            enemy.speed,
+        );
+        velocity.linvel = match siege.kind {
+            SiegeKind::Ram {
+                charge_range,
+                charge_speed,
+            } => match siege.ram_state {
+                RamState::Approach if distance <= charge_range => Vec2::ZERO,
+                RamState::Approach => to_wall,
+                RamState::Charge => to_wall * charge_speed,
+                RamState::Retreat => -to_wall,
+            },
+            SiegeKind::Catapult { .. } if distance <= siege.range => Vec2::ZERO,
+            SiegeKind::Catapult { .. } => to_wall,
+        };
+    }
+}
+
+/// Rams hit the wall at the end of the charge.
+/// Catapults shoot from the range.
+fn siege_attack<S: Side>(
+    time: Res<Time>,
+    wall: Query<(&Transform, &CastleWall<S>)>,
+    freeze: Res<EnemyFreeze<S>>,
+    mut commands: Commands,
+    mut enemies: Query<(&Transform, &mut SiegeAttack<S>)>,
+    mut damage_events: EventWriter<WallDamageEvent<S>>,
+) {
+    if freeze.is_frozen() {
+        return;
+    }
+
+    let (wall_transform, wall) = wall.single();
+
+    for (transform, mut siege) in enemies.iter_mut() {
+        let distance = wall.distance(wall_transform, transform.translation);
+        siege.reload_timer.tick(time.delta());
+
+        match siege.kind {
+            SiegeKind::Ram { charge_range, .. } => match siege.ram_state {
+                RamState::Approach => {
+                    if distance <= charge_range && siege.reload_timer.finished() {
+                        siege.ram_state = RamState::Charge;
+                    }
+                }
+                RamState::Retreat => {
+                    if charge_range <= distance {
+                        siege.ram_state = RamState::Approach;
+                    }
+                }
+                RamState::Charge => {
+                    if distance <= siege.range {
+                        damage_events.send(WallDamageEvent::new(siege.damage));
+                        siege.ram_state = RamState::Retreat;
+                        siege.reload_timer.reset();
+                    }
+                }
+            },
+            SiegeKind::Catapult {
+                projectile_speed,
+                projectile_size,
+            } => {
+                if siege.range < distance || !siege.reload_timer.finished() {
+                    continue;
+                }
+                siege.reload_timer.reset();
+                commands.spawn(WallProjectileBundle::<S>::new(
+                    projectile_size,
+                    siege.damage,
+                    projectile_speed,
+                    -S::DIRECTION,
+                    Transform::from_translation(transform.translation),
+                ));
+            }
+        }
+    }
+}
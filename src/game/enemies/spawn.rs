@@ -1,19 +1,31 @@
 use std::marker::PhantomData;
 
-use bevy::prelude::*;
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy_rapier2d::prelude::Dominance;
 use rand::prelude::*;
 
-use crate::{game::GameState, utils::remove_all_with, GlobalState};
+use crate::{
+    game::{
+        rng::SideRng,
+        stats::{Stat, StatSource},
+        GameState,
+    },
+    utils::remove_all_with,
+    GameAssets, GlobalState,
+};
 
 use super::{
-    Bat, EnemyBundle, EnemyMarker, EnemySprites, EnemyType, GlobalEnemyBuffs, Goblin, MadCrab,
-    PoisonIvy, Side, Skull, SpawnState, SpearGoblin,
+    definitions::{EnemyBehaviour, EnemyDefinition, EnemyDefinitions},
+    elites::EliteDefinitions,
+    siege::{SiegeAttack, SiegeKind, SIEGE_DOMINANCE},
+    waves::{WavePhaseEvent, WaveProgress, WaveSchedule, WaveSpawn},
+    EnemyAssets, EnemyBundle, EnemyFreeze, EnemyMarker, EnemyRanged, EnemySprites,
+    GlobalEnemyBuffs, Side,
 };
 
 const DEFAULT_ENEMY_SPAWN_POSITON: f32 = 1000.0;
 
 const DEFAULT_ENEMY_SPAWN_RADIUS: f32 = 200.0;
-const DEFAULT_ENEMY_SPAWN_RATE: f32 = 10.0;
 
 #[derive(Default)]
 pub struct SpawnPlugin<S: Side> {
@@ -23,47 +35,20 @@ pub struct SpawnPlugin<S: Side> {
 impl<S: Side> Plugin for SpawnPlugin<S> {
     fn build(&self, app: &mut App) {
         app.add_system(setup::<S>.in_schedule(OnEnter(GlobalState::InGame)))
-            .add_systems(
-                (enemy_spawn::<S, Bat>, enemy_spawn::<S, Goblin>)
-                    .in_set(OnUpdate(SpawnState::Stage1))
-                    .in_set(OnUpdate(GameState::InGame)),
-            )
-            .add_systems(
-                (
-                    enemy_spawn::<S, Bat>,
-                    enemy_spawn::<S, Goblin>,
-                    enemy_spawn::<S, SpearGoblin>,
-                    enemy_spawn::<S, Skull>,
-                )
-                    .in_set(OnUpdate(SpawnState::Stage2))
-                    .in_set(OnUpdate(GameState::InGame)),
-            )
-            .add_systems(
-                (
-                    enemy_spawn::<S, Bat>,
-                    enemy_spawn::<S, Goblin>,
-                    enemy_spawn::<S, SpearGoblin>,
-                    enemy_spawn::<S, Skull>,
-                    enemy_spawn::<S, PoisonIvy>,
-                )
-                    .in_set(OnUpdate(SpawnState::Stage3))
-                    .in_set(OnUpdate(GameState::InGame)),
-            )
-            .add_systems(
-                (
-                    enemy_spawn::<S, Bat>,
-                    enemy_spawn::<S, Goblin>,
-                    enemy_spawn::<S, SpearGoblin>,
-                    enemy_spawn::<S, Skull>,
-                    enemy_spawn::<S, PoisonIvy>,
-                    enemy_spawn::<S, MadCrab>,
-                )
-                    .in_set(OnUpdate(SpawnState::Stage4))
-                    .in_set(OnUpdate(GameState::InGame)),
+            // phase can change in data while the game is paused
+            .add_system(
+                start_phase_spawns::<S>
+                    .before(enemy_spawn::<S>)
+                    .in_set(OnUpdate(GlobalState::InGame)),
             )
+            .add_system(enemy_spawn::<S>.in_set(OnUpdate(GameState::InGame)))
             .add_system(remove_all_with::<EnemyMarker>.in_schedule(OnEnter(GlobalState::MainMenu)))
             .add_system(
                 remove_all_with::<EnemySpawnMarker>.in_schedule(OnEnter(GlobalState::MainMenu)),
+            )
+            // restart skips the main menu
+            .add_system(
+                remove_all_with::<ExtraEnemySpawn>.in_schedule(OnExit(GlobalState::InGame)),
             );
     }
 }
@@ -71,25 +56,46 @@ impl<S: Side> Plugin for SpawnPlugin<S> {
 #[derive(Debug, Default, Component)]
 pub struct EnemySpawnMarker;
 
-#[derive(Debug, Default, Resource)]
+/// Spawn added by the upgrade side effect.
+/// It is kept when the wave phase changes.
+#[derive(Debug, Default, Component)]
+pub struct ExtraEnemySpawn;
+
+#[derive(Debug, Default, Clone, Resource)]
 pub struct EnemyBuffs<S: Side> {
-    pub health: f32,
-    pub speed: f32,
-    pub exp: f32,
-    pub damage: f32,
-    pub attack_speed: f32,
+    pub health: Stat,
+    pub speed: Stat,
+    /// Negative modifiers reduce exp drop
+    pub exp: Stat,
+    pub damage: Stat,
+    pub attack_speed: Stat,
+    /// Flat modifiers, 0.1 is +10% chance
+    pub elite_chance: Stat,
     _phantom: PhantomData<S>,
 }
 
 impl<S: Side> std::fmt::Display for EnemyBuffs<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("health +{:.1}%\n", self.health * 100.0))?;
-        f.write_fmt(format_args!("speed +{:.1}%\n", self.speed * 100.0))?;
-        f.write_fmt(format_args!("exp -{:.1}%\n", self.exp * 100.0))?;
-        f.write_fmt(format_args!("damage +{:.1}%\n", self.damage * 100.0))?;
         f.write_fmt(format_args!(
-            "attack speed +{:.1}%\n",
-            self.attack_speed * 100.0
+            "health {:+.1}%\n",
+            self.health.percent() * 100.0
+        ))?;
+        f.write_fmt(format_args!(
+            "speed {:+.1}%\n",
+            self.speed.percent() * 100.0
+        ))?;
+        f.write_fmt(format_args!("exp {:+.1}%\n", self.exp.percent() * 100.0))?;
+        f.write_fmt(format_args!(
+            "damage {:+.1}%\n",
+            self.damage.percent() * 100.0
+        ))?;
+        f.write_fmt(format_args!(
+            "attack speed {:+.1}%\n",
+            self.attack_speed.percent() * 100.0
+        ))?;
+        f.write_fmt(format_args!(
+            "elite chance {:+.1}%\n",
+            self.elite_chance.value() * 100.0
         ))?;
         Ok(())
     }
@@ -98,90 +104,255 @@ impl<S: Side> std::fmt::Display for EnemyBuffs<S> {
 impl<S: Side> EnemyBuffs<S> {
     pub fn with_global_buffs(&self, global_buffs: &GlobalEnemyBuffs) -> Self {
         Self {
-            health: self.health + global_buffs.health,
-            speed: self.speed + global_buffs.speed,
-            exp: self.exp + global_buffs.exp,
-            damage: self.damage + global_buffs.damage,
-            attack_speed: self.attack_speed + global_buffs.attack_speed,
+            health: self.health.with(&global_buffs.health),
+            speed: self.speed.with(&global_buffs.speed),
+            exp: self.exp.with(&global_buffs.exp),
+            damage: self.damage.with(&global_buffs.damage),
+            attack_speed: self.attack_speed.with(&global_buffs.attack_speed),
+            elite_chance: self.elite_chance.clone(),
             _phantom: PhantomData,
         }
     }
+
+    /// Removes modifiers of the source from all buffs
+    pub fn remove(&mut self, source: StatSource) {
+        self.health.remove(source);
+        self.speed.remove(source);
+        self.exp.remove(source);
+        self.damage.remove(source);
+        self.attack_speed.remove(source);
+        self.elite_chance.remove(source);
+    }
 }
 
 #[derive(Debug, Component)]
-pub struct EnemySpawn<S: Side, E: EnemyType<S>> {
+pub struct EnemySpawn<S: Side> {
+    /// Name of the enemy in `EnemyDefinitions`
+    pub enemy: String,
     pub number: u32,
     pub radius: f32,
     pub timer: Timer,
     _phantom: PhantomData<S>,
-    _phantom2: PhantomData<E>,
 }
 
-impl<S: Side, E: EnemyType<S>> Default for EnemySpawn<S, E> {
-    fn default() -> Self {
+impl<S: Side> EnemySpawn<S> {
+    pub fn new(wave_spawn: &WaveSpawn) -> Self {
         Self {
-            number: E::NUMBER_PER_SPAWN,
+            enemy: wave_spawn.enemy.clone(),
+            number: wave_spawn.number,
             radius: DEFAULT_ENEMY_SPAWN_RADIUS,
-            timer: Timer::from_seconds(DEFAULT_ENEMY_SPAWN_RATE, TimerMode::Repeating),
+            timer: Timer::from_seconds(wave_spawn.interval, TimerMode::Repeating),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Spawn of the same enemy with another number of enemies
+    pub fn with_number(&self, number: u32) -> Self {
+        Self {
+            enemy: self.enemy.clone(),
+            number,
+            radius: self.radius,
+            timer: Timer::new(self.timer.duration(), TimerMode::Repeating),
             _phantom: PhantomData,
-            _phantom2: PhantomData,
         }
     }
 }
 
-#[derive(Default, Bundle)]
-pub struct EnemySpawnBundle<S: Side, E: EnemyType<S>> {
-    spawn: EnemySpawn<S, E>,
+#[derive(Bundle)]
+pub struct EnemySpawnBundle<S: Side> {
+    #[bundle]
+    transform: TransformBundle,
+    spawn: EnemySpawn<S>,
     marker: EnemySpawnMarker,
 }
 
-/// Sets up 4 spawns at each side of the screen
+impl<S: Side> EnemySpawnBundle<S> {
+    pub fn new(spawn: EnemySpawn<S>) -> Self {
+        Self {
+            transform: TransformBundle::from_transform(Transform::from_translation(
+                (S::DIRECTION * DEFAULT_ENEMY_SPAWN_POSITON).extend(0.0),
+            )),
+            spawn,
+            marker: EnemySpawnMarker,
+        }
+    }
+}
+
 fn setup<S: Side>(mut commands: Commands) {
     commands.insert_resource(EnemyBuffs::<S>::default());
+    commands.insert_resource(EnemyFreeze::<S>::default());
+}
 
-    // North
-    commands
-        .spawn(TransformBundle::from_transform(
-            Transform::from_translation((S::DIRECTION * DEFAULT_ENEMY_SPAWN_POSITON).extend(0.0)),
-        ))
-        .insert(EnemySpawnBundle::<S, Bat>::default())
-        .insert(EnemySpawnBundle::<S, Goblin>::default())
-        .insert(EnemySpawnBundle::<S, SpearGoblin>::default())
-        .insert(EnemySpawnBundle::<S, Skull>::default())
-        .insert(EnemySpawnBundle::<S, PoisonIvy>::default())
-        .insert(EnemySpawnBundle::<S, MadCrab>::default());
+/// Replaces spawns on the side with the ones
+/// from the new wave phase.
+/// Extra spawns from side effects stay.
+fn start_phase_spawns<S: Side>(
+    enemy_assets: Res<EnemyAssets>,
+    wave_schedules: Res<Assets<WaveSchedule>>,
+    spawns: Query<Entity, (With<EnemySpawn<S>>, Without<ExtraEnemySpawn>)>,
+    mut commands: Commands,
+    mut phase_events: EventReader<WavePhaseEvent>,
+) {
+    let Some(schedule) = wave_schedules.get(&enemy_assets.waves) else {
+        return;
+    };
+
+    for event in phase_events.iter() {
+        for spawn in spawns.iter() {
+            commands.entity(spawn).despawn();
+        }
+
+        let Some(phase) = schedule.phases.get(event.phase) else {
+            continue;
+        };
+        for wave_spawn in phase.spawns.iter() {
+            if wave_spawn.spawns_on::<S>() {
+                commands.spawn(EnemySpawnBundle::<S>::new(EnemySpawn::new(wave_spawn)));
+            }
+        }
+    }
 }
 
 /// Spawns enemies in a circle arond the spawn point equally spread
 /// on a circle
-fn enemy_spawn<S: Side, E: EnemyType<S>>(
+fn enemy_spawn<S: Side>(
     time: Res<Time>,
+    enemy_assets: Res<EnemyAssets>,
+    enemy_definitions: Res<Assets<EnemyDefinitions>>,
     enemy_sprites: Res<EnemySprites>,
+    elite_definitions: Res<Assets<EliteDefinitions>>,
+    game_assets: Res<GameAssets>,
     global_buffs: Res<GlobalEnemyBuffs>,
     buffs: Res<EnemyBuffs<S>>,
+    wave_progress: Res<WaveProgress>,
+    mut side_rng: ResMut<SideRng<S>>,
     mut commands: Commands,
-    mut spawns: Query<(&Transform, &mut EnemySpawn<S, E>)>,
+    mut spawns: Query<(&Transform, &mut EnemySpawn<S>)>,
 ) {
+    let Some(definitions) = enemy_definitions.get(&enemy_assets.definitions) else {
+        return;
+    };
+
+    // later tiers make spawns more frequent and bigger
+    let delta = time.delta().mul_f32(wave_progress.spawn_rate_multiplier());
     for (transform, mut spawn) in spawns.iter_mut() {
-        if !spawn.timer.tick(time.delta()).finished() {
+        let Some(definition) = definitions.get(&spawn.enemy) else {
+            continue;
+        };
+
+        if !spawn.timer.tick(delta).finished() {
             continue;
         }
 
-        for n in 0..spawn.number {
+        let texture_atlas = enemy_sprites
+            .atlases
+            .get(&definition.name)
+            .cloned()
+            .unwrap_or_default();
+
+        let number = (spawn.number as f32 * wave_progress.spawn_number_multiplier()) as u32;
+        for n in 0..number {
             let position = transform.translation
                 + Quat::from_rotation_z(
-                    (2.0 * std::f32::consts::PI / spawn.number as f32) * n as f32
-                        + rand::thread_rng().gen_range(0.0..std::f32::consts::FRAC_PI_6),
+                    (2.0 * std::f32::consts::PI / number as f32) * n as f32
+                        + side_rng.spawn.gen_range(0.0..std::f32::consts::FRAC_PI_6),
                 )
                 .mul_vec3(Vec3::Y * spawn.radius);
 
-            commands.spawn(EnemyBundle::<S, E>::new(
-                E::SIZE,
-                E::texture_atlas(&enemy_sprites),
+            let elite = elite_definitions
+                .get(&enemy_assets.elites)
+                .filter(|elites| {
+                    side_rng
+                        .elites
+                        .gen_bool(elites.chance(wave_progress.wave_number(), &buffs))
+                })
+                .map(|elites| elites.roll(wave_progress.wave_number(), &mut side_rng.elites));
+
+            let mut enemy = spawn_enemy(
+                &mut commands,
+                definition,
+                texture_atlas.clone(),
                 position,
                 &global_buffs,
                 &buffs,
-            ));
+            );
+            if let Some(elite) = elite {
+                elite.insert(
+                    &mut enemy,
+                    definition,
+                    &global_buffs,
+                    &buffs,
+                    game_assets.font.clone(),
+                );
+            }
+        }
+    }
+}
+
+/// Spawns the enemy with all its behaviours
+pub fn spawn_enemy<'w, 's, 'a, S: Side>(
+    commands: &'a mut Commands<'w, 's>,
+    definition: &EnemyDefinition,
+    texture_atlas: Handle<TextureAtlas>,
+    position: Vec3,
+    global_buffs: &GlobalEnemyBuffs,
+    buffs: &EnemyBuffs<S>,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut enemy = commands.spawn(EnemyBundle::<S>::new(
+        definition,
+        texture_atlas,
+        position,
+        global_buffs,
+        buffs,
+    ));
+    for behaviour in definition.behaviours.iter() {
+        match behaviour {
+            EnemyBehaviour::Melee => {
+                enemy.insert(definition.attack(global_buffs, buffs));
+            }
+            EnemyBehaviour::Ranged {
+                projectile_speed,
+                projectile_size,
+            } => {
+                enemy.insert((
+                    definition.attack(global_buffs, buffs),
+                    EnemyRanged {
+                        projectile_speed: *projectile_speed,
+                        projectile_size: *projectile_size,
+                    },
+                ));
+            }
+            EnemyBehaviour::Ram {
+                charge_range,
+                charge_speed,
+            } => {
+                enemy.insert((
+                    SiegeAttack::new(
+                        SiegeKind::Ram {
+                            charge_range: *charge_range,
+                            charge_speed: *charge_speed,
+                        },
+                        definition.attack(global_buffs, buffs),
+                    ),
+                    Dominance::group(SIEGE_DOMINANCE),
+                ));
+            }
+            EnemyBehaviour::Catapult {
+                projectile_speed,
+                projectile_size,
+            } => {
+                enemy.insert((
+                    SiegeAttack::new(
+                        SiegeKind::Catapult {
+                            projectile_speed: *projectile_speed,
+                            projectile_size: *projectile_size,
+                        },
+                        definition.attack(global_buffs, buffs),
+                    ),
+                    Dominance::group(SIEGE_DOMINANCE),
+                ));
+            }
         }
     }
+    enemy
 }
@@ -0,0 +1,257 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use crate::{
+    game::{data::data_modified, stats::StatSource, GameMode, GameState, Side},
+    GlobalState,
+};
+
+use super::{EnemyAssets, GlobalEnemyBuffs};
+
+pub struct WavesPlugin;
+
+impl Plugin for WavesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WavePhaseEvent>()
+            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_system(wave_progress.in_set(OnUpdate(GameState::InGame)))
+            // data can change while the game is paused
+            .add_system(reload_waves.in_set(OnUpdate(GlobalState::InGame)));
+    }
+}
+
+/// Timed phases of enemy spawns.
+/// Loaded from `assets/data/game.waves.ron`
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "0c8f6a2e-7b1d-4e39-a5f4-3d9e2b7c1f58"]
+pub struct WaveSchedule {
+    pub phases: Vec<WavePhase>,
+    /// Growth applied every time the last phase repeats in the endless mode
+    pub tier: WaveTier,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WavePhase {
+    /// Phase length in seconds.
+    /// The last phase repeats with this length forever.
+    pub duration: f32,
+    pub spawns: Vec<WaveSpawn>,
+    /// Added to the global enemy buffs when the phase starts
+    #[serde(default)]
+    pub buffs: WaveBuffs,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveSpawn {
+    /// Name of the enemy in `EnemyDefinitions`
+    pub enemy: String,
+    pub number: u32,
+    /// Seconds between spawns
+    pub interval: f32,
+    #[serde(default = "WaveSide::all")]
+    pub sides: Vec<WaveSide>,
+}
+
+impl WaveSpawn {
+    pub fn spawns_on<S: Side>(&self) -> bool {
+        self.sides
+            .iter()
+            .any(|side| side.direction() == S::DIRECTION)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum WaveSide {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl WaveSide {
+    fn all() -> Vec<WaveSide> {
+        vec![
+            WaveSide::North,
+            WaveSide::South,
+            WaveSide::West,
+            WaveSide::East,
+        ]
+    }
+
+    fn direction(&self) -> Vec2 {
+        match self {
+            WaveSide::North => Vec2::Y,
+            WaveSide::South => Vec2::NEG_Y,
+            WaveSide::West => Vec2::NEG_X,
+            WaveSide::East => Vec2::X,
+        }
+    }
+}
+
+/// Percent buffs, 0.1 is +10%.
+/// Positive exp reduces exp drop
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct WaveBuffs {
+    pub health: f32,
+    pub speed: f32,
+    pub exp: f32,
+    pub damage: f32,
+    pub attack_speed: f32,
+}
+
+impl WaveBuffs {
+    fn apply(&self, global_buffs: &mut GlobalEnemyBuffs, wave: u32) {
+        let source = StatSource::Wave { wave };
+        global_buffs.health.add_percent(source, self.health);
+        global_buffs.speed.add_percent(source, self.speed);
+        global_buffs.exp.add_percent(source, -self.exp);
+        global_buffs.damage.add_percent(source, self.damage);
+        global_buffs
+            .attack_speed
+            .add_percent(source, self.attack_speed);
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct WaveTier {
+    pub buffs: WaveBuffs,
+    /// Growth of the number of enemies in each spawn
+    pub spawn_number_growth: f32,
+    /// Growth of the speed of spawn timers
+    pub spawn_rate_growth: f32,
+}
+
+/// Sent when the next wave phase starts
+/// or the current phase changes in data.
+/// Not sent for tiers of the last phase.
+pub struct WavePhaseEvent {
+    pub phase: usize,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct WaveProgress {
+    pub phase: usize,
+    /// Number of tiers passed since the start of the last phase
+    pub tier: u32,
+    pub timer: Timer,
+    spawn_number_growth: f32,
+    spawn_rate_growth: f32,
+}
+
+impl WaveProgress {
+    /// Wave number shown to the player.
+    /// Keeps growing with each tier after the last phase
+    pub fn wave_number(&self) -> u32 {
+        self.phase as u32 + 1 + self.tier
+    }
+
+    /// Multiplier for the number of enemies in each spawn
+    pub fn spawn_number_multiplier(&self) -> f32 {
+        1.0 + self.tier as f32 * self.spawn_number_growth
+    }
+
+    /// Multiplier for the speed of spawn timers
+    pub fn spawn_rate_multiplier(&self) -> f32 {
+        1.0 + self.tier as f32 * self.spawn_rate_growth
+    }
+}
+
+/// Starts the first phase of the schedule
+fn setup(
+    enemy_assets: Res<EnemyAssets>,
+    wave_schedules: Res<Assets<WaveSchedule>>,
+    mut commands: Commands,
+    mut phase_events: EventWriter<WavePhaseEvent>,
+) {
+    let mut global_buffs = GlobalEnemyBuffs::default();
+    let mut wave_progress = WaveProgress::default();
+
+    if let Some(schedule) = wave_schedules.get(&enemy_assets.waves) {
+        wave_progress.spawn_number_growth = schedule.tier.spawn_number_growth;
+        wave_progress.spawn_rate_growth = schedule.tier.spawn_rate_growth;
+        if let Some(phase) = schedule.phases.first() {
+            phase
+                .buffs
+                .apply(&mut global_buffs, wave_progress.wave_number());
+            wave_progress.timer = Timer::from_seconds(phase.duration, TimerMode::Repeating);
+            phase_events.send(WavePhaseEvent { phase: 0 });
+        }
+    } else {
+        warn!("Wave schedule is not loaded");
+    }
+
+    commands.insert_resource(global_buffs);
+    commands.insert_resource(wave_progress);
+}
+
+fn wave_progress(
+    time: Res<Time>,
+    game_mode: Res<GameMode>,
+    enemy_assets: Res<EnemyAssets>,
+    wave_schedules: Res<Assets<WaveSchedule>>,
+    mut wave_progress: ResMut<WaveProgress>,
+    mut global_buffs: ResMut<GlobalEnemyBuffs>,
+    mut phase_events: EventWriter<WavePhaseEvent>,
+) {
+    let Some(schedule) = wave_schedules.get(&enemy_assets.waves) else {
+        return;
+    };
+    if schedule.phases.is_empty() {
+        return;
+    }
+
+    if !wave_progress.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let next_phase = wave_progress.phase + 1;
+    if let Some(phase) = schedule.phases.get(next_phase) {
+        wave_progress.phase = next_phase;
+        phase
+            .buffs
+            .apply(&mut global_buffs, wave_progress.wave_number());
+        wave_progress.timer = Timer::from_seconds(phase.duration, TimerMode::Repeating);
+        phase_events.send(WavePhaseEvent { phase: next_phase });
+    } else if *game_mode == GameMode::Endless {
+        // last phase repeats and makes enemies stronger
+        wave_progress.tier += 1;
+        schedule
+            .tier
+            .buffs
+            .apply(&mut global_buffs, wave_progress.wave_number());
+    }
+}
+
+/// Applies changes of the wave schedule to the current phase.
+/// Phase buffs are only applied when the next phase starts.
+fn reload_waves(
+    enemy_assets: Res<EnemyAssets>,
+    wave_schedules: Res<Assets<WaveSchedule>>,
+    mut wave_progress: ResMut<WaveProgress>,
+    mut schedule_events: EventReader<AssetEvent<WaveSchedule>>,
+    mut phase_events: EventWriter<WavePhaseEvent>,
+) {
+    if !data_modified(&mut schedule_events, &enemy_assets.waves) {
+        return;
+    }
+    let Some(schedule) = wave_schedules.get(&enemy_assets.waves) else {
+        warn!("Wave schedule is not loaded");
+        return;
+    };
+
+    wave_progress.spawn_number_growth = schedule.tier.spawn_number_growth;
+    wave_progress.spawn_rate_growth = schedule.tier.spawn_rate_growth;
+
+    let phase = wave_progress
+        .phase
+        .min(schedule.phases.len().saturating_sub(1));
+    if let Some(wave_phase) = schedule.phases.get(phase) {
+        wave_progress.phase = phase;
+        wave_progress
+            .timer
+            .set_duration(std::time::Duration::from_secs_f32(wave_phase.duration));
+        // replaces spawners with the new ones
+        phase_events.send(WavePhaseEvent { phase });
+    }
+}
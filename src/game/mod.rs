@@ -4,14 +4,21 @@ use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
 use bevy_kira_audio::Audio;
 use bevy_rapier2d::prelude::RapierConfiguration;
+use serde::{Deserialize, Serialize};
 
 use crate::GameSettings;
 use crate::{impl_into_state, utils::set_state, GameAssets, GlobalState, IntoState};
 
+pub mod abilities;
 pub mod animation;
 pub mod castle;
 pub mod damage;
+pub mod data;
 pub mod enemies;
+pub mod profile;
+pub mod replay;
+pub mod rng;
+pub mod stats;
 pub mod upgrades;
 pub mod weapons;
 
@@ -20,6 +27,7 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
+            .init_resource::<GameMode>()
             .add_system(
                 set_state::<GameState, { GameState::InGame as u8 }>
                     .in_schedule(OnEnter(GlobalState::InGame)),
@@ -28,15 +36,38 @@ impl Plugin for GamePlugin {
                 set_state::<GameState, { GameState::NotInGame as u8 }>
                     .in_schedule(OnExit(GlobalState::InGame)),
             )
-            .add_system(play_main_menu_audio.in_schedule(OnEnter(GlobalState::MainMenu)))
-            .add_system(stop_audio.in_schedule(OnExit(GlobalState::MainMenu)))
-            .add_system(play_in_game_audio.in_schedule(OnEnter(GlobalState::InGame)))
-            .add_system(stop_audio.in_schedule(OnExit(GlobalState::InGame)))
-            .add_system(in_game_key_input.in_set(OnUpdate(GameState::InGame)))
+            // no audio in headless mode
+            .add_system(
+                play_main_menu_audio
+                    .run_if(resource_exists::<Audio>())
+                    .in_schedule(OnEnter(GlobalState::MainMenu)),
+            )
+            .add_system(
+                stop_audio
+                    .run_if(resource_exists::<Audio>())
+                    .in_schedule(OnExit(GlobalState::MainMenu)),
+            )
+            .add_system(
+                play_in_game_audio
+                    .run_if(resource_exists::<Audio>())
+                    .in_schedule(OnEnter(GlobalState::InGame)),
+            )
+            .add_system(
+                stop_audio
+                    .run_if(resource_exists::<Audio>())
+                    .in_schedule(OnExit(GlobalState::InGame)),
+            )
+            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_systems(
+                (in_game_key_input, update_game_time, check_victory)
+                    .in_set(OnUpdate(GameState::InGame)),
+            )
             .add_system(stop_physics.in_schedule(OnEnter(GameState::Paused)))
             .add_system(resume_physics.in_schedule(OnExit(GameState::Paused)))
             .add_system(stop_physics.in_schedule(OnEnter(GameState::GameOver)))
             .add_system(resume_physics.in_schedule(OnExit(GameState::GameOver)))
+            .add_system(stop_physics.in_schedule(OnEnter(GameState::Victory)))
+            .add_system(resume_physics.in_schedule(OnExit(GameState::Victory)))
             .add_system(stop_physics.in_schedule(OnEnter(GameState::LevelUp)))
             .add_system(resume_physics.in_schedule(OnExit(GameState::LevelUp)))
             .add_system(stop_physics.in_schedule(OnEnter(GameState::StatsNorth)))
@@ -47,8 +78,12 @@ impl Plugin for GamePlugin {
             .add_system(resume_physics.in_schedule(OnExit(GameState::StatsWest)))
             .add_system(stop_physics.in_schedule(OnEnter(GameState::StatsEast)))
             .add_system(resume_physics.in_schedule(OnExit(GameState::StatsEast)))
+            .add_plugin(abilities::AbilitiesPlugin)
             .add_plugin(animation::AnimationPlugin)
             .add_plugin(castle::CastlePlugin)
+            .add_plugin(profile::ProfilePlugin)
+            .add_plugin(rng::RngPlugin)
+            .add_plugin(replay::ReplayPlugin)
             .add_plugin(damage::DamagePlugin)
             .add_plugin(enemies::EnemyPlugin)
             .add_plugin(weapons::WeaponsPlugin)
@@ -76,9 +111,26 @@ pub enum GameState {
     StatsSouth,
     StatsWest,
     StatsEast,
+    Victory,
 }
 impl_into_state!(GameState);
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Hold the castle for `GameSettings::normal_mode_duration` to win
+    #[default]
+    Normal,
+    /// Hold the castle for as long as possible
+    Endless,
+}
+
+/// Time passed since the start of the run.
+/// Only ticks while the game is not paused.
+#[derive(Debug, Default, Resource)]
+pub struct GameTime {
+    pub elapsed: f32,
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct North;
 
@@ -134,6 +186,25 @@ fn stop_audio(audio: Res<Audio>) {
     audio.stop();
 }
 
+fn setup(mut commands: Commands) {
+    commands.insert_resource(GameTime::default());
+}
+
+fn update_game_time(time: Res<Time>, mut game_time: ResMut<GameTime>) {
+    game_time.elapsed += time.delta_seconds();
+}
+
+fn check_victory(
+    game_mode: Res<GameMode>,
+    game_time: Res<GameTime>,
+    game_settings: Res<GameSettings>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if *game_mode == GameMode::Normal && game_settings.normal_mode_duration <= game_time.elapsed {
+        game_state.set(GameState::Victory);
+    }
+}
+
 fn in_game_key_input(keyboard: Res<Input<KeyCode>>, mut game_state: ResMut<NextState<GameState>>) {
     if keyboard.pressed(KeyCode::Escape) {
         game_state.set(GameState::Paused);
@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::GlobalState;
+
+use super::{castle::Castle, GameTime};
+
+/// Folder of the game in the user data directory
+const DATA_DIR_NAME: &str = "mind_your_sides";
+const PROFILE_FILE_NAME: &str = "profile.ron";
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Profile>().add_system(
+            record_run
+                .run_if(resource_exists::<ProfilePath>())
+                .in_schedule(OnExit(GlobalState::InGame)),
+        );
+    }
+}
+
+/// File the profile is loaded from and saved to.
+/// Profile is not saved without it.
+#[derive(Debug, Clone, Resource)]
+pub struct ProfilePath(pub PathBuf);
+
+impl ProfilePath {
+    /// Profile file in the user data directory
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn user_data() -> Option<Self> {
+        let home = || std::env::var_os("HOME").map(PathBuf::from);
+        let data_dir = if cfg!(target_os = "windows") {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            home().map(|home| home.join("Library").join("Application Support"))
+        } else {
+            std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| home().map(|home| home.join(".local").join("share")))
+        }?;
+        Some(Self(data_dir.join(DATA_DIR_NAME).join(PROFILE_FILE_NAME)))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn user_data() -> Option<Self> {
+        None
+    }
+}
+
+/// Progress stored between the sessions
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Resource)]
+pub struct Profile {
+    pub runs: u32,
+    pub total_kills: u32,
+    /// Total time spent in game in seconds
+    pub total_time: f32,
+    /// Longest run in seconds
+    pub best_time: f32,
+    pub best_level: u32,
+}
+
+impl Profile {
+    /// New profile if the file does not exist yet
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &ProfilePath) -> Result<Self, String> {
+        let path = &path.0;
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Could not read profile {}: {e}", path.display())),
+        };
+        ron::from_str(&data).map_err(|e| format!("Could not parse profile {}: {e}", path.display()))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(_path: &ProfilePath) -> Result<Self, String> {
+        Ok(Self::default())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &ProfilePath) -> Result<(), String> {
+        let path = &path.0;
+        let data = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|e| format!("Could not serialize profile: {e}"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create {}: {e}", dir.display()))?;
+        }
+        std::fs::write(path, data)
+            .map_err(|e| format!("Could not save profile {}: {e}", path.display()))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self, _path: &ProfilePath) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Adds results of the finished run
+    pub fn add_run(&mut self, castle: &Castle, time: f32) {
+        self.runs += 1;
+        self.total_kills += castle.kills;
+        self.total_time += time;
+        self.best_time = self.best_time.max(time);
+        self.best_level = self.best_level.max(castle.level);
+    }
+}
+
+/// Adds results of the finished run to the profile
+fn record_run(
+    path: Res<ProfilePath>,
+    castle: Query<&Castle>,
+    game_time: Res<GameTime>,
+    mut profile: ResMut<Profile>,
+) {
+    let Ok(castle) = castle.get_single() else {
+        return;
+    };
+
+    profile.add_run(castle, game_time.elapsed);
+    if let Err(e) = profile.save(&path) {
+        error!("{e}");
+    }
+}
@@ -0,0 +1,505 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::GlobalState;
+
+use super::{
+    abilities::{
+        self, CastleAbility, CastleAbilityEvent, CastleAbilityTarget, UseCastleAbilityEvent,
+    },
+    castle::{self, Castle, CastleType, SpendLevelUpsEvent},
+    rng::{self, GameRng},
+    upgrades::{
+        apply::{ApplyUpgradeEvent, UpgradeSet},
+        options::LevelUpOptionEvent,
+    },
+    East, GameMode, GameState, GameTime, North, South, West,
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            manual_time_step
+                .before(TimeSystem)
+                .in_base_set(CoreSet::First)
+                .run_if(resource_exists::<ManualTimeStep>()),
+        )
+        // recording
+        .add_system(
+            start_recording
+                .after(rng::reseed)
+                .run_if(resource_exists::<ReplayRecordPath>())
+                .run_if(not(resource_exists::<ReplayPlayback>()))
+                .in_schedule(OnEnter(GlobalState::InGame)),
+        )
+        .add_system(
+            record_frame
+                .after(abilities::dispatch_abilities)
+                .after(castle::spend_level_ups)
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_set(OnUpdate(GameState::InGame)),
+        )
+        .add_system(
+            record_upgrades
+                .before(UpgradeSet::Finish)
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_set(OnUpdate(GameState::LevelUp)),
+        )
+        .add_system(
+            record_level_up_options
+                .before(UpgradeSet::Finish)
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_set(OnUpdate(GameState::LevelUp)),
+        )
+        .add_system(
+            record_pause_start
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_schedule(OnEnter(GameState::Paused)),
+        )
+        .add_system(
+            record_pause_end
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_schedule(OnExit(GameState::Paused)),
+        )
+        .add_system(
+            save_recording
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_schedule(OnEnter(GameState::GameOver)),
+        )
+        .add_system(
+            save_recording
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_schedule(OnEnter(GameState::Victory)),
+        )
+        .add_system(
+            save_recording
+                .run_if(resource_exists::<ReplayRecorder>())
+                .in_schedule(OnExit(GlobalState::InGame)),
+        )
+        // playback
+        .add_system(
+            start_playback
+                .run_if(resource_exists::<ReplayPlayback>())
+                .in_schedule(OnEnter(GlobalState::InGame)),
+        )
+        .add_system(
+            playback_frame
+                .before(abilities::dispatch_abilities)
+                .before(castle::spend_level_ups)
+                .run_if(resource_exists::<ReplayPlayback>())
+                .in_set(OnUpdate(GameState::InGame)),
+        )
+        .add_system(
+            playback_upgrade
+                .run_if(resource_exists::<ReplayPlayback>())
+                .in_schedule(OnEnter(GameState::LevelUp)),
+        )
+        .add_system(
+            playback_pause
+                .run_if(resource_exists::<ReplayPlayback>())
+                .in_set(OnUpdate(GameState::Paused)),
+        )
+        .add_system(
+            playback_time
+                .in_base_set(CoreSet::Last)
+                .run_if(resource_exists::<ReplayPlayback>()),
+        );
+    }
+}
+
+/// Everything needed to repeat a run.
+/// Frames are counted only while in `GameState::InGame`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub castle_type: CastleType,
+    pub game_mode: GameMode,
+    /// Time step of every frame in nanoseconds
+    pub frame_deltas: Vec<u64>,
+    pub upgrades: Vec<ReplayUpgrade>,
+    /// Rerolls, banishes and skips on level ups
+    #[serde(default)]
+    pub level_up_options: Vec<ReplayLevelUpOption>,
+    pub abilities: Vec<ReplayAbility>,
+    /// Frames where banked level ups were spent
+    #[serde(default)]
+    pub level_up_spends: Vec<usize>,
+    pub pauses: Vec<ReplayPause>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayUpgrade {
+    pub frame: usize,
+    pub time: f32,
+    /// Level the upgrade was picked for
+    pub level: u32,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayLevelUpOption {
+    pub frame: usize,
+    pub time: f32,
+    /// Level the option was used for
+    pub level: u32,
+    pub option: LevelUpOptionEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayAbility {
+    pub frame: usize,
+    pub ability: CastleAbility,
+    pub target: CastleAbilityTarget,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayPause {
+    pub frame: usize,
+    pub time: f32,
+    /// Seconds spent in pause
+    pub duration: f32,
+}
+
+impl Replay {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Option<Self> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Could not read replay {path}: {e}");
+                return None;
+            }
+        };
+        match ron::from_str(&data) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                warn!("Could not parse replay {path}: {e}");
+                None
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(_path: &str) -> Option<Self> {
+        None
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) {
+        let data = match ron::to_string(self) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Could not serialize replay: {e}");
+                return;
+            }
+        };
+        if let Err(e) = std::fs::write(path, data) {
+            warn!("Could not save replay {path}: {e}");
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self, _path: &str) {}
+}
+
+/// File to save the replay of every run to.
+/// Set with `--record <path>`, runs are not recorded otherwise.
+#[derive(Debug, Clone, Resource)]
+pub struct ReplayRecordPath(pub String);
+
+/// Replay of the current run
+#[derive(Debug, Default, Resource)]
+pub struct ReplayRecorder {
+    replay: Replay,
+    pause_start: f32,
+}
+
+/// Replay being played back.
+/// Needs `ManualTimeStep` to repeat recorded time steps.
+#[derive(Debug, Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    frame: usize,
+    next_upgrade: usize,
+    next_level_up_option: usize,
+    next_ability: usize,
+    next_level_up_spend: usize,
+    next_pause: usize,
+    pause_timer: Timer,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            frame: 0,
+            next_upgrade: 0,
+            next_level_up_option: 0,
+            next_ability: 0,
+            next_level_up_spend: 0,
+            next_pause: 0,
+            pause_timer: Timer::default(),
+        }
+    }
+}
+
+/// Drives `Time` with set deltas instead of the real clock
+#[derive(Debug, Resource)]
+pub struct ManualTimeStep {
+    pub delta: Duration,
+    instant: Instant,
+}
+
+impl ManualTimeStep {
+    pub fn new(delta: Duration) -> Self {
+        Self {
+            delta,
+            instant: Instant::now(),
+        }
+    }
+}
+
+fn manual_time_step(
+    mut time_step: ResMut<ManualTimeStep>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    let delta = time_step.delta;
+    time_step.instant += delta;
+    *time_update_strategy = TimeUpdateStrategy::ManualInstant(time_step.instant);
+}
+
+fn start_recording(
+    game_rng: Res<GameRng>,
+    castle_type: Res<CastleType>,
+    game_mode: Res<GameMode>,
+    mut commands: Commands,
+) {
+    commands.insert_resource(ReplayRecorder {
+        replay: Replay {
+            seed: game_rng.seed,
+            castle_type: *castle_type,
+            game_mode: *game_mode,
+            ..default()
+        },
+        ..default()
+    });
+}
+
+/// Records time step, abilities used and
+/// banked level ups spent this frame
+fn record_frame(
+    time: Res<Time>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut north_events: EventReader<CastleAbilityEvent<North>>,
+    mut south_events: EventReader<CastleAbilityEvent<South>>,
+    mut west_events: EventReader<CastleAbilityEvent<West>>,
+    mut east_events: EventReader<CastleAbilityEvent<East>>,
+    mut spend_events: EventReader<SpendLevelUpsEvent>,
+) {
+    let replay = &mut recorder.replay;
+    let frame = replay.frame_deltas.len();
+    let abilities = north_events
+        .iter()
+        .map(|event| (event.ability, CastleAbilityTarget::North))
+        .chain(
+            south_events
+                .iter()
+                .map(|event| (event.ability, CastleAbilityTarget::South)),
+        )
+        .chain(
+            west_events
+                .iter()
+                .map(|event| (event.ability, CastleAbilityTarget::West)),
+        )
+        .chain(
+            east_events
+                .iter()
+                .map(|event| (event.ability, CastleAbilityTarget::East)),
+        );
+    for (ability, target) in abilities {
+        replay.abilities.push(ReplayAbility {
+            frame,
+            ability,
+            target,
+        });
+    }
+    if !spend_events.is_empty() {
+        spend_events.clear();
+        replay.level_up_spends.push(frame);
+    }
+    replay.frame_deltas.push(time.delta().as_nanos() as u64);
+}
+
+fn record_upgrades(
+    game_time: Res<GameTime>,
+    castle: Query<&Castle>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut apply_upgrade_events: EventReader<ApplyUpgradeEvent>,
+) {
+    let frame = recorder.replay.frame_deltas.len();
+    let level = castle.single().upgrade_level();
+    for event in apply_upgrade_events.iter() {
+        recorder.replay.upgrades.push(ReplayUpgrade {
+            frame,
+            time: game_time.elapsed,
+            level,
+            index: *event as usize,
+        });
+    }
+}
+
+fn record_level_up_options(
+    game_time: Res<GameTime>,
+    castle: Query<&Castle>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut option_events: EventReader<LevelUpOptionEvent>,
+) {
+    let frame = recorder.replay.frame_deltas.len();
+    let level = castle.single().upgrade_level();
+    for event in option_events.iter() {
+        recorder.replay.level_up_options.push(ReplayLevelUpOption {
+            frame,
+            time: game_time.elapsed,
+            level,
+            option: *event,
+        });
+    }
+}
+
+fn record_pause_start(time: Res<Time>, mut recorder: ResMut<ReplayRecorder>) {
+    recorder.pause_start = time.elapsed_seconds();
+}
+
+fn record_pause_end(
+    time: Res<Time>,
+    game_time: Res<GameTime>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let pause = ReplayPause {
+        frame: recorder.replay.frame_deltas.len(),
+        time: game_time.elapsed,
+        duration: time.elapsed_seconds() - recorder.pause_start,
+    };
+    recorder.replay.pauses.push(pause);
+}
+
+fn save_recording(path: Res<ReplayRecordPath>, recorder: Res<ReplayRecorder>) {
+    recorder.replay.save(&path.0);
+}
+
+/// Replay starts from the beginning on every run
+fn start_playback(mut playback: ResMut<ReplayPlayback>) {
+    let replay = std::mem::take(&mut playback.replay);
+    *playback = ReplayPlayback::new(replay);
+}
+
+/// Uses recorded abilities, spends banked level ups
+/// and pauses the game at the recorded frames
+fn playback_frame(
+    mut playback: ResMut<ReplayPlayback>,
+    mut target: ResMut<CastleAbilityTarget>,
+    mut ability_events: EventWriter<UseCastleAbilityEvent>,
+    mut spend_events: EventWriter<SpendLevelUpsEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let frame = playback.frame;
+    while let Some(ability) = playback.replay.abilities.get(playback.next_ability) {
+        if ability.frame != frame {
+            break;
+        }
+        // target is set for each use, so dispatch sends
+        // the ability to the recorded side
+        *target = ability.target;
+        ability_events.send(UseCastleAbilityEvent {
+            ability: ability.ability,
+        });
+        playback.next_ability += 1;
+    }
+
+    if playback
+        .replay
+        .level_up_spends
+        .get(playback.next_level_up_spend)
+        == Some(&frame)
+    {
+        spend_events.send(SpendLevelUpsEvent);
+        playback.next_level_up_spend += 1;
+    }
+
+    playback.frame += 1;
+
+    if let Some(pause) = playback.replay.pauses.get(playback.next_pause) {
+        if pause.frame == playback.frame {
+            playback.pause_timer = Timer::from_seconds(pause.duration.max(0.0), TimerMode::Once);
+            playback.next_pause += 1;
+            game_state.set(GameState::Paused);
+        }
+    }
+}
+
+/// Uses recorded level up options and then picks the recorded upgrade.
+/// Level ups finished with an option have no upgrade.
+/// Playback stops if the recorded upgrade does not exist.
+fn playback_upgrade(
+    castle: Query<&Castle>,
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    mut option_events: EventWriter<LevelUpOptionEvent>,
+    mut apply_upgrade_events: EventWriter<ApplyUpgradeEvent>,
+) {
+    let level = castle.single().upgrade_level();
+    while let Some(option) = playback
+        .replay
+        .level_up_options
+        .get(playback.next_level_up_option)
+    {
+        if option.level != level {
+            break;
+        }
+        let option = option.option;
+        option_events.send(option);
+        playback.next_level_up_option += 1;
+        if option.finishes_level_up() {
+            return;
+        }
+    }
+
+    if let Some(upgrade) = playback.replay.upgrades.get(playback.next_upgrade) {
+        if upgrade.level == level {
+            let Some(event) = ApplyUpgradeEvent::ALL.get(upgrade.index) else {
+                warn!(
+                    "Invalid upgrade index {} in replay, stopping playback",
+                    upgrade.index
+                );
+                commands.remove_resource::<ReplayPlayback>();
+                return;
+            };
+            apply_upgrade_events.send(*event);
+            playback.next_upgrade += 1;
+        }
+    }
+}
+
+fn playback_pause(
+    time: Res<Time>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if playback.pause_timer.tick(time.delta()).just_finished() {
+        game_state.set(GameState::InGame);
+    }
+}
+
+/// Sets time step for the next frame
+fn playback_time(playback: Res<ReplayPlayback>, mut time_step: ResMut<ManualTimeStep>) {
+    if let Some(delta) = playback.replay.frame_deltas.get(playback.frame) {
+        time_step.delta = Duration::from_nanos(*delta);
+    }
+}
@@ -0,0 +1,129 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{GameSettings, GlobalState};
+
+use super::{East, North, Side, South, West};
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>()
+            .init_resource::<SideRng<North>>()
+            .init_resource::<SideRng<South>>()
+            .init_resource::<SideRng<West>>()
+            .init_resource::<SideRng<East>>()
+            .add_system(reseed.in_schedule(OnEnter(GlobalState::InGame)));
+    }
+}
+
+/// Separate streams so one subsystem drawing more numbers
+/// does not shift the numbers of the others
+#[derive(Debug, Clone, Copy)]
+enum RngStream {
+    Upgrades,
+    Spawn,
+    Projectiles,
+    Areas,
+    Molotovs,
+    Chooser,
+    Elites,
+    Missiles,
+}
+
+/// Seed of the current run and streams
+/// not tied to any side
+#[derive(Debug, Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    pub upgrades: StdRng,
+    pub chooser: StdRng,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            upgrades: stream(seed, RngStream::Upgrades, 0),
+            chooser: stream(seed, RngStream::Chooser, 0),
+        }
+    }
+}
+
+/// Streams for the per side systems.
+/// Systems of different sides run in parallel, so
+/// they can not share a stream without losing determinism.
+#[derive(Debug, Resource)]
+pub struct SideRng<S: Side> {
+    pub spawn: StdRng,
+    pub projectiles: StdRng,
+    pub areas: StdRng,
+    pub molotovs: StdRng,
+    pub elites: StdRng,
+    pub missiles: StdRng,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> Default for SideRng<S> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<S: Side> SideRng<S> {
+    pub fn new(seed: u64) -> Self {
+        let side = side_index::<S>();
+        Self {
+            spawn: stream(seed, RngStream::Spawn, side),
+            projectiles: stream(seed, RngStream::Projectiles, side),
+            areas: stream(seed, RngStream::Areas, side),
+            molotovs: stream(seed, RngStream::Molotovs, side),
+            elites: stream(seed, RngStream::Elites, side),
+            missiles: stream(seed, RngStream::Missiles, side),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+fn side_index<S: Side>() -> u64 {
+    match (S::DIRECTION.x as i32, S::DIRECTION.y as i32) {
+        (0, 1) => 1,
+        (0, -1) => 2,
+        (-1, 0) => 3,
+        _ => 4,
+    }
+}
+
+fn stream(seed: u64, stream: RngStream, side: u64) -> StdRng {
+    let id = stream as u64 * 8 + side;
+    StdRng::seed_from_u64(seed ^ id.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Uses seed from the command line if there is one,
+/// otherwise every run gets a new random seed
+pub fn reseed(
+    game_settings: Res<GameSettings>,
+    mut game_rng: ResMut<GameRng>,
+    mut north_rng: ResMut<SideRng<North>>,
+    mut south_rng: ResMut<SideRng<South>>,
+    mut west_rng: ResMut<SideRng<West>>,
+    mut east_rng: ResMut<SideRng<East>>,
+) {
+    let seed = game_settings.seed.unwrap_or_else(rand::random);
+    info!("Run seed: {seed}");
+
+    *game_rng = GameRng::new(seed);
+    *north_rng = SideRng::new(seed);
+    *south_rng = SideRng::new(seed);
+    *west_rng = SideRng::new(seed);
+    *east_rng = SideRng::new(seed);
+}
@@ -0,0 +1,252 @@
+use std::fmt::Write;
+
+/// Where stat modifier came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatSource {
+    /// Upgrade picked on the level up
+    Upgrade { level: u32, global: bool },
+    /// Growth of enemies from the wave schedule
+    Wave { wave: u32 },
+    /// Bonus of the synergy triggered on the level up
+    Synergy { level: u32 },
+    /// Active curse picked on the level up
+    Curse { level: u32 },
+}
+
+impl std::fmt::Display for StatSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatSource::Upgrade {
+                level,
+                global: false,
+            } => f.write_fmt(format_args!("lvl {level}")),
+            StatSource::Upgrade {
+                level,
+                global: true,
+            } => f.write_fmt(format_args!("lvl {level} global")),
+            StatSource::Wave { wave } => f.write_fmt(format_args!("wave {wave}")),
+            StatSource::Synergy { level } => f.write_fmt(format_args!("lvl {level} synergy")),
+            StatSource::Curse { level } => f.write_fmt(format_args!("lvl {level} curse")),
+        }
+    }
+}
+
+/// How stat values are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatUnit {
+    Integer,
+    Number,
+    /// 0.1 is printed as 10%
+    Percent,
+}
+
+impl StatUnit {
+    pub fn format(&self, value: f32) -> String {
+        match self {
+            StatUnit::Integer => format!("{}", value as i32),
+            StatUnit::Number => format!("{value:.1}"),
+            StatUnit::Percent => format!("{:.1}%", value * 100.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatModifierKind {
+    /// Added to the base value
+    Flat(f32),
+    /// Multiplies the value. 0.1 is +10%
+    Percent(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatModifier {
+    pub source: StatSource,
+    pub kind: StatModifierKind,
+}
+
+/// Value with modifiers applied on top of it.
+/// Final value is `(base + flat) * (1 + percent)`.
+/// Buffs keep only modifiers, so their base is 0.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stat {
+    pub base: f32,
+    modifiers: Vec<StatModifier>,
+}
+
+impl Stat {
+    pub fn new(base: f32) -> Self {
+        Self {
+            base,
+            modifiers: vec![],
+        }
+    }
+
+    pub fn add_flat(&mut self, source: StatSource, value: f32) {
+        self.modifiers.push(StatModifier {
+            source,
+            kind: StatModifierKind::Flat(value),
+        });
+    }
+
+    pub fn add_percent(&mut self, source: StatSource, value: f32) {
+        self.modifiers.push(StatModifier {
+            source,
+            kind: StatModifierKind::Percent(value),
+        });
+    }
+
+    /// Removes all modifiers of the source
+    pub fn remove(&mut self, source: StatSource) {
+        self.modifiers.retain(|modifier| modifier.source != source);
+    }
+
+    /// Sum of all flat modifiers
+    pub fn flat(&self) -> f32 {
+        self.modifiers
+            .iter()
+            .map(|modifier| match modifier.kind {
+                StatModifierKind::Flat(value) => value,
+                StatModifierKind::Percent(_) => 0.0,
+            })
+            .sum()
+    }
+
+    /// Sum of all percent modifiers
+    pub fn percent(&self) -> f32 {
+        self.modifiers
+            .iter()
+            .map(|modifier| match modifier.kind {
+                StatModifierKind::Flat(_) => 0.0,
+                StatModifierKind::Percent(value) => value,
+            })
+            .sum()
+    }
+
+    pub fn value(&self) -> f32 {
+        (self.base + self.flat()) * (1.0 + self.percent())
+    }
+
+    /// Every modifier on its own line.
+    /// Flat modifiers are printed in the unit of the stat
+    pub fn modifiers_breakdown(&self, unit: StatUnit) -> String {
+        let mut breakdown = String::new();
+        for modifier in self.modifiers.iter() {
+            match modifier.kind {
+                StatModifierKind::Flat(value) => {
+                    let sign = if value < 0.0 { "-" } else { "+" };
+                    let value = unit.format(value.abs());
+                    _ = writeln!(breakdown, "{sign}{value} ({})", modifier.source);
+                }
+                StatModifierKind::Percent(value) => {
+                    let value = value * 100.0;
+                    _ = writeln!(breakdown, "{value:+.1}% ({})", modifier.source);
+                }
+            }
+        }
+        breakdown
+    }
+
+    /// Keeps the base and adds modifiers of the other stat
+    pub fn with(&self, other: &Stat) -> Self {
+        let mut stat = self.clone();
+        stat.modifiers.extend_from_slice(&other.modifiers);
+        stat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPGRADE: StatSource = StatSource::Upgrade {
+        level: 1,
+        global: false,
+    };
+    const GLOBAL_UPGRADE: StatSource = StatSource::Upgrade {
+        level: 1,
+        global: true,
+    };
+    const WAVE: StatSource = StatSource::Wave { wave: 1 };
+    const SYNERGY: StatSource = StatSource::Synergy { level: 1 };
+    const CURSE: StatSource = StatSource::Curse { level: 1 };
+
+    const ALL_SOURCES: [StatSource; 5] = [UPGRADE, GLOBAL_UPGRADE, WAVE, SYNERGY, CURSE];
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn value_without_modifiers_is_base() {
+        assert_near(Stat::new(10.0).value(), 10.0);
+        assert_near(Stat::default().value(), 0.0);
+    }
+
+    #[test]
+    fn flat_is_added_before_percent() {
+        let mut stat = Stat::new(10.0);
+        stat.add_flat(UPGRADE, 5.0);
+        stat.add_percent(WAVE, 0.2);
+        stat.add_percent(CURSE, 0.3);
+
+        assert_near(stat.flat(), 5.0);
+        assert_near(stat.percent(), 0.5);
+        assert_near(stat.value(), (10.0 + 5.0) * 1.5);
+    }
+
+    #[test]
+    fn negative_percent_lowers_value() {
+        let mut stat = Stat::new(2.0);
+        stat.add_percent(CURSE, -0.25);
+        assert_near(stat.value(), 1.5);
+    }
+
+    #[test]
+    fn remove_only_takes_modifiers_of_the_source() {
+        for source in ALL_SOURCES {
+            let mut stat = Stat::new(10.0);
+            for other in ALL_SOURCES {
+                stat.add_flat(other, 1.0);
+                stat.add_percent(other, 0.1);
+            }
+            stat.remove(source);
+
+            assert_near(stat.flat(), 4.0);
+            assert_near(stat.percent(), 0.4);
+            assert_near(stat.value(), 14.0 * 1.4);
+        }
+    }
+
+    #[test]
+    fn apply_and_remove_restores_value() {
+        for source in ALL_SOURCES {
+            let mut stat = Stat::new(10.0);
+            stat.add_flat(source, 3.0);
+            stat.add_percent(source, 0.5);
+            assert_near(stat.value(), 19.5);
+
+            stat.remove(source);
+            assert_eq!(stat, Stat::new(10.0));
+        }
+    }
+
+    #[test]
+    fn sources_of_other_levels_are_kept() {
+        let mut stat = Stat::new(10.0);
+        stat.add_flat(CURSE, 1.0);
+        stat.add_flat(StatSource::Curse { level: 2 }, 2.0);
+        stat.remove(CURSE);
+        assert_near(stat.value(), 12.0);
+    }
+
+    #[test]
+    fn with_keeps_base_and_adds_modifiers() {
+        let mut buffs = Stat::default();
+        buffs.add_flat(SYNERGY, 2.0);
+        buffs.add_percent(GLOBAL_UPGRADE, 1.0);
+
+        let stat = Stat::new(3.0).with(&buffs);
+        assert_near(stat.base, 3.0);
+        assert_near(stat.value(), 10.0);
+    }
+}
@@ -4,14 +4,23 @@ use bevy::prelude::*;
 
 use crate::{
     game::{
-        castle::CastleWall,
-        enemies::{spawn::EnemyBuffs, GlobalEnemyBuffs},
+        castle::{Castle, CastleWall},
+        enemies::{
+            spawn::{EnemyBuffs, EnemySpawn, EnemySpawnBundle, ExtraEnemySpawn},
+            GlobalEnemyBuffs,
+        },
+        rng::{self, GameRng},
+        stats::StatSource,
         weapons::{crossbow::CrossbowBuffs, molotov::MolotovBuffs, GlobalWeaponBuffs},
     },
     GlobalState,
 };
 
-use super::{East, GameState, North, Side, South, West, *};
+use super::{
+    options::LevelUpOptions,
+    pool::{BanishedUpgrades, UpgradePool},
+    East, GameState, North, Side, South, West, *,
+};
 
 pub struct ApplyUpgradesPlugin;
 
@@ -34,14 +43,26 @@ impl Plugin for ApplyUpgradesPlugin {
             .add_event::<EnemyUpgradeEvent<South>>()
             .add_event::<EnemyUpgradeEvent<West>>()
             .add_event::<EnemyUpgradeEvent<East>>()
+            .add_event::<LevelUpRewardEvent>()
+            .add_event::<GlobalSideEffectEvent>()
+            .add_event::<SideEffectEvent<North>>()
+            .add_event::<SideEffectEvent<South>>()
+            .add_event::<SideEffectEvent<West>>()
+            .add_event::<SideEffectEvent<East>>()
             .configure_set(UpgradeSet::Dispatch.before(UpgradeSet::Apply))
             .configure_set(UpgradeSet::Apply.before(UpgradeSet::Finish))
-            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_system(
+                setup
+                    .after(rng::reseed)
+                    .in_schedule(OnEnter(GlobalState::InGame)),
+            )
             .add_systems(
                 (
                     dispatch_wall_upgrades,
                     dispatch_weapon_upgrades,
+                    dispatch_level_up_rewards,
                     dispatch_enemy_upgrades,
+                    dispatch_side_effects,
                 )
                     .in_set(UpgradeSet::Dispatch)
                     .in_set(OnUpdate(GameState::LevelUp)),
@@ -67,6 +88,18 @@ impl Plugin for ApplyUpgradesPlugin {
                     .in_set(UpgradeSet::Apply)
                     .in_set(OnUpdate(GameState::LevelUp)),
             )
+            .add_systems(
+                (
+                    apply_level_up_rewards,
+                    apply_global_side_effects,
+                    apply_side_effects_to_side::<North>,
+                    apply_side_effects_to_side::<South>,
+                    apply_side_effects_to_side::<West>,
+                    apply_side_effects_to_side::<East>,
+                )
+                    .in_set(UpgradeSet::Apply)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            )
             .add_system(
                 finish_upgrade
                     .in_set(UpgradeSet::Finish)
@@ -76,7 +109,7 @@ impl Plugin for ApplyUpgradesPlugin {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
-enum UpgradeSet {
+pub enum UpgradeSet {
     Dispatch,
     Apply,
     Finish,
@@ -89,6 +122,18 @@ pub enum ApplyUpgradeEvent {
     Second = 1,
     Third = 2,
     Fourth = 3,
+    /// Evolution card
+    Fifth = 4,
+}
+
+impl ApplyUpgradeEvent {
+    pub const ALL: [ApplyUpgradeEvent; 5] = [
+        ApplyUpgradeEvent::First,
+        ApplyUpgradeEvent::Second,
+        ApplyUpgradeEvent::Third,
+        ApplyUpgradeEvent::Fourth,
+        ApplyUpgradeEvent::Fifth,
+    ];
 }
 
 pub struct FinishUpgradeEvent;
@@ -171,18 +216,88 @@ impl<S: Side> EnemyUpgradeEvent<S> {
     }
 }
 
-fn setup(mut commands: Commands) {
-    // generate new upgrades ahead of time
-    commands.insert_resource(genereate_upgrades());
+#[derive(Debug, Clone, Copy)]
+pub struct LevelUpRewardEvent {
+    reward: LevelUpReward,
+}
+
+impl LevelUpRewardEvent {
+    pub fn new(reward: LevelUpReward) -> Self {
+        Self { reward }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalSideEffectEvent {
+    side_effect: GlobalSideEffect,
+}
+
+impl GlobalSideEffectEvent {
+    pub fn new(side_effect: GlobalSideEffect) -> Self {
+        Self { side_effect }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SideEffectEvent<S: Side> {
+    side_effect: SideEffect,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> SideEffectEvent<S> {
+    pub fn new(side_effect: SideEffect) -> Self {
+        Self {
+            side_effect,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+fn setup(
+    upgrade_assets: Res<UpgradeAssets>,
+    upgrade_pools: Res<Assets<UpgradePool>>,
+    mut game_rng: ResMut<GameRng>,
+    mut commands: Commands,
+) {
+    // generate new upgrades ahead of time,
+    // nothing is banished at the start of the run
+    let upgrades = match upgrade_pools.get(&upgrade_assets.pool) {
+        Some(pool) => pool.generate_upgrades(&BanishedUpgrades::default(), &mut game_rng.upgrades),
+        None => {
+            warn!("Upgrade pool is not loaded");
+            Upgrades::default()
+        }
+    };
+    commands.insert_resource(upgrades);
 }
 
+/// Spends one pending level up.
+/// Level up menu opens again for the next pending level up.
 fn finish_upgrade(
-    finish_events: EventReader<FinishUpgradeEvent>,
+    upgrade_assets: Res<UpgradeAssets>,
+    upgrade_pools: Res<Assets<UpgradePool>>,
+    banished: Res<BanishedUpgrades>,
+    mut castle: Query<&mut Castle>,
+    mut finish_events: EventReader<FinishUpgradeEvent>,
+    mut game_rng: ResMut<GameRng>,
     mut upgrades: ResMut<Upgrades>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
-    if !finish_events.is_empty() {
-        *upgrades = genereate_upgrades();
+    if finish_events.is_empty() {
+        return;
+    }
+    // every applied part of the upgrade sends the event
+    finish_events.clear();
+
+    if let Some(pool) = upgrade_pools.get(&upgrade_assets.pool) {
+        *upgrades = pool.generate_upgrades(&banished, &mut game_rng.upgrades);
+    }
+
+    let mut castle = castle.single_mut();
+    castle.pending_level_ups = castle.pending_level_ups.saturating_sub(1);
+    if castle.pending_level_ups != 0 && !castle.bank_level_ups {
+        game_state.set(GameState::LevelUp);
+    } else {
         game_state.set(GameState::InGame);
     }
 }
@@ -327,99 +442,292 @@ fn apply_wall_upgrades_to_side<S: Side>(
 ) {
     let mut wall = wall.single_mut();
     for event in wall_upgrade_events.iter() {
-        match event.upgrade {
-            WallUpgrade::AdditionalMaxHp(value) => wall.add_max_hp(value),
-            WallUpgrade::Heal(value) => wall.heal(value),
-        }
+        apply_wall_upgrade(&mut wall, event.upgrade);
         finish_event.send(FinishUpgradeEvent);
     }
 }
 
+pub fn apply_wall_upgrade<S: Side>(wall: &mut CastleWall<S>, upgrade: WallUpgrade) {
+    match upgrade {
+        WallUpgrade::AdditionalMaxHp(value) => wall.add_max_hp(value),
+        WallUpgrade::Heal(value) => wall.heal(value),
+        WallUpgrade::MissileBlock(value) => wall.add_missile_block(value / 100.0),
+        WallUpgrade::MissileInterception(value) => wall.add_missile_interception(value / 100.0),
+    }
+}
+
 fn apply_global_weapon_upgrades(
+    castle: Query<&Castle>,
     mut global_weapons_buffs: ResMut<GlobalWeaponBuffs>,
     mut global_weapon_upgrade_events: EventReader<GlobalWeaponUpgradeEvent>,
     mut finish_event: EventWriter<FinishUpgradeEvent>,
 ) {
+    let source = StatSource::Upgrade {
+        level: castle.single().upgrade_level(),
+        global: true,
+    };
     for event in global_weapon_upgrade_events.iter() {
-        match event.upgrade {
-            GlobalWeaponUpgrade::Damage(value) => global_weapons_buffs.damage += value / 100.0,
-            GlobalWeaponUpgrade::DamageFlat(value) => global_weapons_buffs.damage_flat += value,
-            GlobalWeaponUpgrade::CritDamage(value) => {
-                global_weapons_buffs.crit_damage += value / 100.0
-            }
-            GlobalWeaponUpgrade::CritChance(value) => {
-                global_weapons_buffs.crit_chance += value / 100.0
-            }
-        }
+        apply_global_weapon_upgrade(&mut global_weapons_buffs, event.upgrade, source);
         finish_event.send(FinishUpgradeEvent);
     }
 }
 
+pub fn apply_global_weapon_upgrade(
+    buffs: &mut GlobalWeaponBuffs,
+    upgrade: GlobalWeaponUpgrade,
+    source: StatSource,
+) {
+    match upgrade {
+        GlobalWeaponUpgrade::Damage(value) => buffs.damage.add_percent(source, value / 100.0),
+        GlobalWeaponUpgrade::DamageFlat(value) => buffs.damage.add_flat(source, value as f32),
+        GlobalWeaponUpgrade::CritDamage(value) => buffs.crit_damage.add_flat(source, value / 100.0),
+        GlobalWeaponUpgrade::CritChance(value) => buffs.crit_chance.add_flat(source, value / 100.0),
+    }
+}
+
 fn apply_weapon_upgrades_to_side<S: Side>(
+    castle: Query<&Castle>,
     mut crossbow_buffs: ResMut<CrossbowBuffs<S>>,
     mut molotov_buffs: ResMut<MolotovBuffs<S>>,
     mut weapon_upgrade_events: EventReader<WeaponUpgradeEvent<S>>,
     mut finish_event: EventWriter<FinishUpgradeEvent>,
 ) {
+    let source = StatSource::Upgrade {
+        level: castle.single().upgrade_level(),
+        global: false,
+    };
     for event in weapon_upgrade_events.iter() {
-        match event.upgrade {
-            WeaponUpgrade::CrossbowDamage(value) => crossbow_buffs.damage += value / 100.0,
-            WeaponUpgrade::CrossbowDamageFlat(value) => crossbow_buffs.damage_flat += value,
-            WeaponUpgrade::CrossbowCritDamage(value) => crossbow_buffs.crit_damage += value / 100.0,
-            WeaponUpgrade::CrossbowCritChance(value) => crossbow_buffs.crit_chance += value / 100.0,
-            WeaponUpgrade::CrossbowRange(value) => crossbow_buffs.range += value / 100.0,
-            WeaponUpgrade::CrossbowAttackSpeed(value) => {
-                crossbow_buffs.attack_speed += value / 100.0
-            }
-            WeaponUpgrade::MolotovDamage(value) => molotov_buffs.damage += value / 100.0,
-            WeaponUpgrade::MolotovDamageFlat(value) => molotov_buffs.damage_flat += value,
-            WeaponUpgrade::MolotovCritDamage(value) => molotov_buffs.crit_damage += value / 100.0,
-            WeaponUpgrade::MolotovCritChance(value) => molotov_buffs.crit_chance += value / 100.0,
-            WeaponUpgrade::MolotovAreaSize(value) => molotov_buffs.area_size += value / 100.0,
-            WeaponUpgrade::MolotovAttackSpeed(value) => molotov_buffs.attack_speed += value / 100.0,
-            WeaponUpgrade::MolotovAreaAttackSpeed(value) => {
-                molotov_buffs.area_attack_speed += value
-            }
-            WeaponUpgrade::MolotovAreaLifespan(value) => {
-                molotov_buffs.area_lifespan += value / 100.0
-            }
-        }
+        apply_weapon_upgrade(
+            &mut crossbow_buffs,
+            &mut molotov_buffs,
+            event.upgrade,
+            source,
+        );
         finish_event.send(FinishUpgradeEvent);
     }
 }
 
+#[rustfmt::skip]
+pub fn apply_weapon_upgrade<S: Side>(
+    crossbow_buffs: &mut CrossbowBuffs<S>,
+    molotov_buffs: &mut MolotovBuffs<S>,
+    upgrade: WeaponUpgrade,
+    source: StatSource,
+) {
+    match upgrade {
+        WeaponUpgrade::CrossbowDamage(value) => crossbow_buffs.damage.add_percent(source, value / 100.0),
+        WeaponUpgrade::CrossbowDamageFlat(value) => crossbow_buffs.damage.add_flat(source, value as f32),
+        WeaponUpgrade::CrossbowCritDamage(value) => crossbow_buffs.crit_damage.add_flat(source, value / 100.0),
+        WeaponUpgrade::CrossbowCritChance(value) => crossbow_buffs.crit_chance.add_flat(source, value / 100.0),
+        WeaponUpgrade::CrossbowRange(value) => crossbow_buffs.range.add_percent(source, value / 100.0),
+        WeaponUpgrade::CrossbowAttackSpeed(value) => crossbow_buffs.attack_speed.add_percent(source, value / 100.0),
+        WeaponUpgrade::MolotovDamage(value) => molotov_buffs.damage.add_percent(source, value / 100.0),
+        WeaponUpgrade::MolotovDamageFlat(value) => molotov_buffs.damage.add_flat(source, value as f32),
+        WeaponUpgrade::MolotovCritDamage(value) => molotov_buffs.crit_damage.add_flat(source, value / 100.0),
+        WeaponUpgrade::MolotovCritChance(value) => molotov_buffs.crit_chance.add_flat(source, value / 100.0),
+        WeaponUpgrade::MolotovAreaSize(value) => molotov_buffs.area_size.add_percent(source, value / 100.0),
+        WeaponUpgrade::MolotovAttackSpeed(value) => molotov_buffs.attack_speed.add_percent(source, value / 100.0),
+        WeaponUpgrade::MolotovAreaAttackSpeed(value) => molotov_buffs.area_attack_speed.add_percent(source, value / 100.0),
+        WeaponUpgrade::MolotovAreaLifespan(value) => molotov_buffs.area_lifespan.add_percent(source, value / 100.0),
+    }
+}
+
 fn apply_global_enemy_upgrades(
+    castle: Query<&Castle>,
     mut global_enemy_buffs: ResMut<GlobalEnemyBuffs>,
     mut global_enemy_upgrade_events: EventReader<GlobalEnemyUpgradeEvent>,
     mut finish_event: EventWriter<FinishUpgradeEvent>,
 ) {
+    let source = StatSource::Upgrade {
+        level: castle.single().upgrade_level(),
+        global: true,
+    };
     for event in global_enemy_upgrade_events.iter() {
-        match event.upgrade {
-            GlobalEnemyUpgrade::Health(value) => global_enemy_buffs.health += value / 100.0,
-            GlobalEnemyUpgrade::Speed(value) => global_enemy_buffs.speed += value / 100.0,
-            GlobalEnemyUpgrade::Exp(value) => global_enemy_buffs.exp += value / 100.0,
-            GlobalEnemyUpgrade::Damage(value) => global_enemy_buffs.damage += value / 100.0,
-            GlobalEnemyUpgrade::AttackSpeed(value) => {
-                global_enemy_buffs.attack_speed += value / 100.0
-            }
-        }
+        apply_global_enemy_upgrade(&mut global_enemy_buffs, event.upgrade, source);
         finish_event.send(FinishUpgradeEvent);
     }
 }
 
+pub fn apply_global_enemy_upgrade(
+    buffs: &mut GlobalEnemyBuffs,
+    upgrade: GlobalEnemyUpgrade,
+    source: StatSource,
+) {
+    match upgrade {
+        GlobalEnemyUpgrade::Health(value) => buffs.health.add_percent(source, value / 100.0),
+        GlobalEnemyUpgrade::Speed(value) => buffs.speed.add_percent(source, value / 100.0),
+        GlobalEnemyUpgrade::Exp(value) => buffs.exp.add_percent(source, -value / 100.0),
+        GlobalEnemyUpgrade::Damage(value) => buffs.damage.add_percent(source, value / 100.0),
+        GlobalEnemyUpgrade::AttackSpeed(value) => {
+            buffs.attack_speed.add_percent(source, value / 100.0)
+        }
+    }
+}
+
 fn apply_enemy_upgrades_to_side<S: Side>(
+    castle: Query<&Castle>,
     mut enemy_spawn_buffs: ResMut<EnemyBuffs<S>>,
     mut enemy_upgrade_events: EventReader<EnemyUpgradeEvent<S>>,
     mut finish_event: EventWriter<FinishUpgradeEvent>,
 ) {
+    let source = StatSource::Upgrade {
+        level: castle.single().upgrade_level(),
+        global: false,
+    };
     for event in enemy_upgrade_events.iter() {
-        match event.upgrade {
-            EnemyUpgrade::Health(value) => enemy_spawn_buffs.health += value / 100.0,
-            EnemyUpgrade::Speed(value) => enemy_spawn_buffs.speed += value / 100.0,
-            EnemyUpgrade::Exp(value) => enemy_spawn_buffs.exp += value / 100.0,
-            EnemyUpgrade::Damage(value) => enemy_spawn_buffs.damage += value / 100.0,
-            EnemyUpgrade::AttackSpeed(value) => enemy_spawn_buffs.attack_speed += value / 100.0,
+        apply_enemy_upgrade(&mut enemy_spawn_buffs, event.upgrade, source);
+        finish_event.send(FinishUpgradeEvent);
+    }
+}
+
+pub fn apply_enemy_upgrade<S: Side>(
+    buffs: &mut EnemyBuffs<S>,
+    upgrade: EnemyUpgrade,
+    source: StatSource,
+) {
+    match upgrade {
+        EnemyUpgrade::Health(value) => buffs.health.add_percent(source, value / 100.0),
+        EnemyUpgrade::Speed(value) => buffs.speed.add_percent(source, value / 100.0),
+        EnemyUpgrade::Exp(value) => buffs.exp.add_percent(source, -value / 100.0),
+        EnemyUpgrade::Damage(value) => buffs.damage.add_percent(source, value / 100.0),
+        EnemyUpgrade::AttackSpeed(value) => buffs.attack_speed.add_percent(source, value / 100.0),
+    }
+}
+
+fn dispatch_level_up_rewards(
+    upgrades: Res<Upgrades>,
+    mut apply_upgrade_events: EventReader<ApplyUpgradeEvent>,
+    mut level_up_reward_event: EventWriter<LevelUpRewardEvent>,
+) {
+    for event in apply_upgrade_events.iter() {
+        let upgrade_to_apply = &upgrades.upgrades[*event as usize];
+
+        if let Some(reward) = upgrade_to_apply.level_up_reward {
+            level_up_reward_event.send(LevelUpRewardEvent::new(reward));
         }
+    }
+}
+
+fn apply_level_up_rewards(
+    mut level_up_options: ResMut<LevelUpOptions>,
+    mut level_up_reward_events: EventReader<LevelUpRewardEvent>,
+    mut finish_event: EventWriter<FinishUpgradeEvent>,
+) {
+    for event in level_up_reward_events.iter() {
+        apply_level_up_reward(&mut level_up_options, event.reward);
         finish_event.send(FinishUpgradeEvent);
     }
 }
+
+pub fn apply_level_up_reward(options: &mut LevelUpOptions, reward: LevelUpReward) {
+    match reward {
+        LevelUpReward::Rerolls(value) => options.rerolls += value,
+        LevelUpReward::Banishes(value) => options.banishes += value,
+    }
+}
+
+fn dispatch_side_effects(
+    upgrades: Res<Upgrades>,
+    mut apply_upgrade_events: EventReader<ApplyUpgradeEvent>,
+    mut global_side_effect_event: EventWriter<GlobalSideEffectEvent>,
+    mut side_effect_north_event: EventWriter<SideEffectEvent<North>>,
+    mut side_effect_south_event: EventWriter<SideEffectEvent<South>>,
+    mut side_effect_west_event: EventWriter<SideEffectEvent<West>>,
+    mut side_effect_east_event: EventWriter<SideEffectEvent<East>>,
+) {
+    for event in apply_upgrade_events.iter() {
+        let upgrade_to_apply = &upgrades.upgrades[*event as usize];
+
+        if let Some(side_effect) = upgrade_to_apply.global_side_effect {
+            global_side_effect_event.send(GlobalSideEffectEvent::new(side_effect))
+        }
+        if let Some(side_effect) = upgrade_to_apply.side_effect {
+            match side_effect {
+                UpgradeSide::North(side_effect) => {
+                    side_effect_north_event.send(SideEffectEvent::new(side_effect))
+                }
+                UpgradeSide::South(side_effect) => {
+                    side_effect_south_event.send(SideEffectEvent::new(side_effect))
+                }
+                UpgradeSide::West(side_effect) => {
+                    side_effect_west_event.send(SideEffectEvent::new(side_effect))
+                }
+                UpgradeSide::East(side_effect) => {
+                    side_effect_east_event.send(SideEffectEvent::new(side_effect))
+                }
+            }
+        }
+    }
+}
+
+fn apply_global_side_effects(
+    mut level_up_options: ResMut<LevelUpOptions>,
+    mut global_side_effect_events: EventReader<GlobalSideEffectEvent>,
+    mut finish_event: EventWriter<FinishUpgradeEvent>,
+) {
+    for event in global_side_effect_events.iter() {
+        apply_global_side_effect(&mut level_up_options, event.side_effect);
+        finish_event.send(FinishUpgradeEvent);
+    }
+}
+
+pub fn apply_global_side_effect(options: &mut LevelUpOptions, side_effect: GlobalSideEffect) {
+    match side_effect {
+        GlobalSideEffect::LevelUpCards(value) => {
+            options.cards = options
+                .cards
+                .saturating_sub(value as usize)
+                .max(LevelUpOptions::MIN_CARDS)
+        }
+    }
+}
+
+/// Extra spawn copies the enemy and interval of the current wave spawn on the side
+fn apply_side_effects_to_side<S: Side>(
+    castle: Query<&Castle>,
+    spawns: Query<&EnemySpawn<S>, Without<ExtraEnemySpawn>>,
+    mut wall: Query<&mut CastleWall<S>>,
+    mut crossbow_buffs: ResMut<CrossbowBuffs<S>>,
+    mut molotov_buffs: ResMut<MolotovBuffs<S>>,
+    mut enemy_buffs: ResMut<EnemyBuffs<S>>,
+    mut commands: Commands,
+    mut side_effect_events: EventReader<SideEffectEvent<S>>,
+    mut finish_event: EventWriter<FinishUpgradeEvent>,
+) {
+    let source = StatSource::Upgrade {
+        level: castle.single().upgrade_level(),
+        global: false,
+    };
+    let mut wall = wall.single_mut();
+    for event in side_effect_events.iter() {
+        match event.side_effect {
+            SideEffect::WeaponDamage(value) => {
+                weaken_weapons(&mut crossbow_buffs, &mut molotov_buffs, value, source)
+            }
+            SideEffect::WallMaxHpDrain(value) => wall.max_hp_drain += value,
+            SideEffect::ExtraSpawn(value) => match spawns.iter().next() {
+                Some(spawn) => {
+                    commands.spawn((
+                        EnemySpawnBundle::new(spawn.with_number(value)),
+                        ExtraEnemySpawn,
+                    ));
+                }
+                None => warn!("No enemy spawn on {:?} to copy", S::default()),
+            },
+            SideEffect::EliteChance(value) => {
+                enemy_buffs.elite_chance.add_flat(source, value / 100.0)
+            }
+        }
+        finish_event.send(FinishUpgradeEvent);
+    }
+}
+
+pub fn weaken_weapons<S: Side>(
+    crossbow_buffs: &mut CrossbowBuffs<S>,
+    molotov_buffs: &mut MolotovBuffs<S>,
+    value: f32,
+    source: StatSource,
+) {
+    crossbow_buffs.damage.add_percent(source, -value / 100.0);
+    molotov_buffs.damage.add_percent(source, -value / 100.0);
+}
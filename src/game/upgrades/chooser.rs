@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::game::{castle::CastleWall, rng::GameRng, East, GameState, North, Side, South, West};
+
+use super::{apply::ApplyUpgradeEvent, options::LevelUpOptions, Upgrade, UpgradeSide, Upgrades};
+
+pub struct UpgradeChooserPlugin;
+
+impl Plugin for UpgradeChooserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UpgradeChooser>()
+            .add_system(choose_upgrade.in_schedule(OnEnter(GameState::LevelUp)));
+    }
+}
+
+/// Strategy used to pick upgrades on level up
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum UpgradeChooser {
+    /// Player picks upgrades in the level up menu
+    #[default]
+    Manual,
+    Random,
+    /// Prefers upgrades for the wall with the lowest hp
+    GreedySideHp,
+    AlwaysFirst,
+}
+
+impl UpgradeChooser {
+    /// Parses the `--chooser` argument value
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "manual" => Some(Self::Manual),
+            "random" => Some(Self::Random),
+            "greedy" => Some(Self::GreedySideHp),
+            "first" => Some(Self::AlwaysFirst),
+            _ => None,
+        }
+    }
+}
+
+pub fn choose_upgrade(
+    chooser: Res<UpgradeChooser>,
+    upgrades: Res<Upgrades>,
+    options: Res<LevelUpOptions>,
+    north_wall: Query<&CastleWall<North>>,
+    south_wall: Query<&CastleWall<South>>,
+    west_wall: Query<&CastleWall<West>>,
+    east_wall: Query<&CastleWall<East>>,
+    mut game_rng: ResMut<GameRng>,
+    mut apply_upgrade_events: EventWriter<ApplyUpgradeEvent>,
+) {
+    let offered: Vec<usize> = upgrades.offered(options.offered_cards()).collect();
+    let index = match *chooser {
+        UpgradeChooser::Manual => return,
+        UpgradeChooser::Random => offered[game_rng.chooser.gen_range(0..offered.len())],
+        UpgradeChooser::GreedySideHp => {
+            let hp = [
+                hp_ratio(north_wall.single()),
+                hp_ratio(south_wall.single()),
+                hp_ratio(west_wall.single()),
+                hp_ratio(east_wall.single()),
+            ];
+            let weakest = (0..hp.len())
+                .min_by(|a, b| hp[*a].total_cmp(&hp[*b]))
+                .unwrap_or_default();
+            // first upgrade wins on equal score
+            offered
+                .iter()
+                .rev()
+                .max_by_key(|index| greedy_score(&upgrades.upgrades[**index], weakest))
+                .copied()
+                .unwrap_or_default()
+        }
+        UpgradeChooser::AlwaysFirst => 0,
+    };
+    apply_upgrade_events.send(ApplyUpgradeEvent::ALL[index]);
+}
+
+fn hp_ratio<S: Side>(wall: &CastleWall<S>) -> f32 {
+    wall.health as f32 / wall.max_health as f32
+}
+
+fn side_index<U>(upgrade_side: &UpgradeSide<U>) -> usize {
+    match upgrade_side {
+        UpgradeSide::North(_) => 0,
+        UpgradeSide::South(_) => 1,
+        UpgradeSide::West(_) => 2,
+        UpgradeSide::East(_) => 3,
+    }
+}
+
+/// How much the upgrade helps the side
+fn greedy_score(upgrade: &Upgrade, side: usize) -> i32 {
+    let mut score = 0;
+    if upgrade.global_wall_upgrade.is_some() {
+        score += 1;
+    }
+    if upgrade.global_weapon_upgrade.is_some() {
+        score += 1;
+    }
+    if upgrade.level_up_reward.is_some() {
+        score += 1;
+    }
+    if upgrade
+        .wall_upgrade
+        .is_some_and(|upgrade| side_index(&upgrade) == side)
+    {
+        score += 3;
+    }
+    if upgrade
+        .weapon_upgrade
+        .is_some_and(|upgrade| side_index(&upgrade) == side)
+    {
+        score += 2;
+    }
+    if upgrade
+        .evolution
+        .is_some_and(|evolution| side_index(&evolution) == side)
+    {
+        score += 3;
+    }
+    if upgrade.global_enemy_upgrade.is_some() {
+        score -= 1;
+    }
+    if upgrade
+        .enemy_upgrade
+        .is_some_and(|upgrade| side_index(&upgrade) == side)
+    {
+        score -= 2;
+    }
+    if upgrade
+        .curse
+        .is_some_and(|curse| side_index(&curse) == side)
+    {
+        score -= 2;
+    }
+    if upgrade.global_side_effect.is_some() {
+        score -= 1;
+    }
+    if upgrade
+        .side_effect
+        .is_some_and(|side_effect| side_index(&side_effect) == side)
+    {
+        score -= 2;
+    }
+    score
+}
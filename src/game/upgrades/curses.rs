@@ -0,0 +1,298 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    game::{
+        castle::Castle,
+        damage::WallDamageEvent,
+        enemies::{
+            definitions::EnemyDefinitions, refresh_enemies, spawn::EnemyBuffs, EnemyAssets,
+            GlobalEnemyBuffs, RefreshedEnemies,
+        },
+        stats::StatSource,
+    },
+    utils::remove_all_with,
+    GlobalState,
+};
+
+use super::{
+    apply::{ApplyUpgradeEvent, FinishUpgradeEvent, UpgradeSet},
+    Curse, CurseEffect, East, GameState, North, Side, South, UpgradeSide, Upgrades, West,
+};
+
+pub struct CursesPlugin;
+
+impl Plugin for CursesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CurseEvent<North>>()
+            .add_event::<CurseEvent<South>>()
+            .add_event::<CurseEvent<West>>()
+            .add_event::<CurseEvent<East>>()
+            .add_system(
+                dispatch_curses
+                    .in_set(UpgradeSet::Dispatch)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            )
+            .add_systems(
+                (
+                    start_curses::<North>,
+                    start_curses::<South>,
+                    start_curses::<West>,
+                    start_curses::<East>,
+                )
+                    .in_set(UpgradeSet::Apply)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            )
+            .add_systems(
+                (
+                    update_curses::<North>,
+                    update_curses::<South>,
+                    update_curses::<West>,
+                    update_curses::<East>,
+                )
+                    .in_set(OnUpdate(GameState::InGame)),
+            )
+            .add_system(remove_all_with::<CurseMarker>.in_schedule(OnExit(GlobalState::InGame)));
+    }
+}
+
+/// When the curse is removed
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum CurseExpiry {
+    /// Seconds of the game time
+    Time(f32),
+    /// Enemies killed on any side after the curse was picked
+    Kills(u32),
+}
+
+impl std::fmt::Display for CurseExpiry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Time(seconds) => f.write_fmt(format_args!("for {seconds:.0}s")),
+            Self::Kills(kills) => f.write_fmt(format_args!("until {kills} kills")),
+        }
+    }
+}
+
+/// Curse grows stronger while it is active
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CurseEscalation {
+    /// Seconds between escalations
+    pub interval: f32,
+    /// Part of the initial value added on every escalation
+    pub growth: f32,
+    /// Curse stops growing after this many escalations
+    pub max_steps: u32,
+}
+
+impl std::fmt::Display for CurseEscalation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "+{:.0}% every {:.0}s up to {} times",
+            self.growth * 100.0,
+            self.interval,
+            self.max_steps
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CurseEvent<S: Side> {
+    curse: Curse,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> CurseEvent<S> {
+    pub fn new(curse: Curse) -> Self {
+        Self {
+            curse,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug, Default, Component)]
+pub struct CurseMarker;
+
+/// Curse picked on the level up and not expired yet
+#[derive(Debug, Component)]
+pub struct ActiveCurse<S: Side> {
+    pub curse: Curse,
+    /// Enemy buff modifiers of the curse use this source
+    pub source: StatSource,
+    /// Game time since the curse was picked
+    pub elapsed: f32,
+    /// Castle kills when the curse was picked
+    kills: u32,
+    /// Escalations applied to enemy buffs.
+    /// `None` until the curse is applied for the first time
+    applied_escalations: Option<u32>,
+    /// Part of the hp drained, but not dealt to the wall yet
+    drained: f32,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> ActiveCurse<S> {
+    pub fn new(curse: Curse, level: u32, kills: u32) -> Self {
+        Self {
+            curse,
+            source: StatSource::Curse { level },
+            elapsed: 0.0,
+            kills,
+            applied_escalations: None,
+            drained: 0.0,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn escalations(&self) -> u32 {
+        match self.curse.escalation {
+            Some(escalation) if 0.0 < escalation.interval => {
+                ((self.elapsed / escalation.interval) as u32).min(escalation.max_steps)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Effect with all escalations
+    pub fn effect(&self) -> CurseEffect {
+        let growth = self
+            .curse
+            .escalation
+            .map(|escalation| escalation.growth)
+            .unwrap_or_default();
+        let value = self.curse.effect.value() * (1.0 + growth * self.escalations() as f32);
+        self.curse.effect.kind().with_value(value)
+    }
+
+    fn is_expired(&self, kills: u32) -> bool {
+        match self.curse.expiry {
+            CurseExpiry::Time(seconds) => seconds <= self.elapsed,
+            CurseExpiry::Kills(target) => target <= kills.saturating_sub(self.kills),
+        }
+    }
+
+    /// Time or kills left until the curse expires
+    pub fn remaining(&self, kills: u32) -> String {
+        match self.curse.expiry {
+            CurseExpiry::Time(seconds) => format!("{:.0}s", (seconds - self.elapsed).max(0.0)),
+            CurseExpiry::Kills(target) => format!(
+                "{} kills",
+                target.saturating_sub(kills.saturating_sub(self.kills))
+            ),
+        }
+    }
+}
+
+fn dispatch_curses(
+    upgrades: Res<Upgrades>,
+    mut apply_upgrade_events: EventReader<ApplyUpgradeEvent>,
+    mut curse_north_event: EventWriter<CurseEvent<North>>,
+    mut curse_south_event: EventWriter<CurseEvent<South>>,
+    mut curse_west_event: EventWriter<CurseEvent<West>>,
+    mut curse_east_event: EventWriter<CurseEvent<East>>,
+) {
+    for event in apply_upgrade_events.iter() {
+        let upgrade_to_apply = &upgrades.upgrades[*event as usize];
+
+        if let Some(curse) = upgrade_to_apply.curse {
+            match curse {
+                UpgradeSide::North(curse) => curse_north_event.send(CurseEvent::new(curse)),
+                UpgradeSide::South(curse) => curse_south_event.send(CurseEvent::new(curse)),
+                UpgradeSide::West(curse) => curse_west_event.send(CurseEvent::new(curse)),
+                UpgradeSide::East(curse) => curse_east_event.send(CurseEvent::new(curse)),
+            }
+        }
+    }
+}
+
+/// Curse takes effect when the game continues
+fn start_curses<S: Side>(
+    castle: Query<&Castle>,
+    mut commands: Commands,
+    mut curse_events: EventReader<CurseEvent<S>>,
+    mut finish_event: EventWriter<FinishUpgradeEvent>,
+) {
+    let castle = castle.single();
+    for event in curse_events.iter() {
+        commands.spawn((
+            ActiveCurse::<S>::new(event.curse, castle.upgrade_level(), castle.kills),
+            CurseMarker,
+        ));
+        finish_event.send(FinishUpgradeEvent);
+    }
+}
+
+/// Escalates, expires and drains walls with curses.
+/// Enemies on the field get new stats when enemy buffs change.
+fn update_curses<S: Side>(
+    time: Res<Time>,
+    castle: Query<&Castle>,
+    enemy_assets: Res<EnemyAssets>,
+    enemy_definitions: Res<Assets<EnemyDefinitions>>,
+    global_buffs: Res<GlobalEnemyBuffs>,
+    mut buffs: ResMut<EnemyBuffs<S>>,
+    mut curses: Query<(Entity, &mut ActiveCurse<S>)>,
+    mut enemies: RefreshedEnemies<S>,
+    mut commands: Commands,
+    mut damage_events: EventWriter<WallDamageEvent<S>>,
+) {
+    let kills = castle.single().kills;
+    let mut buffs_changed = false;
+    for (entity, mut curse) in curses.iter_mut() {
+        curse.elapsed += time.delta_seconds();
+
+        if curse.is_expired(kills) {
+            buffs.remove(curse.source);
+            buffs_changed = true;
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let effect = curse.effect();
+        let escalations = curse.escalations();
+        if curse.applied_escalations != Some(escalations) {
+            curse.applied_escalations = Some(escalations);
+            buffs.remove(curse.source);
+            buffs_changed |= apply_curse_effect(&mut buffs, effect, curse.source);
+        }
+
+        if let CurseEffect::WallDrain(value) = effect {
+            curse.drained += value * time.delta_seconds();
+            let damage = curse.drained.floor();
+            if 1.0 <= damage {
+                curse.drained -= damage;
+                damage_events.send(WallDamageEvent::new(damage as i32));
+            }
+        }
+    }
+
+    if !buffs_changed {
+        return;
+    }
+    let Some(definitions) = enemy_definitions.get(&enemy_assets.definitions) else {
+        warn!("Enemy definitions are not loaded");
+        return;
+    };
+    refresh_enemies(definitions, &global_buffs, &buffs, &mut enemies);
+}
+
+/// Returns true if enemy buffs were changed
+fn apply_curse_effect<S: Side>(
+    buffs: &mut EnemyBuffs<S>,
+    effect: CurseEffect,
+    source: StatSource,
+) -> bool {
+    match effect {
+        CurseEffect::EnemyHealth(value) => buffs.health.add_percent(source, value / 100.0),
+        CurseEffect::EnemySpeed(value) => buffs.speed.add_percent(source, value / 100.0),
+        CurseEffect::EnemyDamage(value) => buffs.damage.add_percent(source, value / 100.0),
+        CurseEffect::EnemyAttackSpeed(value) => {
+            buffs.attack_speed.add_percent(source, value / 100.0)
+        }
+        CurseEffect::WallDrain(_) => return false,
+    }
+    true
+}
@@ -0,0 +1,207 @@
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::game::{
+    castle::CastleWall,
+    weapons::{
+        crossbow::{Crossbow, CrossbowBuffs},
+        molotov::{Molotov, MolotovBuffs},
+        EvolutionStats, WeaponStats, WeaponsAssets,
+    },
+};
+
+use super::{
+    apply::{ApplyUpgradeEvent, FinishUpgradeEvent, UpgradeSet},
+    chooser, options,
+    pool::UpgradeRarity,
+    East, GameState, North, Side, South, Upgrade, UpgradeSide, Upgrades, WeaponEvolution, West,
+};
+
+pub struct EvolutionPlugin;
+
+impl Plugin for EvolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EvolveWeaponEvent<North>>()
+            .add_event::<EvolveWeaponEvent<South>>()
+            .add_event::<EvolveWeaponEvent<West>>()
+            .add_event::<EvolveWeaponEvent<East>>()
+            .add_system(
+                offer_evolution
+                    .before(chooser::choose_upgrade)
+                    .in_schedule(OnEnter(GameState::LevelUp)),
+            )
+            // rerolls and banishes generate new upgrades
+            .add_system(
+                offer_evolution
+                    .after(options::use_level_up_options)
+                    .before(UpgradeSet::Dispatch)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            )
+            .add_system(
+                dispatch_evolutions
+                    .in_set(UpgradeSet::Dispatch)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            )
+            .add_systems(
+                (
+                    evolve_weapon::<North>,
+                    evolve_weapon::<South>,
+                    evolve_weapon::<West>,
+                    evolve_weapon::<East>,
+                )
+                    .in_set(UpgradeSet::Apply)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EvolveWeaponEvent<S: Side> {
+    evolution: WeaponEvolution,
+    _phantom: PhantomData<S>,
+}
+
+impl<S: Side> EvolveWeaponEvent<S> {
+    pub fn new(evolution: WeaponEvolution) -> Self {
+        Self {
+            evolution,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Weapons of the side that are not evolved yet
+#[derive(SystemParam)]
+struct SideWeapons<'w, 's, S: Side> {
+    crossbow: Query<'w, 's, (), With<Crossbow<S>>>,
+    molotov: Query<'w, 's, (), With<Molotov<S>>>,
+    crossbow_buffs: Res<'w, CrossbowBuffs<S>>,
+    molotov_buffs: Res<'w, MolotovBuffs<S>>,
+}
+
+impl<'w, 's, S: Side> SideWeapons<'w, 's, S> {
+    fn evolution(&self, stats: &EvolutionStats) -> Option<WeaponEvolution> {
+        if !self.crossbow.is_empty() && stats.repeating_crossbow.can_evolve(&self.crossbow_buffs) {
+            Some(WeaponEvolution::RepeatingCrossbow)
+        } else if !self.molotov.is_empty() && stats.napalm_molotov.can_evolve(&self.molotov_buffs) {
+            Some(WeaponEvolution::NapalmMolotov)
+        } else {
+            None
+        }
+    }
+}
+
+/// Puts the evolution card into the extra slot
+/// if any weapon reached the evolution thresholds.
+/// Only one evolution is offered at a time.
+/// Runs before `choose_upgrade` on entering the level up,
+/// so auto choosers see the evolution on the same frame.
+fn offer_evolution(
+    weapons_assets: Res<WeaponsAssets>,
+    weapon_stats: Res<Assets<WeaponStats>>,
+    north: SideWeapons<North>,
+    south: SideWeapons<South>,
+    west: SideWeapons<West>,
+    east: SideWeapons<East>,
+    mut upgrades: ResMut<Upgrades>,
+) {
+    if upgrades.upgrades[Upgrades::EVOLUTION].evolution.is_some() {
+        return;
+    }
+    let Some(weapon_stats) = weapon_stats.get(&weapons_assets.stats) else {
+        warn!("Weapon stats are not loaded");
+        return;
+    };
+
+    let stats = &weapon_stats.evolutions;
+    let evolution = north
+        .evolution(stats)
+        .map(UpgradeSide::North)
+        .or_else(|| south.evolution(stats).map(UpgradeSide::South))
+        .or_else(|| west.evolution(stats).map(UpgradeSide::West))
+        .or_else(|| east.evolution(stats).map(UpgradeSide::East));
+
+    if let Some(evolution) = evolution {
+        upgrades.upgrades[Upgrades::EVOLUTION] = Upgrade {
+            evolution: Some(evolution),
+            rarity: UpgradeRarity::Epic,
+            ..default()
+        };
+    }
+}
+
+fn dispatch_evolutions(
+    upgrades: Res<Upgrades>,
+    mut apply_upgrade_events: EventReader<ApplyUpgradeEvent>,
+    mut evolve_north_event: EventWriter<EvolveWeaponEvent<North>>,
+    mut evolve_south_event: EventWriter<EvolveWeaponEvent<South>>,
+    mut evolve_west_event: EventWriter<EvolveWeaponEvent<West>>,
+    mut evolve_east_event: EventWriter<EvolveWeaponEvent<East>>,
+) {
+    for event in apply_upgrade_events.iter() {
+        let upgrade_to_apply = &upgrades.upgrades[*event as usize];
+
+        if let Some(evolution) = upgrade_to_apply.evolution {
+            match evolution {
+                UpgradeSide::North(evolution) => {
+                    evolve_north_event.send(EvolveWeaponEvent::new(evolution))
+                }
+                UpgradeSide::South(evolution) => {
+                    evolve_south_event.send(EvolveWeaponEvent::new(evolution))
+                }
+                UpgradeSide::West(evolution) => {
+                    evolve_west_event.send(EvolveWeaponEvent::new(evolution))
+                }
+                UpgradeSide::East(evolution) => {
+                    evolve_east_event.send(EvolveWeaponEvent::new(evolution))
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the weapon on the wall with the evolved one.
+/// Evolved weapon keeps stats of the replaced weapon.
+fn evolve_weapon<S: Side>(
+    weapons_assets: Res<WeaponsAssets>,
+    weapon_stats: Res<Assets<WeaponStats>>,
+    walls: Query<(Entity, Option<&Crossbow<S>>, Option<&Molotov<S>>), With<CastleWall<S>>>,
+    mut commands: Commands,
+    mut evolve_events: EventReader<EvolveWeaponEvent<S>>,
+    mut finish_event: EventWriter<FinishUpgradeEvent>,
+) {
+    let default_stats = EvolutionStats::default();
+    let stats = match weapon_stats.get(&weapons_assets.stats) {
+        Some(weapon_stats) => &weapon_stats.evolutions,
+        None => {
+            warn!("Weapon stats are not loaded");
+            &default_stats
+        }
+    };
+
+    for event in evolve_events.iter() {
+        for (entity, crossbow, molotov) in walls.iter() {
+            match event.evolution {
+                WeaponEvolution::RepeatingCrossbow => {
+                    if let Some(crossbow) = crossbow {
+                        commands.entity(entity).remove::<Crossbow<S>>().insert(
+                            stats
+                                .repeating_crossbow
+                                .repeating_crossbow(crossbow.clone()),
+                        );
+                    }
+                }
+                WeaponEvolution::NapalmMolotov => {
+                    if let Some(molotov) = molotov {
+                        commands
+                            .entity(entity)
+                            .remove::<Molotov<S>>()
+                            .insert(stats.napalm_molotov.napalm_molotov(molotov.clone()));
+                    }
+                }
+            }
+        }
+        finish_event.send(FinishUpgradeEvent);
+    }
+}
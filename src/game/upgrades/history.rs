@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{castle::Castle, GameTime},
+    GlobalState,
+};
+
+use super::{
+    apply::{ApplyUpgradeEvent, UpgradeSet},
+    GameState, Upgrade, Upgrades,
+};
+
+pub struct UpgradeHistoryPlugin;
+
+impl Plugin for UpgradeHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_system(
+                record_upgrades
+                    .in_set(UpgradeSet::Dispatch)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            );
+    }
+}
+
+/// Upgrade picked on level up
+#[derive(Debug, Clone)]
+pub struct UpgradeRecord {
+    pub level: u32,
+    /// Game time of the pick in seconds
+    pub time: f32,
+    pub upgrade: Upgrade,
+}
+
+impl UpgradeRecord {
+    /// All buffs of the upgrade in one line
+    pub fn buffs(&self) -> String {
+        let upgrade = &self.upgrade;
+        let mut buffs = vec![];
+        if let Some(upgrade) = upgrade.global_wall_upgrade {
+            buffs.push(format!("global {upgrade}"));
+        }
+        if let Some(upgrade) = upgrade.wall_upgrade {
+            buffs.push(format!("{} wall {}", upgrade.name(), upgrade.upgrade()));
+        }
+        if let Some(upgrade) = upgrade.global_weapon_upgrade {
+            buffs.push(format!("global {upgrade}"));
+        }
+        if let Some(upgrade) = upgrade.weapon_upgrade {
+            buffs.push(format!("{} {}", upgrade.name(), upgrade.upgrade()));
+        }
+        if let Some(evolution) = upgrade.evolution {
+            buffs.push(format!("{} {}", evolution.name(), evolution.upgrade()));
+        }
+        if let Some(reward) = upgrade.level_up_reward {
+            buffs.push(format!("level up {reward}"));
+        }
+        buffs.join(", ")
+    }
+
+    /// All side effects of the upgrade in one line
+    pub fn side_effects(&self) -> String {
+        let upgrade = &self.upgrade;
+        let mut side_effects = vec![];
+        if let Some(upgrade) = upgrade.global_enemy_upgrade {
+            side_effects.push(format!("global enemies {upgrade}"));
+        }
+        if let Some(upgrade) = upgrade.enemy_upgrade {
+            side_effects.push(format!("{} enemies {}", upgrade.name(), upgrade.upgrade()));
+        }
+        if let Some(curse) = upgrade.curse {
+            side_effects.push(format!("{} curse {}", curse.name(), curse.upgrade()));
+        }
+        if let Some(side_effect) = upgrade.global_side_effect {
+            side_effects.push(format!("global {side_effect}"));
+        }
+        if let Some(side_effect) = upgrade.side_effect {
+            side_effects.push(format!("{} {}", side_effect.name(), side_effect.upgrade()));
+        }
+        side_effects.join(", ")
+    }
+}
+
+impl std::fmt::Display for UpgradeRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "lvl {} at {:.1}s: {}",
+            self.level,
+            self.time,
+            self.buffs()
+        ))?;
+        let side_effects = self.side_effects();
+        if !side_effects.is_empty() {
+            f.write_fmt(format_args!(" | side effects: {side_effects}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// All upgrades picked in the current run
+#[derive(Debug, Default, Resource)]
+pub struct UpgradeHistory {
+    pub records: Vec<UpgradeRecord>,
+}
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(UpgradeHistory::default());
+}
+
+fn record_upgrades(
+    game_time: Res<GameTime>,
+    upgrades: Res<Upgrades>,
+    castle: Query<&Castle>,
+    mut history: ResMut<UpgradeHistory>,
+    mut apply_upgrade_events: EventReader<ApplyUpgradeEvent>,
+) {
+    let level = castle.single().upgrade_level();
+    for event in apply_upgrade_events.iter() {
+        history.records.push(UpgradeRecord {
+            level,
+            time: game_time.elapsed,
+            upgrade: upgrades.upgrades[*event as usize],
+        });
+    }
+}
@@ -1,28 +1,61 @@
-use std::marker::PhantomData;
+use std::{any::TypeId, marker::PhantomData};
 
 use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
 
-use super::{East, GameState, North, Side, South, West};
+use crate::GlobalState;
+
+use self::{
+    curses::{CurseEscalation, CurseExpiry},
+    pool::{UpgradePool, UpgradeRarity},
+    synergies::SynergyDefinitions,
+};
+
+use super::{data::RonAssetLoader, East, GameState, North, Side, South, West};
 
 pub mod apply;
+pub mod chooser;
+pub mod curses;
+pub mod evolution;
+pub mod history;
+pub mod options;
+pub mod pool;
+pub mod preview;
+pub mod synergies;
 
 pub struct UpgradesPlugin;
 
 impl Plugin for UpgradesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(apply::ApplyUpgradesPlugin);
+        app.add_asset::<UpgradePool>()
+            .add_asset_loader(RonAssetLoader::<UpgradePool>::new(&["upgrades.ron"]))
+            .add_asset::<SynergyDefinitions>()
+            .add_asset_loader(RonAssetLoader::<SynergyDefinitions>::new(&[
+                "synergies.ron",
+            ]))
+            .add_collection_to_loading_state::<_, UpgradeAssets>(GlobalState::AssetLoading)
+            .add_plugin(apply::ApplyUpgradesPlugin)
+            .add_plugin(chooser::UpgradeChooserPlugin)
+            .add_plugin(options::LevelUpOptionsPlugin)
+            .add_plugin(history::UpgradeHistoryPlugin)
+            .add_plugin(synergies::SynergiesPlugin)
+            .add_plugin(evolution::EvolutionPlugin)
+            .add_plugin(curses::CursesPlugin);
     }
 }
 
-macro_rules! random_upgrade {
-    ($name:ident, $variant:ident, $type:tt, $min:expr, $max:expr) => {
-        pub fn $name(rng: &mut impl rand::Rng) -> Self {
-            const MIN: $type = $min;
-            const MAX: $type = $max;
-            let value = rng.gen_range(MIN..=MAX);
-            Self::$variant(value)
-        }
-    };
+#[derive(AssetCollection, Resource, Default)]
+pub struct UpgradeAssets {
+    #[asset(path = "data/game.upgrades.ron")]
+    pub pool: Handle<UpgradePool>,
+    #[asset(path = "data/game.synergies.ron")]
+    pub synergies: Handle<SynergyDefinitions>,
+}
+
+impl UpgradeAssets {
+    /// Used to read data when asset loading is skipped
+    pub const POOL_PATH: &str = "data/game.upgrades.ron";
+    pub const SYNERGIES_PATH: &str = "data/game.synergies.ron";
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -31,11 +64,6 @@ pub enum GlobalWallUpgrade {
     Heal(i32),
 }
 
-impl GlobalWallUpgrade {
-    random_upgrade!(additional_max_hp, AdditionalMaxHp, i32, 10, 90);
-    random_upgrade!(heal, Heal, i32, 20, 100);
-}
-
 impl std::fmt::Display for GlobalWallUpgrade {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -50,11 +78,10 @@ impl std::fmt::Display for GlobalWallUpgrade {
 pub enum WallUpgrade {
     AdditionalMaxHp(i32),
     Heal(i32),
-}
-
-impl WallUpgrade {
-    random_upgrade!(additional_max_hp, AdditionalMaxHp, i32, 20, 120);
-    random_upgrade!(heal, Heal, i32, 40, 150);
+    /// Chance to block missiles of ranged enemies
+    MissileBlock(f32),
+    /// Chance to shoot down missiles before they hit the wall
+    MissileInterception(f32),
 }
 
 impl std::fmt::Display for WallUpgrade {
@@ -62,6 +89,12 @@ impl std::fmt::Display for WallUpgrade {
         match self {
             Self::AdditionalMaxHp(value) => f.write_fmt(format_args!("max hx: +{value}"))?,
             Self::Heal(value) => f.write_fmt(format_args!("heal: {value}"))?,
+            Self::MissileBlock(value) => {
+                f.write_fmt(format_args!("missile block: +{value:.1}%"))?
+            }
+            Self::MissileInterception(value) => {
+                f.write_fmt(format_args!("missile interception: +{value:.1}%"))?
+            }
         }
         Ok(())
     }
@@ -75,13 +108,6 @@ pub enum GlobalWeaponUpgrade {
     CritChance(f32),
 }
 
-impl GlobalWeaponUpgrade {
-    random_upgrade!(damage, Damage, f32, 5.0, 20.0);
-    random_upgrade!(damage_flat, DamageFlat, i32, 20, 50);
-    random_upgrade!(crit_dmamge, CritDamage, f32, 5.0, 20.0);
-    random_upgrade!(crit_chance, CritChance, f32, 3.0, 15.0);
-}
-
 impl std::fmt::Display for GlobalWeaponUpgrade {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -113,30 +139,6 @@ pub enum WeaponUpgrade {
     MolotovAreaLifespan(f32),
 }
 
-impl WeaponUpgrade {
-    random_upgrade!(crossbow_damage, CrossbowDamage, f32, 3.0, 15.0);
-    random_upgrade!(crossbow_damage_flat, CrossbowDamageFlat, i32, 10, 80);
-    random_upgrade!(crossbow_crit_damage, CrossbowCritDamage, f32, 5.0, 15.0);
-    random_upgrade!(crossbow_crit_chance, CrossbowCritChance, f32, 5.0, 20.0);
-    random_upgrade!(crossbow_range, CrossbowRange, f32, 10.0, 100.0);
-    random_upgrade!(crossbow_attack_speed, CrossbowAttackSpeed, f32, 5.0, 50.0);
-
-    random_upgrade!(molotov_damage, MolotovDamage, f32, 3.0, 20.0);
-    random_upgrade!(molotov_damage_flat, MolotovDamageFlat, i32, 5, 50);
-    random_upgrade!(molotov_crit_damage, MolotovCritDamage, f32, 5.0, 10.0);
-    random_upgrade!(molotov_crit_chance, MolotovCritChance, f32, 10.0, 30.0);
-    random_upgrade!(molotov_area_size, MolotovAreaSize, f32, 5.0, 25.0);
-    random_upgrade!(molotov_attack_speed, MolotovAttackSpeed, f32, 10.0, 50.0);
-    random_upgrade!(
-        molotov_area_attack_speed,
-        MolotovAreaAttackSpeed,
-        f32,
-        5.0,
-        30.0
-    );
-    random_upgrade!(molotov_area_lifespan, MolotovAreaLifespan, f32, 10.0, 30.0);
-}
-
 #[rustfmt::skip]
 impl std::fmt::Display for WeaponUpgrade {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -169,14 +171,6 @@ pub enum GlobalEnemyUpgrade {
     AttackSpeed(f32),
 }
 
-impl GlobalEnemyUpgrade {
-    random_upgrade!(health, Health, f32, 1.0, 10.0);
-    random_upgrade!(speed, Speed, f32, 1.0, 2.0);
-    random_upgrade!(exp, Exp, f32, 1.0, 8.0);
-    random_upgrade!(damage, Damage, f32, 2.0, 10.0);
-    random_upgrade!(attack_speed, AttackSpeed, f32, 1.0, 5.0);
-}
-
 impl std::fmt::Display for GlobalEnemyUpgrade {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -212,12 +206,136 @@ impl std::fmt::Display for EnemyUpgrade {
     }
 }
 
-impl EnemyUpgrade {
-    random_upgrade!(health, Health, f32, 1.0, 20.0);
-    random_upgrade!(speed, Speed, f32, 1.0, 2.0);
-    random_upgrade!(exp, Exp, f32, 1.0, 15.0);
-    random_upgrade!(damage, Damage, f32, 2.0, 15.0);
-    random_upgrade!(attack_speed, AttackSpeed, f32, 1.0, 10.0);
+/// More level up options for the rest of the run
+#[derive(Debug, Clone, Copy)]
+pub enum LevelUpReward {
+    Rerolls(u32),
+    Banishes(u32),
+}
+
+impl std::fmt::Display for LevelUpReward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rerolls(value) => f.write_fmt(format_args!("rerolls: +{value}"))?,
+            Self::Banishes(value) => f.write_fmt(format_args!("banishes: +{value}"))?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum GlobalSideEffect {
+    /// Fewer upgrades are offered on the level up
+    LevelUpCards(u32),
+}
+
+impl std::fmt::Display for GlobalSideEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LevelUpCards(value) => f.write_fmt(format_args!("level up cards: -{value}"))?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SideEffect {
+    /// Generated on the side opposite to the weapon buff
+    WeaponDamage(f32),
+    /// Max hp of the wall lost every second
+    WallMaxHpDrain(f32),
+    /// Additional spawn with the number of enemies
+    ExtraSpawn(u32),
+    /// Percent added to the elite chance of the side
+    EliteChance(f32),
+}
+
+impl std::fmt::Display for SideEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WeaponDamage(value) => {
+                f.write_fmt(format_args!("weapon damage: -{value:.1}%"))?
+            }
+            Self::WallMaxHpDrain(value) => {
+                f.write_fmt(format_args!("wall max hp: -{value:.2}/s"))?
+            }
+            Self::ExtraSpawn(value) => f.write_fmt(format_args!("extra spawn: {value} enemies"))?,
+            Self::EliteChance(value) => f.write_fmt(format_args!("elite chance: +{value:.1}%"))?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CurseEffect {
+    EnemyHealth(f32),
+    EnemySpeed(f32),
+    EnemyDamage(f32),
+    EnemyAttackSpeed(f32),
+    /// Wall loses hp every second
+    WallDrain(f32),
+}
+
+impl CurseEffect {
+    pub fn value(&self) -> f32 {
+        match self {
+            Self::EnemyHealth(value)
+            | Self::EnemySpeed(value)
+            | Self::EnemyDamage(value)
+            | Self::EnemyAttackSpeed(value)
+            | Self::WallDrain(value) => *value,
+        }
+    }
+}
+
+impl std::fmt::Display for CurseEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EnemyHealth(value) => f.write_fmt(format_args!("enemy health: +{value:.1}%"))?,
+            Self::EnemySpeed(value) => f.write_fmt(format_args!("enemy speed: +{value:.1}%"))?,
+            Self::EnemyDamage(value) => f.write_fmt(format_args!("enemy damage: +{value:.1}%"))?,
+            Self::EnemyAttackSpeed(value) => {
+                f.write_fmt(format_args!("enemy attack speed: +{value:.1}%"))?
+            }
+            Self::WallDrain(value) => f.write_fmt(format_args!("wall hp: -{value:.1}/s"))?,
+        }
+        Ok(())
+    }
+}
+
+/// Side effect that is removed once it expires
+#[derive(Debug, Clone, Copy)]
+pub struct Curse {
+    pub effect: CurseEffect,
+    pub expiry: CurseExpiry,
+    pub escalation: Option<CurseEscalation>,
+}
+
+impl std::fmt::Display for Curse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{} {}", self.effect, self.expiry))?;
+        if let Some(escalation) = self.escalation {
+            f.write_fmt(format_args!(", {escalation}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the weapon of the side with the evolved one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeaponEvolution {
+    RepeatingCrossbow,
+    NapalmMolotov,
+}
+
+impl std::fmt::Display for WeaponEvolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepeatingCrossbow => f.write_str("evolve: repeating crossbow")?,
+            Self::NapalmMolotov => f.write_str("evolve: napalm molotov")?,
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -228,19 +346,59 @@ pub enum UpgradeSide<U> {
     East(U),
 }
 
+impl<U> UpgradeSide<U> {
+    /// Upgrade if it is on the side `S`
+    pub fn on_side<S: Side>(&self) -> Option<&U> {
+        let side = match self {
+            UpgradeSide::North(_) => TypeId::of::<North>(),
+            UpgradeSide::South(_) => TypeId::of::<South>(),
+            UpgradeSide::West(_) => TypeId::of::<West>(),
+            UpgradeSide::East(_) => TypeId::of::<East>(),
+        };
+        (side == TypeId::of::<S>()).then_some(self.upgrade())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpgradeSide::North(_) => "North",
+            UpgradeSide::South(_) => "South",
+            UpgradeSide::West(_) => "West",
+            UpgradeSide::East(_) => "East",
+        }
+    }
+
+    pub fn upgrade(&self) -> &U {
+        match self {
+            UpgradeSide::North(upgrade)
+            | UpgradeSide::South(upgrade)
+            | UpgradeSide::West(upgrade)
+            | UpgradeSide::East(upgrade) => upgrade,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Upgrade {
     pub global_wall_upgrade: Option<GlobalWallUpgrade>,
     pub wall_upgrade: Option<UpgradeSide<WallUpgrade>>,
     pub global_weapon_upgrade: Option<GlobalWeaponUpgrade>,
     pub weapon_upgrade: Option<UpgradeSide<WeaponUpgrade>>,
+    /// Only offered once the weapon buffs reach the evolution thresholds
+    pub evolution: Option<UpgradeSide<WeaponEvolution>>,
+    pub level_up_reward: Option<LevelUpReward>,
     pub global_enemy_upgrade: Option<GlobalEnemyUpgrade>,
     pub enemy_upgrade: Option<UpgradeSide<EnemyUpgrade>>,
+    pub curse: Option<UpgradeSide<Curse>>,
+    pub global_side_effect: Option<GlobalSideEffect>,
+    pub side_effect: Option<UpgradeSide<SideEffect>>,
+    /// Highest rarity of all upgrade parts
+    pub rarity: UpgradeRarity,
 }
 
 pub struct GlobalBuffs {
     pub wall_upgrade: Option<GlobalWallUpgrade>,
     pub weapon_upgrade: Option<GlobalWeaponUpgrade>,
+    pub level_up_reward: Option<LevelUpReward>,
 }
 
 impl std::fmt::Display for GlobalBuffs {
@@ -251,12 +409,16 @@ impl std::fmt::Display for GlobalBuffs {
         if let Some(upgrade) = self.weapon_upgrade {
             f.write_fmt(format_args!("{upgrade}\n"))?;
         }
+        if let Some(reward) = self.level_up_reward {
+            f.write_fmt(format_args!("{reward}\n"))?;
+        }
         Ok(())
     }
 }
 
 pub struct GlobalDebuffs {
     pub enemy_upgrade: Option<GlobalEnemyUpgrade>,
+    pub side_effect: Option<GlobalSideEffect>,
 }
 
 impl std::fmt::Display for GlobalDebuffs {
@@ -264,6 +426,9 @@ impl std::fmt::Display for GlobalDebuffs {
         if let Some(upgrade) = self.enemy_upgrade {
             f.write_fmt(format_args!("{upgrade}\n"))?;
         }
+        if let Some(side_effect) = self.side_effect {
+            f.write_fmt(format_args!("{side_effect}\n"))?;
+        }
         Ok(())
     }
 }
@@ -271,14 +436,20 @@ impl std::fmt::Display for GlobalDebuffs {
 pub struct SideBuffs<S: Side> {
     pub wall_upgrade: Option<WallUpgrade>,
     pub weapon_upgrade: Option<WeaponUpgrade>,
+    pub evolution: Option<WeaponEvolution>,
     _phantom: PhantomData<S>,
 }
 
 impl<S: Side> SideBuffs<S> {
-    pub fn new(wall_upgrade: Option<WallUpgrade>, weapon_upgrade: Option<WeaponUpgrade>) -> Self {
+    pub fn new(
+        wall_upgrade: Option<WallUpgrade>,
+        weapon_upgrade: Option<WeaponUpgrade>,
+        evolution: Option<WeaponEvolution>,
+    ) -> Self {
         Self {
             wall_upgrade,
             weapon_upgrade,
+            evolution,
             _phantom: PhantomData,
         }
     }
@@ -292,19 +463,30 @@ impl<S: Side> std::fmt::Display for SideBuffs<S> {
         if let Some(upgrade) = self.weapon_upgrade {
             f.write_fmt(format_args!("{upgrade}\n"))?;
         }
+        if let Some(evolution) = self.evolution {
+            f.write_fmt(format_args!("{evolution}\n"))?;
+        }
         Ok(())
     }
 }
 
 pub struct SideDebuffs<S: Side> {
     pub enemy_upgrade: Option<EnemyUpgrade>,
+    pub curse: Option<Curse>,
+    pub side_effect: Option<SideEffect>,
     _phantom: PhantomData<S>,
 }
 
 impl<S: Side> SideDebuffs<S> {
-    pub fn new(enemy_upgrade: Option<EnemyUpgrade>) -> Self {
+    pub fn new(
+        enemy_upgrade: Option<EnemyUpgrade>,
+        curse: Option<Curse>,
+        side_effect: Option<SideEffect>,
+    ) -> Self {
         Self {
             enemy_upgrade,
+            curse,
+            side_effect,
             _phantom: PhantomData,
         }
     }
@@ -315,6 +497,12 @@ impl<S: Side> std::fmt::Display for SideDebuffs<S> {
         if let Some(upgrade) = self.enemy_upgrade {
             f.write_fmt(format_args!("{upgrade}\n"))?;
         }
+        if let Some(curse) = self.curse {
+            f.write_fmt(format_args!("curse: {curse}\n"))?;
+        }
+        if let Some(side_effect) = self.side_effect {
+            f.write_fmt(format_args!("{side_effect}\n"))?;
+        }
         Ok(())
     }
 }
@@ -335,7 +523,9 @@ impl Upgrade {
     pub fn has_global_upgrades(&self) -> bool {
         self.global_wall_upgrade.is_some()
             || self.global_weapon_upgrade.is_some()
+            || self.level_up_reward.is_some()
             || self.global_enemy_upgrade.is_some()
+            || self.global_side_effect.is_some()
     }
 
     pub fn global_upgrades(&self) -> (GlobalBuffs, GlobalDebuffs) {
@@ -343,9 +533,11 @@ impl Upgrade {
             GlobalBuffs {
                 wall_upgrade: self.global_wall_upgrade,
                 weapon_upgrade: self.global_weapon_upgrade,
+                level_up_reward: self.level_up_reward,
             },
             GlobalDebuffs {
                 enemy_upgrade: self.global_enemy_upgrade,
+                side_effect: self.global_side_effect,
             },
         )
     }
@@ -353,68 +545,92 @@ impl Upgrade {
     pub fn has_north_upgrades(&self) -> bool {
         let wall_upgrade = get_side!(self, wall_upgrade, North).is_some();
         let weapon_upgrade = get_side!(self, weapon_upgrade, North).is_some();
+        let evolution = get_side!(self, evolution, North).is_some();
         let enemy_upgrade = get_side!(self, enemy_upgrade, North).is_some();
-        wall_upgrade || weapon_upgrade || enemy_upgrade
+        let curse = get_side!(self, curse, North).is_some();
+        let side_effect = get_side!(self, side_effect, North).is_some();
+        wall_upgrade || weapon_upgrade || evolution || enemy_upgrade || curse || side_effect
     }
 
     pub fn north_upgrades(&self) -> (SideBuffs<North>, SideDebuffs<North>) {
         let wall_upgrade = get_side!(self, wall_upgrade, North);
         let weapon_upgrade = get_side!(self, weapon_upgrade, North);
+        let evolution = get_side!(self, evolution, North);
         let enemy_upgrade = get_side!(self, enemy_upgrade, North);
+        let curse = get_side!(self, curse, North);
+        let side_effect = get_side!(self, side_effect, North);
         (
-            SideBuffs::new(wall_upgrade, weapon_upgrade),
-            SideDebuffs::new(enemy_upgrade),
+            SideBuffs::new(wall_upgrade, weapon_upgrade, evolution),
+            SideDebuffs::new(enemy_upgrade, curse, side_effect),
         )
     }
 
     pub fn has_south_upgrades(&self) -> bool {
         let wall_upgrade = get_side!(self, wall_upgrade, South).is_some();
         let weapon_upgrade = get_side!(self, weapon_upgrade, South).is_some();
+        let evolution = get_side!(self, evolution, South).is_some();
         let enemy_upgrade = get_side!(self, enemy_upgrade, South).is_some();
-        wall_upgrade || weapon_upgrade || enemy_upgrade
+        let curse = get_side!(self, curse, South).is_some();
+        let side_effect = get_side!(self, side_effect, South).is_some();
+        wall_upgrade || weapon_upgrade || evolution || enemy_upgrade || curse || side_effect
     }
 
-    pub fn south_upgrades(&self) -> (SideBuffs<North>, SideDebuffs<North>) {
+    pub fn south_upgrades(&self) -> (SideBuffs<South>, SideDebuffs<South>) {
         let wall_upgrade = get_side!(self, wall_upgrade, South);
         let weapon_upgrade = get_side!(self, weapon_upgrade, South);
+        let evolution = get_side!(self, evolution, South);
         let enemy_upgrade = get_side!(self, enemy_upgrade, South);
+        let curse = get_side!(self, curse, South);
+        let side_effect = get_side!(self, side_effect, South);
         (
-            SideBuffs::new(wall_upgrade, weapon_upgrade),
-            SideDebuffs::new(enemy_upgrade),
+            SideBuffs::new(wall_upgrade, weapon_upgrade, evolution),
+            SideDebuffs::new(enemy_upgrade, curse, side_effect),
         )
     }
 
     pub fn has_west_upgrades(&self) -> bool {
         let wall_upgrade = get_side!(self, wall_upgrade, West).is_some();
         let weapon_upgrade = get_side!(self, weapon_upgrade, West).is_some();
+        let evolution = get_side!(self, evolution, West).is_some();
         let enemy_upgrade = get_side!(self, enemy_upgrade, West).is_some();
-        wall_upgrade || weapon_upgrade || enemy_upgrade
+        let curse = get_side!(self, curse, West).is_some();
+        let side_effect = get_side!(self, side_effect, West).is_some();
+        wall_upgrade || weapon_upgrade || evolution || enemy_upgrade || curse || side_effect
     }
 
     pub fn west_upgrades(&self) -> (SideBuffs<West>, SideDebuffs<West>) {
         let wall_upgrade = get_side!(self, wall_upgrade, West);
         let weapon_upgrade = get_side!(self, weapon_upgrade, West);
+        let evolution = get_side!(self, evolution, West);
         let enemy_upgrade = get_side!(self, enemy_upgrade, West);
+        let curse = get_side!(self, curse, West);
+        let side_effect = get_side!(self, side_effect, West);
         (
-            SideBuffs::new(wall_upgrade, weapon_upgrade),
-            SideDebuffs::new(enemy_upgrade),
+            SideBuffs::new(wall_upgrade, weapon_upgrade, evolution),
+            SideDebuffs::new(enemy_upgrade, curse, side_effect),
         )
     }
 
     pub fn has_east_upgrades(&self) -> bool {
         let wall_upgrade = get_side!(self, wall_upgrade, East).is_some();
         let weapon_upgrade = get_side!(self, weapon_upgrade, East).is_some();
+        let evolution = get_side!(self, evolution, East).is_some();
         let enemy_upgrade = get_side!(self, enemy_upgrade, East).is_some();
-        wall_upgrade || weapon_upgrade || enemy_upgrade
+        let curse = get_side!(self, curse, East).is_some();
+        let side_effect = get_side!(self, side_effect, East).is_some();
+        wall_upgrade || weapon_upgrade || evolution || enemy_upgrade || curse || side_effect
     }
 
     pub fn east_upgrades(&self) -> (SideBuffs<East>, SideDebuffs<East>) {
         let wall_upgrade = get_side!(self, wall_upgrade, East);
         let weapon_upgrade = get_side!(self, weapon_upgrade, East);
+        let evolution = get_side!(self, evolution, East);
         let enemy_upgrade = get_side!(self, enemy_upgrade, East);
+        let curse = get_side!(self, curse, East);
+        let side_effect = get_side!(self, side_effect, East);
         (
-            SideBuffs::new(wall_upgrade, weapon_upgrade),
-            SideDebuffs::new(enemy_upgrade),
+            SideBuffs::new(wall_upgrade, weapon_upgrade, evolution),
+            SideDebuffs::new(enemy_upgrade, curse, side_effect),
         )
     }
 }
@@ -462,6 +678,18 @@ impl std::fmt::Display for Upgrade {
             }
         }
 
+        if let Some(evolution) = self.evolution {
+            f.write_fmt(format_args!(
+                "{}:\n\t{}\n",
+                evolution.name(),
+                evolution.upgrade()
+            ))?;
+        }
+
+        if let Some(reward) = self.level_up_reward {
+            f.write_fmt(format_args!("Level up:\n\t{reward}\n"))?;
+        }
+
         if let Some(upgrade) = self.global_enemy_upgrade {
             f.write_fmt(format_args!("Global enemies:\n\t{upgrade}\n"))?;
         }
@@ -481,145 +709,45 @@ impl std::fmt::Display for Upgrade {
                 }
             }
         }
+        if let Some(curse) = self.curse {
+            f.write_fmt(format_args!(
+                "{} curse:\n\t{}\n",
+                curse.name(),
+                curse.upgrade()
+            ))?;
+        }
+        if let Some(side_effect) = self.global_side_effect {
+            f.write_fmt(format_args!("Global side effect:\n\t{side_effect}\n"))?;
+        }
+        if let Some(side_effect) = self.side_effect {
+            f.write_fmt(format_args!(
+                "{} side effect:\n\t{}\n",
+                side_effect.name(),
+                side_effect.upgrade()
+            ))?;
+        }
         Ok(())
     }
 }
 
 #[derive(Debug, Default, Resource)]
 pub struct Upgrades {
-    pub upgrades: [Upgrade; 4],
-}
-
-pub fn genereate_upgrades() -> Upgrades {
-    let mut rng = rand::thread_rng();
-    Upgrades {
-        upgrades: [
-            genereate_upgrade(&mut rng),
-            genereate_upgrade(&mut rng),
-            genereate_upgrade(&mut rng),
-            genereate_upgrade(&mut rng),
-        ],
-    }
-}
-
-pub fn genereate_upgrade(mut rng: &mut impl rand::Rng) -> Upgrade {
-    // wall
-    let (global_wall_upgrade, wall_upgrade, must_have_weapon) = if rng.gen_ratio(2, 10) {
-        if rng.gen_ratio(3, 10) {
-            let upgrade = match rng.gen_range(0..1) {
-                0 => GlobalWallUpgrade::additional_max_hp(&mut rng),
-                1 => GlobalWallUpgrade::heal(&mut rng),
-                _ => unreachable!(),
-            };
-            (Some(upgrade), None, false)
-        } else {
-            // one side wall
-            let upgrade = match rng.gen_range(0..2) {
-                0 => WallUpgrade::additional_max_hp(&mut rng),
-                1 => WallUpgrade::heal(&mut rng),
-                _ => unreachable!(),
-            };
-
-            let upgrade = match rng.gen_range(0..4) {
-                0 => UpgradeSide::North(upgrade),
-                1 => UpgradeSide::South(upgrade),
-                2 => UpgradeSide::West(upgrade),
-                3 => UpgradeSide::East(upgrade),
-                _ => unreachable!(),
-            };
-            (None, Some(upgrade), false)
-        }
-    } else {
-        (None, None, true)
-    };
-
-    // weapon
-    let (global_weapon_upgrade, weapon_upgrade) = if rng.gen_ratio(9, 10) || must_have_weapon {
-        if rng.gen_ratio(4, 10) {
-            let upgrade = match rng.gen_range(0..4) {
-                0 => GlobalWeaponUpgrade::damage(&mut rng),
-                1 => GlobalWeaponUpgrade::damage_flat(&mut rng),
-                2 => GlobalWeaponUpgrade::crit_dmamge(&mut rng),
-                3 => GlobalWeaponUpgrade::crit_chance(&mut rng),
-                _ => unreachable!(),
-            };
-            (Some(upgrade), None)
-        } else {
-            // one side wall
-            let upgrade = match rng.gen_range(0..20) {
-                0 => WeaponUpgrade::crossbow_damage(&mut rng),
-                1 => WeaponUpgrade::crossbow_damage_flat(&mut rng),
-                2 => WeaponUpgrade::crossbow_crit_damage(&mut rng),
-                3 => WeaponUpgrade::crossbow_crit_chance(&mut rng),
-                4 => WeaponUpgrade::crossbow_range(&mut rng),
-                5 | 6 | 7 | 8 => WeaponUpgrade::crossbow_attack_speed(&mut rng),
-
-                9 => WeaponUpgrade::molotov_damage(&mut rng),
-                10 => WeaponUpgrade::molotov_damage_flat(&mut rng),
-                11 => WeaponUpgrade::molotov_crit_damage(&mut rng),
-                12 => WeaponUpgrade::molotov_crit_chance(&mut rng),
-                13 => WeaponUpgrade::molotov_area_size(&mut rng),
-                14 | 15 | 16 | 17 => WeaponUpgrade::molotov_attack_speed(&mut rng),
-                18 => WeaponUpgrade::molotov_area_attack_speed(&mut rng),
-                19 => WeaponUpgrade::molotov_area_lifespan(&mut rng),
-                _ => unreachable!(),
-            };
-
-            let upgrade = match rng.gen_range(0..4) {
-                0 => UpgradeSide::North(upgrade),
-                1 => UpgradeSide::South(upgrade),
-                2 => UpgradeSide::West(upgrade),
-                3 => UpgradeSide::East(upgrade),
-                _ => unreachable!(),
-            };
-            (None, Some(upgrade))
-        }
-    } else {
-        (None, None)
-    };
-
-    // enemy
-    let (global_enemy_upgrade, enemy_upgrade) = if rng.gen_ratio(99, 100) {
-        if rng.gen_ratio(3, 10) {
-            let upgrade = match rng.gen_range(0..5) {
-                0 => GlobalEnemyUpgrade::health(&mut rng),
-                1 => GlobalEnemyUpgrade::speed(&mut rng),
-                2 => GlobalEnemyUpgrade::exp(&mut rng),
-                3 => GlobalEnemyUpgrade::damage(&mut rng),
-                4 => GlobalEnemyUpgrade::attack_speed(&mut rng),
-                _ => unreachable!(),
-            };
-            (Some(upgrade), None)
-        } else {
-            // one side wall
-            let upgrade = match rng.gen_range(0..5) {
-                0 => EnemyUpgrade::health(&mut rng),
-                1 => EnemyUpgrade::speed(&mut rng),
-                2 => EnemyUpgrade::exp(&mut rng),
-                3 => EnemyUpgrade::damage(&mut rng),
-                4 => EnemyUpgrade::attack_speed(&mut rng),
-                _ => unreachable!(),
-            };
-
-            let upgrade = match rng.gen_range(0..4) {
-                0 => UpgradeSide::North(upgrade),
-                1 => UpgradeSide::South(upgrade),
-                2 => UpgradeSide::West(upgrade),
-                3 => UpgradeSide::East(upgrade),
-                _ => unreachable!(),
-            };
-            (None, Some(upgrade))
-        }
-    } else {
-        (None, None)
-    };
-
-    Upgrade {
-        global_wall_upgrade,
-        wall_upgrade,
-        global_weapon_upgrade,
-        weapon_upgrade,
-        global_enemy_upgrade,
-        enemy_upgrade,
+    /// Rolled cards followed by the evolution slot
+    pub upgrades: [Upgrade; 5],
+}
+
+impl Upgrades {
+    /// Evolutions are offered in the extra slot after the rolled cards
+    pub const EVOLUTION: usize = 4;
+
+    /// Indices of the offered upgrades.
+    /// Evolution slot is included if it has an evolution.
+    pub fn offered(&self, cards: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..cards).chain(
+            self.upgrades[Self::EVOLUTION]
+                .evolution
+                .is_some()
+                .then_some(Self::EVOLUTION),
+        )
     }
 }
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{
+        castle::{Castle, CastleWall},
+        rng::GameRng,
+    },
+    GlobalState,
+};
+
+use super::{
+    apply::{FinishUpgradeEvent, UpgradeSet},
+    pool::{BanishedUpgrades, UpgradePool},
+    East, GameState, North, South, UpgradeAssets, Upgrades, West,
+};
+
+pub struct LevelUpOptionsPlugin;
+
+impl Plugin for LevelUpOptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelUpOptionEvent>()
+            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_system(
+                use_level_up_options
+                    .before(UpgradeSet::Dispatch)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            );
+    }
+}
+
+/// Ways to change or skip upgrades on level up.
+/// Starting values are set in `assets/data/game.upgrades.ron`
+#[derive(Debug, Clone, Deserialize, Resource)]
+#[serde(default)]
+pub struct LevelUpOptions {
+    /// Number of times all upgrades can be generated again
+    pub rerolls: u32,
+    /// Number of times upgrade kinds can be removed from the pool
+    pub banishes: u32,
+    /// Exp for skipping as a share of exp needed for the next level
+    pub skip_exp: f32,
+    /// Heal of all walls for skipping
+    pub skip_heal: i32,
+    /// Number of upgrades offered on level up
+    pub cards: usize,
+}
+
+impl LevelUpOptions {
+    /// Side effects can not take away the choice
+    pub const MIN_CARDS: usize = 2;
+    /// Evolution slot is not counted
+    pub const MAX_CARDS: usize = 4;
+
+    /// Rolled upgrades with index less than this are offered
+    pub fn offered_cards(&self) -> usize {
+        self.cards.clamp(1, Self::MAX_CARDS)
+    }
+}
+
+impl Default for LevelUpOptions {
+    fn default() -> Self {
+        Self {
+            rerolls: 2,
+            banishes: 1,
+            skip_exp: 0.25,
+            skip_heal: 50,
+            cards: Self::MAX_CARDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LevelUpOptionEvent {
+    /// Generates all upgrades again
+    Reroll,
+    /// Banishes buffs of the upgrade with the index
+    /// and replaces it with a new one
+    Banish(usize),
+    SkipForExp,
+    SkipForHeal,
+    /// Keeps pending level ups to spend them later
+    Bank,
+}
+
+impl LevelUpOptionEvent {
+    /// Option closes the level up menu without picking an upgrade
+    pub fn finishes_level_up(&self) -> bool {
+        matches!(
+            self,
+            LevelUpOptionEvent::SkipForExp
+                | LevelUpOptionEvent::SkipForHeal
+                | LevelUpOptionEvent::Bank
+        )
+    }
+}
+
+fn setup(
+    upgrade_assets: Res<UpgradeAssets>,
+    upgrade_pools: Res<Assets<UpgradePool>>,
+    mut commands: Commands,
+) {
+    let options = match upgrade_pools.get(&upgrade_assets.pool) {
+        Some(pool) => pool.options.clone(),
+        None => {
+            warn!("Upgrade pool is not loaded");
+            LevelUpOptions::default()
+        }
+    };
+    commands.insert_resource(options);
+    commands.insert_resource(BanishedUpgrades::default());
+}
+
+pub fn use_level_up_options(
+    upgrade_assets: Res<UpgradeAssets>,
+    upgrade_pools: Res<Assets<UpgradePool>>,
+    mut castle: Query<&mut Castle>,
+    mut north_wall: Query<&mut CastleWall<North>>,
+    mut south_wall: Query<&mut CastleWall<South>>,
+    mut west_wall: Query<&mut CastleWall<West>>,
+    mut east_wall: Query<&mut CastleWall<East>>,
+    mut game_rng: ResMut<GameRng>,
+    mut options: ResMut<LevelUpOptions>,
+    mut banished: ResMut<BanishedUpgrades>,
+    mut upgrades: ResMut<Upgrades>,
+    mut option_events: EventReader<LevelUpOptionEvent>,
+    mut finish_event: EventWriter<FinishUpgradeEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for event in option_events.iter() {
+        match *event {
+            LevelUpOptionEvent::Reroll => {
+                if options.rerolls == 0 {
+                    continue;
+                }
+                let Some(pool) = upgrade_pools.get(&upgrade_assets.pool) else {
+                    warn!("Upgrade pool is not loaded");
+                    continue;
+                };
+                options.rerolls -= 1;
+                *upgrades = pool.generate_upgrades(&banished, &mut game_rng.upgrades);
+            }
+            LevelUpOptionEvent::Banish(index) => {
+                if options.banishes == 0
+                    || index >= options.offered_cards()
+                    || !BanishedUpgrades::can_banish(&upgrades.upgrades[index])
+                {
+                    continue;
+                }
+                let Some(pool) = upgrade_pools.get(&upgrade_assets.pool) else {
+                    warn!("Upgrade pool is not loaded");
+                    continue;
+                };
+                options.banishes -= 1;
+                banished.banish(&upgrades.upgrades[index]);
+                upgrades.upgrades[index] = pool.generate_upgrade(&banished, &mut game_rng.upgrades);
+            }
+            LevelUpOptionEvent::SkipForExp => {
+                let mut castle = castle.single_mut();
+                castle.exp += (castle.next_level_exp as f32 * options.skip_exp) as u32;
+                finish_event.send(FinishUpgradeEvent);
+            }
+            LevelUpOptionEvent::SkipForHeal => {
+                north_wall.single_mut().heal(options.skip_heal);
+                south_wall.single_mut().heal(options.skip_heal);
+                west_wall.single_mut().heal(options.skip_heal);
+                east_wall.single_mut().heal(options.skip_heal);
+                finish_event.send(FinishUpgradeEvent);
+            }
+            LevelUpOptionEvent::Bank => {
+                castle.single_mut().bank_level_ups = true;
+                game_state.set(GameState::InGame);
+            }
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use super::{
+    curses::{CurseEscalation, CurseExpiry},
+    options::LevelUpOptions,
+    Curse, CurseEffect, EnemyUpgrade, GlobalEnemyUpgrade, GlobalSideEffect, GlobalWallUpgrade,
+    GlobalWeaponUpgrade, LevelUpReward, SideEffect, Upgrade, UpgradeSide, Upgrades, WallUpgrade,
+    WeaponUpgrade,
+};
+
+/// Creates kind enum to reference upgrade variants in data
+/// and methods to roll upgrade value in a range
+/// or create it with a fixed value
+/// and to get the kind of rolled upgrade
+macro_rules! upgrade_kind {
+    ($kind:ident, $upgrade:ident, { $($variant:ident: $type:ty),* $(,)? }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+        pub enum $kind {
+            $($variant),*
+        }
+
+        impl $kind {
+            pub fn roll(&self, min: f32, max: f32, rng: &mut impl Rng) -> $upgrade {
+                let (min, max) = (min.min(max), min.max(max));
+                match self {
+                    $(Self::$variant => $upgrade::$variant(rng.gen_range(min as $type..=max as $type))),*
+                }
+            }
+
+            #[allow(dead_code)]
+            pub fn with_value(&self, value: f32) -> $upgrade {
+                match self {
+                    $(Self::$variant => $upgrade::$variant(value as $type)),*
+                }
+            }
+        }
+
+        // side effects are never banished, so not every kind is used
+        #[allow(dead_code)]
+        impl $upgrade {
+            pub fn kind(&self) -> $kind {
+                match self {
+                    $($upgrade::$variant(_) => $kind::$variant),*
+                }
+            }
+        }
+    };
+}
+
+upgrade_kind!(GlobalWallUpgradeKind, GlobalWallUpgrade, {
+    AdditionalMaxHp: i32,
+    Heal: i32,
+});
+
+upgrade_kind!(WallUpgradeKind, WallUpgrade, {
+    AdditionalMaxHp: i32,
+    Heal: i32,
+    MissileBlock: f32,
+    MissileInterception: f32,
+});
+
+upgrade_kind!(GlobalWeaponUpgradeKind, GlobalWeaponUpgrade, {
+    Damage: f32,
+    DamageFlat: i32,
+    CritDamage: f32,
+    CritChance: f32,
+});
+
+upgrade_kind!(WeaponUpgradeKind, WeaponUpgrade, {
+    CrossbowDamage: f32,
+    CrossbowDamageFlat: i32,
+    CrossbowCritDamage: f32,
+    CrossbowCritChance: f32,
+    CrossbowRange: f32,
+    CrossbowAttackSpeed: f32,
+    MolotovDamage: f32,
+    MolotovDamageFlat: i32,
+    MolotovCritDamage: f32,
+    MolotovCritChance: f32,
+    MolotovAreaSize: f32,
+    MolotovAttackSpeed: f32,
+    MolotovAreaAttackSpeed: f32,
+    MolotovAreaLifespan: f32,
+});
+
+upgrade_kind!(GlobalEnemyUpgradeKind, GlobalEnemyUpgrade, {
+    Health: f32,
+    Speed: f32,
+    Exp: f32,
+    Damage: f32,
+    AttackSpeed: f32,
+});
+
+upgrade_kind!(EnemyUpgradeKind, EnemyUpgrade, {
+    Health: f32,
+    Speed: f32,
+    Exp: f32,
+    Damage: f32,
+    AttackSpeed: f32,
+});
+
+upgrade_kind!(GlobalSideEffectKind, GlobalSideEffect, {
+    LevelUpCards: u32,
+});
+
+upgrade_kind!(SideEffectKind, SideEffect, {
+    WeaponDamage: f32,
+    WallMaxHpDrain: f32,
+    ExtraSpawn: u32,
+    EliteChance: f32,
+});
+
+upgrade_kind!(LevelUpRewardKind, LevelUpReward, {
+    Rerolls: u32,
+    Banishes: u32,
+});
+
+upgrade_kind!(CurseKind, CurseEffect, {
+    EnemyHealth: f32,
+    EnemySpeed: f32,
+    EnemyDamage: f32,
+    EnemyAttackSpeed: f32,
+    WallDrain: f32,
+});
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Component)]
+pub enum UpgradeRarity {
+    #[default]
+    Common,
+    Rare,
+    Epic,
+}
+
+impl std::fmt::Display for UpgradeRarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpgradeRarity::Common => f.write_str("Common"),
+            UpgradeRarity::Rare => f.write_str("Rare"),
+            UpgradeRarity::Epic => f.write_str("Epic"),
+        }
+    }
+}
+
+/// All upgrades that can appear on level up.
+/// Loaded from `assets/data/game.upgrades.ron`
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "9d2e4b71-3c8a-4f06-b1e5-6a7f0c2d8e93"]
+pub struct UpgradePool {
+    pub walls: UpgradeCategory<GlobalWallUpgradeKind, WallUpgradeKind>,
+    pub weapons: UpgradeCategory<GlobalWeaponUpgradeKind, WeaponUpgradeKind>,
+    #[serde(default)]
+    pub rewards: RewardCategory,
+    pub enemies: UpgradeCategory<GlobalEnemyUpgradeKind, EnemyUpgradeKind>,
+    #[serde(default)]
+    pub side_effects: UpgradeCategory<GlobalSideEffectKind, SideEffectKind>,
+    #[serde(default)]
+    pub curses: CurseCategory,
+    /// Rerolls, banishes and skip rewards at the start of the run
+    #[serde(default)]
+    pub options: LevelUpOptions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpgradeCategory<G, S> {
+    /// Chance for upgrade to have this category
+    pub chance: f64,
+    /// Chance for category upgrade to be global instead of one side
+    pub global_chance: f64,
+    pub global: Vec<UpgradeEntry<G>>,
+    pub side: Vec<UpgradeEntry<S>>,
+}
+
+impl<G, S> Default for UpgradeCategory<G, S> {
+    fn default() -> Self {
+        Self {
+            chance: 0.0,
+            global_chance: 0.0,
+            global: vec![],
+            side: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpgradeEntry<K> {
+    pub upgrade: K,
+    pub min: f32,
+    pub max: f32,
+    pub weight: u32,
+    pub rarity: UpgradeRarity,
+}
+
+/// Level up rewards are always global
+#[derive(Debug, Default, Deserialize)]
+pub struct RewardCategory {
+    /// Chance for upgrade to have a level up reward
+    pub chance: f64,
+    pub entries: Vec<UpgradeEntry<LevelUpRewardKind>>,
+}
+
+/// Curses are always on one side
+#[derive(Debug, Default, Deserialize)]
+pub struct CurseCategory {
+    /// Chance for upgrade to have a curse
+    pub chance: f64,
+    pub entries: Vec<CurseEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurseEntry {
+    pub upgrade: CurseKind,
+    pub min: f32,
+    pub max: f32,
+    pub weight: u32,
+    pub rarity: UpgradeRarity,
+    pub expiry: CurseExpiry,
+    #[serde(default)]
+    pub escalation: Option<CurseEscalation>,
+}
+
+impl CurseEntry {
+    fn roll(&self, rng: &mut impl Rng) -> Curse {
+        Curse {
+            effect: self.upgrade.roll(self.min, self.max, rng),
+            expiry: self.expiry,
+            escalation: self.escalation,
+        }
+    }
+}
+
+impl<G, S> UpgradeCategory<G, S> {
+    fn rolls(&self, rng: &mut impl Rng) -> bool {
+        rng.gen_bool(self.chance.clamp(0.0, 1.0))
+    }
+
+    /// Picks global or one side entry of the category.
+    /// Banished entries are never picked. If all global or all side
+    /// entries are banished, the other ones are picked instead.
+    fn pick<R: Rng>(
+        &self,
+        banished_global: &[G],
+        banished_side: &[S],
+        rng: &mut R,
+    ) -> (Option<&UpgradeEntry<G>>, Option<&UpgradeEntry<S>>)
+    where
+        G: PartialEq,
+        S: PartialEq,
+    {
+        let pick_global = |rng: &mut R| {
+            self.global
+                .choose_weighted(rng, |entry| entry.weight(banished_global))
+                .ok()
+        };
+        let pick_side = |rng: &mut R| {
+            self.side
+                .choose_weighted(rng, |entry| entry.weight(banished_side))
+                .ok()
+        };
+        if rng.gen_bool(self.global_chance.clamp(0.0, 1.0)) {
+            match pick_global(rng) {
+                Some(entry) => (Some(entry), None),
+                None => (None, pick_side(rng)),
+            }
+        } else {
+            match pick_side(rng) {
+                Some(entry) => (None, Some(entry)),
+                None => (pick_global(rng), None),
+            }
+        }
+    }
+}
+
+impl<K: PartialEq> UpgradeEntry<K> {
+    fn weight(&self, banished: &[K]) -> u32 {
+        if banished.contains(&self.upgrade) {
+            0
+        } else {
+            self.weight
+        }
+    }
+}
+
+/// Upgrade kinds removed from the pool for the rest of the run.
+/// Only buffs can be banished, side effects stay in the pool.
+#[derive(Debug, Default, Resource)]
+pub struct BanishedUpgrades {
+    pub global_walls: Vec<GlobalWallUpgradeKind>,
+    pub walls: Vec<WallUpgradeKind>,
+    pub global_weapons: Vec<GlobalWeaponUpgradeKind>,
+    pub weapons: Vec<WeaponUpgradeKind>,
+}
+
+impl BanishedUpgrades {
+    /// Evolutions are not in the pool, so only upgrades
+    /// with wall or weapon buffs can be banished
+    pub fn can_banish(upgrade: &Upgrade) -> bool {
+        upgrade.evolution.is_none()
+            && (upgrade.global_wall_upgrade.is_some()
+                || upgrade.wall_upgrade.is_some()
+                || upgrade.global_weapon_upgrade.is_some()
+                || upgrade.weapon_upgrade.is_some())
+    }
+
+    /// Banishes kinds of all buffs of the upgrade
+    pub fn banish(&mut self, upgrade: &Upgrade) {
+        if let Some(upgrade) = upgrade.global_wall_upgrade {
+            self.global_walls.push(upgrade.kind());
+        }
+        if let Some(upgrade) = upgrade.wall_upgrade {
+            self.walls.push(upgrade.upgrade().kind());
+        }
+        if let Some(upgrade) = upgrade.global_weapon_upgrade {
+            self.global_weapons.push(upgrade.kind());
+        }
+        if let Some(upgrade) = upgrade.weapon_upgrade {
+            self.weapons.push(upgrade.upgrade().kind());
+        }
+    }
+}
+
+fn random_side<U>(upgrade: U, rng: &mut impl Rng) -> UpgradeSide<U> {
+    match rng.gen_range(0..4) {
+        0 => UpgradeSide::North(upgrade),
+        1 => UpgradeSide::South(upgrade),
+        2 => UpgradeSide::West(upgrade),
+        3 => UpgradeSide::East(upgrade),
+        _ => unreachable!(),
+    }
+}
+
+/// Side of the upgrade across the castle
+fn opposite_side<U, V>(upgrade: U, side: &UpgradeSide<V>) -> UpgradeSide<U> {
+    match side {
+        UpgradeSide::North(_) => UpgradeSide::South(upgrade),
+        UpgradeSide::South(_) => UpgradeSide::North(upgrade),
+        UpgradeSide::West(_) => UpgradeSide::East(upgrade),
+        UpgradeSide::East(_) => UpgradeSide::West(upgrade),
+    }
+}
+
+impl UpgradePool {
+    /// Returns false if all wall upgrades are banished
+    fn add_wall(
+        &self,
+        upgrade: &mut Upgrade,
+        banished: &BanishedUpgrades,
+        rng: &mut impl Rng,
+    ) -> bool {
+        let (global, side) = self
+            .walls
+            .pick(&banished.global_walls, &banished.walls, rng);
+        if let Some(entry) = global {
+            upgrade.global_wall_upgrade = Some(entry.upgrade.roll(entry.min, entry.max, rng));
+            upgrade.rarity = upgrade.rarity.max(entry.rarity);
+        }
+        if let Some(entry) = side {
+            let wall_upgrade = entry.upgrade.roll(entry.min, entry.max, rng);
+            upgrade.wall_upgrade = Some(random_side(wall_upgrade, rng));
+            upgrade.rarity = upgrade.rarity.max(entry.rarity);
+        }
+        global.is_some() || side.is_some()
+    }
+
+    /// Returns false if all weapon upgrades are banished
+    fn add_weapon(
+        &self,
+        upgrade: &mut Upgrade,
+        banished: &BanishedUpgrades,
+        rng: &mut impl Rng,
+    ) -> bool {
+        let (global, side) = self
+            .weapons
+            .pick(&banished.global_weapons, &banished.weapons, rng);
+        if let Some(entry) = global {
+            upgrade.global_weapon_upgrade = Some(entry.upgrade.roll(entry.min, entry.max, rng));
+            upgrade.rarity = upgrade.rarity.max(entry.rarity);
+        }
+        if let Some(entry) = side {
+            let weapon_upgrade = entry.upgrade.roll(entry.min, entry.max, rng);
+            upgrade.weapon_upgrade = Some(random_side(weapon_upgrade, rng));
+            upgrade.rarity = upgrade.rarity.max(entry.rarity);
+        }
+        global.is_some() || side.is_some()
+    }
+
+    pub fn generate_upgrades(&self, banished: &BanishedUpgrades, rng: &mut impl Rng) -> Upgrades {
+        Upgrades {
+            upgrades: [
+                self.generate_upgrade(banished, rng),
+                self.generate_upgrade(banished, rng),
+                self.generate_upgrade(banished, rng),
+                self.generate_upgrade(banished, rng),
+                // evolution slot
+                Upgrade::default(),
+            ],
+        }
+    }
+
+    pub fn generate_upgrade(&self, banished: &BanishedUpgrades, rng: &mut impl Rng) -> Upgrade {
+        let mut upgrade = Upgrade::default();
+
+        // wall
+        let has_wall = self.walls.rolls(rng) && self.add_wall(&mut upgrade, banished, rng);
+
+        // weapon, every upgrade without wall part has it.
+        // if all weapons are banished, wall is used instead
+        let has_weapon =
+            (self.weapons.rolls(rng) || !has_wall) && self.add_weapon(&mut upgrade, banished, rng);
+        if !has_wall && !has_weapon {
+            self.add_wall(&mut upgrade, banished, rng);
+        }
+
+        // level up reward
+        if rng.gen_bool(self.rewards.chance.clamp(0.0, 1.0)) {
+            if let Ok(entry) = self
+                .rewards
+                .entries
+                .choose_weighted(rng, |entry| entry.weight)
+            {
+                upgrade.level_up_reward = Some(entry.upgrade.roll(entry.min, entry.max, rng));
+                upgrade.rarity = upgrade.rarity.max(entry.rarity);
+            }
+        }
+
+        // enemy
+        if self.enemies.rolls(rng) {
+            let (global, side) = self.enemies.pick(&[], &[], rng);
+            if let Some(entry) = global {
+                upgrade.global_enemy_upgrade = Some(entry.upgrade.roll(entry.min, entry.max, rng));
+                upgrade.rarity = upgrade.rarity.max(entry.rarity);
+            }
+            if let Some(entry) = side {
+                let enemy_upgrade = entry.upgrade.roll(entry.min, entry.max, rng);
+                upgrade.enemy_upgrade = Some(random_side(enemy_upgrade, rng));
+                upgrade.rarity = upgrade.rarity.max(entry.rarity);
+            }
+        }
+
+        // other side effects
+        if self.side_effects.rolls(rng) {
+            let (global, side) = self.side_effects.pick(&[], &[], rng);
+            if let Some(entry) = global {
+                upgrade.global_side_effect = Some(entry.upgrade.roll(entry.min, entry.max, rng));
+                upgrade.rarity = upgrade.rarity.max(entry.rarity);
+            }
+            if let Some(entry) = side {
+                let side_effect = entry.upgrade.roll(entry.min, entry.max, rng);
+                upgrade.side_effect = Some(match (side_effect, upgrade.weapon_upgrade) {
+                    (SideEffect::WeaponDamage(_), Some(weapon_upgrade)) => {
+                        opposite_side(side_effect, &weapon_upgrade)
+                    }
+                    _ => random_side(side_effect, rng),
+                });
+                upgrade.rarity = upgrade.rarity.max(entry.rarity);
+            }
+        }
+
+        // curse
+        if rng.gen_bool(self.curses.chance.clamp(0.0, 1.0)) {
+            if let Ok(entry) = self
+                .curses
+                .entries
+                .choose_weighted(rng, |entry| entry.weight)
+            {
+                upgrade.curse = Some(random_side(entry.roll(rng), rng));
+                upgrade.rarity = upgrade.rarity.max(entry.rarity);
+            }
+        }
+
+        upgrade
+    }
+}
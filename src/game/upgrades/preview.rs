@@ -0,0 +1,468 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::game::{
+    castle::{Castle, CastleWall},
+    enemies::{spawn::EnemyBuffs, GlobalEnemyBuffs},
+    stats::{Stat, StatSource, StatUnit},
+    weapons::{
+        crossbow::{base_crossbow, AnyCrossbow, CrossbowBuffs},
+        molotov::{base_molotov, AnyMolotov, MolotovBuffs},
+        GlobalWeaponBuffs,
+    },
+};
+
+use super::{
+    apply::{
+        apply_enemy_upgrade, apply_global_enemy_upgrade, apply_global_side_effect,
+        apply_global_weapon_upgrade, apply_level_up_reward, apply_wall_upgrade,
+        apply_weapon_upgrade, weaken_weapons,
+    },
+    options::LevelUpOptions,
+    East, GlobalEnemyUpgrade, GlobalWallUpgrade, GlobalWeaponUpgrade, LevelUpReward, North, Side,
+    SideBuffs, SideDebuffs, SideEffect, South, Upgrade, WallUpgrade, WeaponEvolution, West,
+};
+
+/// Current and resulting stats of the upgrade.
+/// Upgrades are applied to copies of the buffs with the same
+/// functions the apply systems use.
+#[derive(SystemParam)]
+pub struct UpgradePreview<'w, 's> {
+    castle: Query<'w, 's, &'static Castle>,
+    options: Res<'w, LevelUpOptions>,
+    global_weapon_buffs: Res<'w, GlobalWeaponBuffs>,
+    global_enemy_buffs: Res<'w, GlobalEnemyBuffs>,
+    north: SidePreview<'w, 's, North>,
+    south: SidePreview<'w, 's, South>,
+    west: SidePreview<'w, 's, West>,
+    east: SidePreview<'w, 's, East>,
+}
+
+#[derive(SystemParam)]
+pub struct SidePreview<'w, 's, S: Side> {
+    wall: Query<'w, 's, &'static CastleWall<S>>,
+    crossbow: Query<'w, 's, AnyCrossbow<S>>,
+    molotov: Query<'w, 's, AnyMolotov<S>>,
+    crossbow_buffs: Res<'w, CrossbowBuffs<S>>,
+    molotov_buffs: Res<'w, MolotovBuffs<S>>,
+    enemy_buffs: Res<'w, EnemyBuffs<S>>,
+}
+
+impl<'w, 's> UpgradePreview<'w, 's> {
+    /// One line for every stat the upgrade changes,
+    /// buffs first and side effects after them
+    pub fn preview(&self, upgrade: &Upgrade) -> (Vec<String>, Vec<String>) {
+        let level = self
+            .castle
+            .get_single()
+            .map(|castle| castle.upgrade_level())
+            .unwrap_or_default();
+
+        let mut buffs = vec![];
+        let mut debuffs = vec![];
+
+        if let Some(upgrade) = upgrade.global_wall_upgrade {
+            buffs.push(self.global_wall_preview(upgrade));
+        }
+        if let Some(upgrade) = upgrade.global_weapon_upgrade {
+            let source = StatSource::Upgrade {
+                level,
+                global: true,
+            };
+            buffs.push(self.global_weapon_preview(upgrade, source));
+        }
+        if let Some(reward) = upgrade.level_up_reward {
+            buffs.push(self.level_up_reward_preview(reward));
+        }
+        if let Some(upgrade) = upgrade.global_enemy_upgrade {
+            let source = StatSource::Upgrade {
+                level,
+                global: true,
+            };
+            debuffs.push(self.global_enemy_preview(upgrade, source));
+        }
+        if let Some(side_effect) = upgrade.global_side_effect {
+            let mut options = self.options.clone();
+            apply_global_side_effect(&mut options, side_effect);
+            debuffs.push(format!(
+                "level up cards {} -> {}",
+                self.options.offered_cards(),
+                options.offered_cards()
+            ));
+        }
+
+        let source = StatSource::Upgrade {
+            level,
+            global: false,
+        };
+        let (side_buffs, side_debuffs) = upgrade.north_upgrades();
+        self.north.preview(
+            self,
+            side_buffs,
+            side_debuffs,
+            source,
+            &mut buffs,
+            &mut debuffs,
+        );
+        let (side_buffs, side_debuffs) = upgrade.south_upgrades();
+        self.south.preview(
+            self,
+            side_buffs,
+            side_debuffs,
+            source,
+            &mut buffs,
+            &mut debuffs,
+        );
+        let (side_buffs, side_debuffs) = upgrade.west_upgrades();
+        self.west.preview(
+            self,
+            side_buffs,
+            side_debuffs,
+            source,
+            &mut buffs,
+            &mut debuffs,
+        );
+        let (side_buffs, side_debuffs) = upgrade.east_upgrades();
+        self.east.preview(
+            self,
+            side_buffs,
+            side_debuffs,
+            source,
+            &mut buffs,
+            &mut debuffs,
+        );
+
+        (buffs, debuffs)
+    }
+
+    fn global_wall_preview(&self, upgrade: GlobalWallUpgrade) -> String {
+        match upgrade {
+            GlobalWallUpgrade::AdditionalMaxHp(value) => format!(
+                "walls max hp {} -> {}",
+                self.walls_hp(|wall| wall.max_health, 0),
+                self.walls_hp(|wall| wall.max_health, value),
+            ),
+            GlobalWallUpgrade::Heal(value) => format!(
+                "walls hp {} -> {}",
+                self.walls_hp(|wall| wall.health, 0),
+                self.walls_hp(|wall| (wall.health + value).min(wall.max_health), 0),
+            ),
+        }
+    }
+
+    fn level_up_reward_preview(&self, reward: LevelUpReward) -> String {
+        let mut options = self.options.clone();
+        apply_level_up_reward(&mut options, reward);
+        match reward {
+            LevelUpReward::Rerolls(_) => {
+                format!("rerolls {} -> {}", self.options.rerolls, options.rerolls)
+            }
+            LevelUpReward::Banishes(_) => {
+                format!("banishes {} -> {}", self.options.banishes, options.banishes)
+            }
+        }
+    }
+
+    /// Hp of all walls in the `North/South/West/East` order
+    fn walls_hp(&self, hp: impl Fn(WallHp) -> i32, added: i32) -> String {
+        [
+            self.north.wall_hp(),
+            self.south.wall_hp(),
+            self.west.wall_hp(),
+            self.east.wall_hp(),
+        ]
+        .map(|wall| (hp(wall) + added).to_string())
+        .join("/")
+    }
+
+    fn global_weapon_preview(&self, upgrade: GlobalWeaponUpgrade, source: StatSource) -> String {
+        let mut buffs = self.global_weapon_buffs.clone();
+        apply_global_weapon_upgrade(&mut buffs, upgrade, source);
+        let (name, before, after, unit) = match upgrade {
+            GlobalWeaponUpgrade::Damage(_) | GlobalWeaponUpgrade::DamageFlat(_) => (
+                "damage",
+                &self.global_weapon_buffs.damage,
+                &buffs.damage,
+                StatUnit::Integer,
+            ),
+            GlobalWeaponUpgrade::CritDamage(_) => (
+                "crit damage",
+                &self.global_weapon_buffs.crit_damage,
+                &buffs.crit_damage,
+                StatUnit::Percent,
+            ),
+            GlobalWeaponUpgrade::CritChance(_) => (
+                "crit chance",
+                &self.global_weapon_buffs.crit_chance,
+                &buffs.crit_chance,
+                StatUnit::Percent,
+            ),
+        };
+        format!(
+            "weapons {name} {} -> {}",
+            buff_preview(before, unit),
+            buff_preview(after, unit)
+        )
+    }
+
+    fn global_enemy_preview(&self, upgrade: GlobalEnemyUpgrade, source: StatSource) -> String {
+        let mut buffs = self.global_enemy_buffs.clone();
+        apply_global_enemy_upgrade(&mut buffs, upgrade, source);
+        let (name, before, after) = match upgrade {
+            GlobalEnemyUpgrade::Health(_) => {
+                ("health", &self.global_enemy_buffs.health, &buffs.health)
+            }
+            GlobalEnemyUpgrade::Speed(_) => ("speed", &self.global_enemy_buffs.speed, &buffs.speed),
+            GlobalEnemyUpgrade::Exp(_) => ("exp", &self.global_enemy_buffs.exp, &buffs.exp),
+            GlobalEnemyUpgrade::Damage(_) => {
+                ("damage", &self.global_enemy_buffs.damage, &buffs.damage)
+            }
+            GlobalEnemyUpgrade::AttackSpeed(_) => (
+                "attack speed",
+                &self.global_enemy_buffs.attack_speed,
+                &buffs.attack_speed,
+            ),
+        };
+        format!(
+            "enemies {name} {:+.1}% -> {:+.1}%",
+            before.percent() * 100.0,
+            after.percent() * 100.0
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WallHp {
+    health: i32,
+    max_health: i32,
+}
+
+impl<'w, 's, S: Side> SidePreview<'w, 's, S> {
+    fn wall_hp(&self) -> WallHp {
+        self.wall
+            .get_single()
+            .map(|wall| WallHp {
+                health: wall.health,
+                max_health: wall.max_health,
+            })
+            .unwrap_or(WallHp {
+                health: 0,
+                max_health: 0,
+            })
+    }
+
+    fn preview(
+        &self,
+        upgrade_preview: &UpgradePreview,
+        side_buffs: SideBuffs<S>,
+        side_debuffs: SideDebuffs<S>,
+        source: StatSource,
+        buffs: &mut Vec<String>,
+        debuffs: &mut Vec<String>,
+    ) {
+        let side = format!("{:?}", S::default());
+
+        if let (Some(upgrade), Ok(wall)) = (side_buffs.wall_upgrade, self.wall.get_single()) {
+            let mut upgraded = wall.clone();
+            apply_wall_upgrade(&mut upgraded, upgrade);
+            buffs.push(match upgrade {
+                WallUpgrade::AdditionalMaxHp(_) | WallUpgrade::Heal(_) => format!(
+                    "{side} wall hp {}/{} -> {}/{}",
+                    wall.health, wall.max_health, upgraded.health, upgraded.max_health
+                ),
+                WallUpgrade::MissileBlock(_) => format!(
+                    "{side} missile block {:.1}% -> {:.1}%",
+                    wall.missile_block * 100.0,
+                    upgraded.missile_block * 100.0
+                ),
+                WallUpgrade::MissileInterception(_) => format!(
+                    "{side} missile interception {:.1}% -> {:.1}%",
+                    wall.missile_interception * 100.0,
+                    upgraded.missile_interception * 100.0
+                ),
+            });
+        }
+
+        if let Some(upgrade) = side_buffs.weapon_upgrade {
+            let global_buffs = &upgrade_preview.global_weapon_buffs;
+            let mut crossbow_buffs = self.crossbow_buffs.clone();
+            let mut molotov_buffs = self.molotov_buffs.clone();
+            apply_weapon_upgrade(&mut crossbow_buffs, &mut molotov_buffs, upgrade, source);
+
+            if let Ok(crossbow) = self.crossbow.get_single().map(base_crossbow) {
+                let before = crossbow.with_buffs(&self.crossbow_buffs, global_buffs);
+                let after = crossbow.with_buffs(&crossbow_buffs, global_buffs);
+                let stats = [
+                    ("damage", &before.damage, &after.damage, StatUnit::Integer),
+                    ("range", &before.range, &after.range, StatUnit::Number),
+                    (
+                        "crit damage",
+                        &before.crit_damage,
+                        &after.crit_damage,
+                        StatUnit::Percent,
+                    ),
+                    (
+                        "crit chance",
+                        &before.crit_chance,
+                        &after.crit_chance,
+                        StatUnit::Percent,
+                    ),
+                    (
+                        "attack speed",
+                        &before.attack_speed,
+                        &after.attack_speed,
+                        StatUnit::Number,
+                    ),
+                ];
+                push_changed_stats(buffs, &side, "crossbow", &stats);
+            }
+            if let Ok(molotov) = self.molotov.get_single().map(base_molotov) {
+                let before = molotov.with_buffs(&self.molotov_buffs, global_buffs);
+                let after = molotov.with_buffs(&molotov_buffs, global_buffs);
+                let stats = [
+                    ("damage", &before.damage, &after.damage, StatUnit::Integer),
+                    (
+                        "crit damage",
+                        &before.crit_damage,
+                        &after.crit_damage,
+                        StatUnit::Percent,
+                    ),
+                    (
+                        "crit chance",
+                        &before.crit_chance,
+                        &after.crit_chance,
+                        StatUnit::Percent,
+                    ),
+                    (
+                        "area size",
+                        &before.area_size,
+                        &after.area_size,
+                        StatUnit::Number,
+                    ),
+                    (
+                        "area attack speed",
+                        &before.area_attack_speed,
+                        &after.area_attack_speed,
+                        StatUnit::Number,
+                    ),
+                    (
+                        "area lifespan",
+                        &before.area_lifespan,
+                        &after.area_lifespan,
+                        StatUnit::Number,
+                    ),
+                    (
+                        "attack speed",
+                        &before.attack_speed,
+                        &after.attack_speed,
+                        StatUnit::Number,
+                    ),
+                ];
+                push_changed_stats(buffs, &side, "molotov", &stats);
+            }
+        }
+
+        if let Some(evolution) = side_buffs.evolution {
+            let weapon = match evolution {
+                WeaponEvolution::RepeatingCrossbow => "crossbow -> repeating crossbow",
+                WeaponEvolution::NapalmMolotov => "molotov -> napalm molotov",
+            };
+            buffs.push(format!("{side} {weapon}"));
+        }
+
+        if let Some(upgrade) = side_debuffs.enemy_upgrade {
+            let global_buffs = &upgrade_preview.global_enemy_buffs;
+            let mut enemy_buffs = self.enemy_buffs.clone();
+            apply_enemy_upgrade(&mut enemy_buffs, upgrade, source);
+            let before = self.enemy_buffs.with_global_buffs(global_buffs);
+            let after = enemy_buffs.with_global_buffs(global_buffs);
+            let stats = [
+                ("health", &before.health, &after.health),
+                ("speed", &before.speed, &after.speed),
+                ("exp", &before.exp, &after.exp),
+                ("damage", &before.damage, &after.damage),
+                ("attack speed", &before.attack_speed, &after.attack_speed),
+            ];
+            for (name, before, after) in stats {
+                if before.percent() != after.percent() {
+                    debuffs.push(format!(
+                        "{side} enemy {name} {:+.1}% -> {:+.1}%",
+                        before.percent() * 100.0,
+                        after.percent() * 100.0
+                    ));
+                }
+            }
+        }
+
+        if let Some(curse) = side_debuffs.curse {
+            debuffs.push(format!("{side} curse {curse}"));
+        }
+
+        match side_debuffs.side_effect {
+            Some(SideEffect::WeaponDamage(value)) => {
+                let global_buffs = &upgrade_preview.global_weapon_buffs;
+                let mut crossbow_buffs = self.crossbow_buffs.clone();
+                let mut molotov_buffs = self.molotov_buffs.clone();
+                weaken_weapons(&mut crossbow_buffs, &mut molotov_buffs, value, source);
+
+                if let Ok(crossbow) = self.crossbow.get_single().map(base_crossbow) {
+                    let before = crossbow.with_buffs(&self.crossbow_buffs, global_buffs);
+                    let after = crossbow.with_buffs(&crossbow_buffs, global_buffs);
+                    let stats = [("damage", &before.damage, &after.damage, StatUnit::Integer)];
+                    push_changed_stats(debuffs, &side, "crossbow", &stats);
+                }
+                if let Ok(molotov) = self.molotov.get_single().map(base_molotov) {
+                    let before = molotov.with_buffs(&self.molotov_buffs, global_buffs);
+                    let after = molotov.with_buffs(&molotov_buffs, global_buffs);
+                    let stats = [("damage", &before.damage, &after.damage, StatUnit::Integer)];
+                    push_changed_stats(debuffs, &side, "molotov", &stats);
+                }
+            }
+            Some(SideEffect::WallMaxHpDrain(value)) => {
+                if let Ok(wall) = self.wall.get_single() {
+                    debuffs.push(format!(
+                        "{side} wall max hp drain {:.2}/s -> {:.2}/s",
+                        wall.max_hp_drain,
+                        wall.max_hp_drain + value
+                    ));
+                }
+            }
+            Some(SideEffect::ExtraSpawn(value)) => {
+                debuffs.push(format!("{side} extra spawn of {value} enemies"));
+            }
+            Some(SideEffect::EliteChance(value)) => {
+                debuffs.push(format!("{side} elite chance +{value:.1}%"));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Lines only for stats changed by the upgrade
+fn push_changed_stats(
+    lines: &mut Vec<String>,
+    side: &str,
+    weapon: &str,
+    stats: &[(&str, &Stat, &Stat, StatUnit)],
+) {
+    for (name, before, after, unit) in stats {
+        if before.value() != after.value() {
+            lines.push(format!(
+                "{side} {weapon} {name} {} -> {}",
+                unit.format(before.value()),
+                unit.format(after.value())
+            ));
+        }
+    }
+}
+
+/// Global buff with only modifiers.
+/// Percent buffs are printed as percents and flat as values
+fn buff_preview(buff: &Stat, unit: StatUnit) -> String {
+    let percent = buff.percent();
+    let flat = buff.flat();
+    match (flat != 0.0, percent != 0.0) {
+        (true, true) => format!("+{} {:+.1}%", unit.format(flat), percent * 100.0),
+        (true, false) => format!("+{}", unit.format(flat)),
+        _ => format!("{:+.1}%", percent * 100.0),
+    }
+}
@@ -0,0 +1,185 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use crate::{
+    game::{
+        castle::{Castle, CastleWall},
+        stats::StatSource,
+        weapons::{crossbow::CrossbowBuffs, molotov::MolotovBuffs},
+    },
+    GlobalState,
+};
+
+use super::{
+    apply::{apply_wall_upgrade, apply_weapon_upgrade, UpgradeSet},
+    history::UpgradeHistory,
+    pool::{WallUpgradeKind, WeaponUpgradeKind},
+    East, GameState, North, Side, South, UpgradeAssets, West,
+};
+
+pub struct SynergiesPlugin;
+
+impl Plugin for SynergiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SynergyEvent>()
+            .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
+            .add_systems(
+                (
+                    detect_synergies::<North>,
+                    detect_synergies::<South>,
+                    detect_synergies::<West>,
+                    detect_synergies::<East>,
+                )
+                    .in_set(UpgradeSet::Apply)
+                    .in_set(OnUpdate(GameState::LevelUp)),
+            );
+    }
+}
+
+/// Bonuses for picking related upgrades on one side.
+/// Loaded from `assets/data/game.synergies.ron`
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "3b8e1f6c-9a24-4d07-b5c3-7e2f0a9d4c18"]
+pub struct SynergyDefinitions {
+    pub synergies: Vec<SynergyDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SynergyDefinition {
+    pub name: String,
+    /// All requirements should be met by upgrades of one side
+    pub requirements: Vec<SynergyRequirement>,
+    /// Applied to the side that triggered the synergy
+    pub bonuses: Vec<SynergyBonus>,
+}
+
+/// Number of picked upgrades with any of the kinds
+#[derive(Debug, Clone, Deserialize)]
+pub struct SynergyRequirement {
+    pub upgrades: Vec<WeaponUpgradeKind>,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum SynergyBonus {
+    Weapon {
+        upgrade: WeaponUpgradeKind,
+        value: f32,
+    },
+    Wall {
+        upgrade: WallUpgradeKind,
+        value: f32,
+    },
+}
+
+impl SynergyDefinition {
+    fn is_met<S: Side>(&self, history: &UpgradeHistory) -> bool {
+        self.requirements.iter().all(|requirement| {
+            let count = history
+                .records
+                .iter()
+                .filter_map(|record| record.upgrade.weapon_upgrade)
+                .filter_map(|upgrade| upgrade.on_side::<S>().map(|upgrade| upgrade.kind()))
+                .filter(|kind| requirement.upgrades.contains(kind))
+                .count();
+            requirement.count as usize <= count
+        })
+    }
+}
+
+/// Sent when a synergy is triggered
+#[derive(Debug, Clone)]
+pub struct SynergyEvent {
+    pub synergy: ActiveSynergy,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveSynergy {
+    pub name: String,
+    pub side: String,
+    /// Level of the upgrade that triggered the synergy
+    pub level: u32,
+}
+
+impl std::fmt::Display for ActiveSynergy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} on {} at lvl {}",
+            self.name, self.side, self.level
+        ))
+    }
+}
+
+/// Synergies triggered in the current run.
+/// Every synergy triggers only once for each side
+#[derive(Debug, Default, Resource)]
+pub struct ActiveSynergies {
+    pub synergies: Vec<ActiveSynergy>,
+}
+
+impl ActiveSynergies {
+    fn is_active(&self, name: &str, side: &str) -> bool {
+        self.synergies
+            .iter()
+            .any(|synergy| synergy.name == name && synergy.side == side)
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(ActiveSynergies::default());
+}
+
+/// Checks upgrade history of the side after every pick
+/// and applies bonuses of new synergies
+fn detect_synergies<S: Side>(
+    upgrade_assets: Res<UpgradeAssets>,
+    synergy_definitions: Res<Assets<SynergyDefinitions>>,
+    history: Res<UpgradeHistory>,
+    castle: Query<&Castle>,
+    mut wall: Query<&mut CastleWall<S>>,
+    mut crossbow_buffs: ResMut<CrossbowBuffs<S>>,
+    mut molotov_buffs: ResMut<MolotovBuffs<S>>,
+    mut active_synergies: ResMut<ActiveSynergies>,
+    mut synergy_events: EventWriter<SynergyEvent>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+    let Some(definitions) = synergy_definitions.get(&upgrade_assets.synergies) else {
+        warn!("Synergy definitions are not loaded");
+        return;
+    };
+
+    let side = format!("{:?}", S::default());
+    let level = castle.single().upgrade_level();
+    let source = StatSource::Synergy { level };
+    for definition in definitions.synergies.iter() {
+        if active_synergies.is_active(&definition.name, &side) || !definition.is_met::<S>(&history)
+        {
+            continue;
+        }
+
+        for bonus in definition.bonuses.iter() {
+            match *bonus {
+                SynergyBonus::Weapon { upgrade, value } => apply_weapon_upgrade(
+                    &mut crossbow_buffs,
+                    &mut molotov_buffs,
+                    upgrade.with_value(value),
+                    source,
+                ),
+                SynergyBonus::Wall { upgrade, value } => {
+                    apply_wall_upgrade(&mut wall.single_mut(), upgrade.with_value(value))
+                }
+            }
+        }
+
+        let synergy = ActiveSynergy {
+            name: definition.name.clone(),
+            side: side.clone(),
+            level,
+        };
+        info!("Synergy triggered: {synergy}");
+        active_synergies.synergies.push(synergy.clone());
+        synergy_events.send(SynergyEvent { synergy });
+    }
+}
@@ -2,11 +2,12 @@ use std::marker::PhantomData;
 
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
+use serde::Deserialize;
 
 use crate::{
     game::{
-        damage::projectile::ProjectileBundle, enemies::Enemy, East, GameState, North, Side, South,
-        West,
+        damage::projectile::ProjectileBundle, enemies::Enemy, stats::Stat, East, GameState, North,
+        Side, South, West,
     },
     utils::remove_all_with,
     GameAssets, GameSettings, GlobalState,
@@ -16,12 +17,6 @@ use super::{GlobalWeaponBuffs, WeaponsAssets};
 
 const DEFAULT_BOLT_SIZE: f32 = 3.0;
 
-const DEFAULT_CROSSBOW_DAMAGE: i32 = 20;
-const DEFAULT_CROSSBOW_CRIT_DAMAGE: f32 = 2.0;
-const DEFAULT_CROSSBOW_CRIT_CHANCE: f32 = 0.05;
-const DEFAULT_CROSSBOW_RANGE: f32 = 400.0;
-const DEFAULT_CROSSBOW_ATTACK_SPEED: f32 = 1.0;
-
 /// Offsets arrow spawn point in the enemy direction
 const DEFAULT_BOLT_SPAWN_OFFSET: f32 = 30.0;
 
@@ -46,100 +41,212 @@ impl Plugin for CrossbowPlugin {
 #[derive(Component)]
 pub struct CrossbowMarker;
 
-#[derive(Debug, Default, Resource)]
+#[derive(Debug, Default, Clone, Resource)]
 pub struct CrossbowBuffs<S: Side> {
-    pub damage: f32,
-    pub damage_flat: i32,
+    pub damage: Stat,
+    pub crit_damage: Stat,
+    pub crit_chance: Stat,
+    pub range: Stat,
+    pub attack_speed: Stat,
+    _phantom: PhantomData<S>,
+}
+
+/// Crossbow stats without any buffs.
+/// Part of `WeaponStats`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrossbowStats {
+    pub damage: i32,
+    pub range: f32,
     pub crit_damage: f32,
     pub crit_chance: f32,
-    pub range: f32,
     pub attack_speed: f32,
-    _phantom: PhantomData<S>,
 }
 
-#[derive(Component)]
+impl Default for CrossbowStats {
+    /// Used only if weapon data is not loaded
+    fn default() -> Self {
+        Self {
+            damage: 20,
+            range: 400.0,
+            crit_damage: 2.0,
+            crit_chance: 0.05,
+            attack_speed: 1.0,
+        }
+    }
+}
+
+/// Evolution of the crossbow shooting several bolts at once.
+/// Part of `EvolutionStats`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RepeatingCrossbowStats {
+    /// Percent of crossbow attack speed buffs needed to evolve.
+    /// 0.5 is +50%
+    pub attack_speed_buff: f32,
+    /// Percent of crossbow damage buffs needed to evolve
+    pub damage_buff: f32,
+    /// Bolts in each shot
+    pub bolts: u32,
+    /// Angle between bolts in degrees
+    pub spread_angle: f32,
+}
+
+impl Default for RepeatingCrossbowStats {
+    /// Used only if weapon data is not loaded
+    fn default() -> Self {
+        Self {
+            attack_speed_buff: 1.0,
+            damage_buff: 0.3,
+            bolts: 3,
+            spread_angle: 10.0,
+        }
+    }
+}
+
+impl RepeatingCrossbowStats {
+    pub fn can_evolve<S: Side>(&self, buffs: &CrossbowBuffs<S>) -> bool {
+        self.attack_speed_buff <= buffs.attack_speed.percent()
+            && self.damage_buff <= buffs.damage.percent()
+    }
+
+    pub fn repeating_crossbow<S: Side>(&self, crossbow: Crossbow<S>) -> RepeatingCrossbow<S> {
+        RepeatingCrossbow {
+            crossbow,
+            bolts: self.bolts,
+            spread_angle: self.spread_angle,
+        }
+    }
+}
+
+impl CrossbowStats {
+    pub fn crossbow<S: Side>(&self) -> Crossbow<S> {
+        Crossbow::new(
+            self.damage,
+            self.range,
+            self.crit_damage,
+            self.crit_chance,
+            self.attack_speed,
+        )
+    }
+}
+
+#[derive(Clone, Component)]
 pub struct Crossbow<S: Side> {
-    damage: i32,
-    range: f32,
-    crit_damage: f32,
-    crit_chance: f32,
+    pub damage: Stat,
+    pub range: Stat,
+    /// Multiplier for the damage on crit
+    pub crit_damage: Stat,
+    pub crit_chance: Stat,
+    /// Shots per second
+    pub attack_speed: Stat,
     attack_timer: Timer,
     _phantom: PhantomData<S>,
 }
 
 impl<S: Side> std::fmt::Display for Crossbow<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("damage {}\n", self.damage))?;
-        f.write_fmt(format_args!("range {:.1}\n", self.range))?;
+        f.write_fmt(format_args!("damage {}\n", self.damage.value() as i32))?;
+        f.write_fmt(format_args!("range {:.1}\n", self.range.value()))?;
         f.write_fmt(format_args!(
             "crit damage {:.1}%\n",
-            self.crit_damage * 100.0
+            self.crit_damage.value() * 100.0
         ))?;
         f.write_fmt(format_args!(
             "crit chance {:.1}%\n",
-            self.crit_chance * 100.0
+            self.crit_chance.value() * 100.0
         ))?;
         f.write_fmt(format_args!(
             "attack speed {:.1}/s\n",
-            self.attack_timer.duration().as_secs_f32()
+            self.attack_speed.value()
         ))?;
         Ok(())
     }
 }
 
-impl<S: Side> Default for Crossbow<S> {
-    fn default() -> Self {
+impl<S: Side> Crossbow<S> {
+    pub fn new(
+        damage: i32,
+        range: f32,
+        crit_damage: f32,
+        crit_chance: f32,
+        attack_speed: f32,
+    ) -> Self {
         Self {
-            damage: DEFAULT_CROSSBOW_DAMAGE,
-            range: DEFAULT_CROSSBOW_RANGE,
-            crit_damage: DEFAULT_CROSSBOW_CRIT_DAMAGE,
-            crit_chance: DEFAULT_CROSSBOW_CRIT_CHANCE,
-            attack_timer: Timer::from_seconds(
-                1.0 / DEFAULT_CROSSBOW_ATTACK_SPEED,
-                TimerMode::Repeating,
-            ),
+            damage: Stat::new(damage as f32),
+            range: Stat::new(range),
+            crit_damage: Stat::new(crit_damage),
+            crit_chance: Stat::new(crit_chance),
+            attack_speed: Stat::new(attack_speed),
+            attack_timer: Timer::from_seconds(1.0 / attack_speed, TimerMode::Repeating),
             _phantom: PhantomData,
         }
     }
-}
 
-impl<S: Side> Crossbow<S> {
+    /// Crossbow with all buffs applied to its stats
     pub fn with_buffs(
-        self,
+        &self,
         crossbow_buffs: &CrossbowBuffs<S>,
         global_weapons_buffs: &GlobalWeaponBuffs,
     ) -> Self {
+        let attack_speed = self.attack_speed.with(&crossbow_buffs.attack_speed);
         Self {
-            damage: ((self.damage + crossbow_buffs.damage_flat + global_weapons_buffs.damage_flat)
-                as f32
-                * (1.0 + crossbow_buffs.damage + global_weapons_buffs.damage))
-                as i32,
-            range: self.range * (1.0 + crossbow_buffs.range),
-            crit_damage: self.crit_damage
-                + crossbow_buffs.crit_damage
-                + global_weapons_buffs.crit_damage,
-            crit_chance: self.crit_chance
-                + crossbow_buffs.crit_chance
-                + global_weapons_buffs.crit_chance,
-            attack_timer: Timer::from_seconds(
-                DEFAULT_CROSSBOW_ATTACK_SPEED * (1.0 + crossbow_buffs.attack_speed),
-                TimerMode::Repeating,
-            ),
+            damage: self
+                .damage
+                .with(&crossbow_buffs.damage)
+                .with(&global_weapons_buffs.damage),
+            range: self.range.with(&crossbow_buffs.range),
+            crit_damage: self
+                .crit_damage
+                .with(&crossbow_buffs.crit_damage)
+                .with(&global_weapons_buffs.crit_damage),
+            crit_chance: self
+                .crit_chance
+                .with(&crossbow_buffs.crit_chance)
+                .with(&global_weapons_buffs.crit_chance),
+            attack_timer: Timer::from_seconds(1.0 / attack_speed.value(), TimerMode::Repeating),
+            attack_speed,
             _phantom: PhantomData,
         }
     }
 }
 
+/// Evolved crossbow.
+/// Replaces `Crossbow` on the wall and keeps its stats
+#[derive(Clone, Component)]
+pub struct RepeatingCrossbow<S: Side> {
+    pub crossbow: Crossbow<S>,
+    /// Bolts in each shot
+    pub bolts: u32,
+    /// Angle between bolts in degrees
+    pub spread_angle: f32,
+}
+
+/// Crossbow of the side before or after the evolution
+pub type AnyCrossbow<S> = AnyOf<(&'static Crossbow<S>, &'static RepeatingCrossbow<S>)>;
+
+/// Mutable crossbow of the side before or after the evolution
+pub type AnyCrossbowMut<S> = AnyOf<(&'static mut Crossbow<S>, &'static mut RepeatingCrossbow<S>)>;
+
+/// Stats of the crossbow from the `AnyCrossbow` query item
+pub fn base_crossbow<'a, S: Side>(
+    any_crossbow: (Option<&'a Crossbow<S>>, Option<&'a RepeatingCrossbow<S>>),
+) -> &'a Crossbow<S> {
+    match any_crossbow {
+        (Some(crossbow), _) => crossbow,
+        (None, Some(repeating)) => &repeating.crossbow,
+        (None, None) => unreachable!("AnyOf matches at least one component"),
+    }
+}
+
 #[derive(Bundle)]
 pub struct CrossbowBundle<S: Side> {
     crossbow: Crossbow<S>,
     marker: CrossbowMarker,
 }
 
-impl<S: Side> Default for CrossbowBundle<S> {
-    fn default() -> Self {
+impl<S: Side> CrossbowBundle<S> {
+    pub fn new(crossbow: Crossbow<S>) -> Self {
         Self {
-            crossbow: Default::default(),
+            crossbow,
             marker: CrossbowMarker,
         }
     }
@@ -154,7 +261,7 @@ fn setup(mut commands: Commands) {
 
 fn crossbow_attack<S: Side>(
     time: Res<Time>,
-    audio: Res<Audio>,
+    audio: Option<Res<Audio>>,
     game_assets: Res<GameAssets>,
     game_settings: Res<GameSettings>,
     weapon_assets: Res<WeaponsAssets>,
@@ -162,19 +269,31 @@ fn crossbow_attack<S: Side>(
     global_weapons_buffs: Res<GlobalWeaponBuffs>,
     enemies: Query<&Transform, With<Enemy<S>>>,
     mut commands: Commands,
-    mut crossbows: Query<(&Transform, &mut Crossbow<S>)>,
+    mut crossbows: Query<(&Transform, AnyCrossbowMut<S>)>,
 ) {
-    for (transform, mut crossbow) in crossbows.iter_mut() {
+    for (transform, any_crossbow) in crossbows.iter_mut() {
+        let (crossbow, bolts, spread_angle) = match any_crossbow {
+            (Some(crossbow), _) => (crossbow.into_inner(), 1, 0.0),
+            (None, Some(repeating)) => {
+                let repeating = repeating.into_inner();
+                (
+                    &mut repeating.crossbow,
+                    repeating.bolts,
+                    repeating.spread_angle,
+                )
+            }
+            (None, None) => continue,
+        };
+
         if !crossbow.attack_timer.tick(time.delta()).finished() {
             continue;
         }
 
-        let attack_speed = DEFAULT_CROSSBOW_ATTACK_SPEED * (1.0 + crossbow_buffs.attack_speed);
-
-        crossbow.attack_timer = Timer::from_seconds(1.0 / attack_speed, TimerMode::Repeating);
+        let buffed_crossbow = crossbow.with_buffs(&crossbow_buffs, &global_weapons_buffs);
+        crossbow.attack_timer = buffed_crossbow.attack_timer;
 
         let mut enemy_vec = Vec2::default();
-        let mut min_range = crossbow.range;
+        let mut min_range = buffed_crossbow.range.value();
         for enemy_transform in enemies.iter() {
             let vec = (enemy_transform.translation - transform.translation).truncate();
             let distance = vec.length();
@@ -185,46 +304,46 @@ fn crossbow_attack<S: Side>(
         }
 
         // no enemies in range
-        let range = crossbow.range * (1.0 + crossbow_buffs.range);
-        if range <= min_range {
+        if enemy_vec == Vec2::ZERO {
             continue;
         }
 
-        let arrow_speed = min_range * attack_speed;
-
-        let direction = enemy_vec.normalize();
-        let mut projectile_transform = *transform;
-        projectile_transform.translation += (direction * DEFAULT_BOLT_SPAWN_OFFSET).extend(0.0);
-
-        // rotates arrow in the enemy direaction
-        // arorw sprite looks to the left == NEG_X
-        let arrow_direction = Vec2::NEG_X;
-        projectile_transform.rotate_z(-direction.angle_between(arrow_direction));
-
-        let damage =
-            ((crossbow.damage + crossbow_buffs.damage_flat + global_weapons_buffs.damage_flat)
-                as f32
-                * (1.0 + crossbow_buffs.damage + global_weapons_buffs.damage)) as i32;
-        let crit_chance =
-            crossbow.crit_chance + crossbow_buffs.crit_chance + global_weapons_buffs.crit_chance;
-        let crit_damage = (damage as f32
-            * (crossbow.crit_damage
-                + crossbow_buffs.crit_damage
-                + global_weapons_buffs.crit_damage)) as i32;
-
-        commands.spawn(ProjectileBundle::<S>::new(
-            weapon_assets.arrow.clone(),
-            DEFAULT_BOLT_SIZE,
-            damage,
-            crit_damage,
-            crit_chance,
-            arrow_speed,
-            direction,
-            projectile_transform,
-        ));
-
-        audio
-            .play(game_assets.crossbow_shoot.clone())
-            .with_volume(game_settings.sound_volume);
+        let arrow_speed = min_range * buffed_crossbow.attack_speed.value();
+
+        let damage = buffed_crossbow.damage.value() as i32;
+        let crit_chance = buffed_crossbow.crit_chance.value();
+        let crit_damage = (damage as f32 * buffed_crossbow.crit_damage.value()) as i32;
+
+        // bolts are spread evenly around the enemy direction
+        let enemy_direction = enemy_vec.normalize();
+        for bolt in 0..bolts {
+            let angle = (bolt as f32 - (bolts - 1) as f32 / 2.0) * spread_angle.to_radians();
+            let direction = Vec2::from_angle(angle).rotate(enemy_direction);
+            let mut projectile_transform = *transform;
+            projectile_transform.translation += (direction * DEFAULT_BOLT_SPAWN_OFFSET).extend(0.0);
+
+            // rotates arrow in the enemy direaction
+            // arorw sprite looks to the left == NEG_X
+            let arrow_direction = Vec2::NEG_X;
+            projectile_transform.rotate_z(-direction.angle_between(arrow_direction));
+
+            commands.spawn(ProjectileBundle::<S>::new(
+                weapon_assets.arrow.clone(),
+                DEFAULT_BOLT_SIZE,
+                damage,
+                crit_damage,
+                crit_chance,
+                arrow_speed,
+                direction,
+                projectile_transform,
+            ));
+        }
+
+        // no audio in headless mode
+        if let Some(audio) = &audio {
+            audio
+                .play(game_assets.crossbow_shoot.clone())
+                .with_volume(game_settings.sound_volume);
+        }
     }
 }
@@ -1,8 +1,16 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, reflect::TypeUuid};
 use bevy_asset_loader::prelude::*;
+use serde::Deserialize;
 
 use crate::GlobalState;
 
+use self::{
+    crossbow::{CrossbowStats, RepeatingCrossbowStats},
+    molotov::{MolotovStats, NapalmMolotovStats},
+};
+
+use super::{data::RonAssetLoader, stats::Stat};
+
 pub struct WeaponsPlugin;
 
 pub mod crossbow;
@@ -10,15 +18,17 @@ pub mod molotov;
 
 impl Plugin for WeaponsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_collection_to_loading_state::<_, WeaponsAssets>(GlobalState::AssetLoading)
+        app.add_asset::<WeaponStats>()
+            .add_asset_loader(RonAssetLoader::<WeaponStats>::new(&["weapons.ron"]))
+            .add_collection_to_loading_state::<_, WeaponsAssets>(GlobalState::AssetLoading)
             .add_system(setup.in_schedule(OnEnter(GlobalState::InGame)))
             .add_plugin(crossbow::CrossbowPlugin)
             .add_plugin(molotov::MolotovPlugin);
     }
 }
 
-#[derive(AssetCollection, Resource)]
-struct WeaponsAssets {
+#[derive(AssetCollection, Resource, Default)]
+pub struct WeaponsAssets {
     #[asset(path = "sprites/arrow.png")]
     arrow: Handle<Image>,
     #[asset(path = "sprites/molotov.png")]
@@ -26,14 +36,38 @@ struct WeaponsAssets {
     #[asset(texture_atlas(tile_size_x = 32.0, tile_size_y = 32.0, columns = 3, rows = 1,))]
     #[asset(path = "sprites/fire.png")]
     pub fire: Handle<TextureAtlas>,
+    #[asset(path = "data/game.weapons.ron")]
+    pub stats: Handle<WeaponStats>,
+}
+
+impl WeaponsAssets {
+    /// Used to read data when asset loading is skipped
+    pub const STATS_PATH: &str = "data/game.weapons.ron";
+}
+
+/// Base stats of the castle weapons.
+/// Loaded from `assets/data/game.weapons.ron`
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "e3b6f1a4-58c2-4d7e-9a0b-2f4c6d8e1b37"]
+pub struct WeaponStats {
+    pub crossbow: CrossbowStats,
+    pub molotov: MolotovStats,
+    #[serde(default)]
+    pub evolutions: EvolutionStats,
+}
+
+/// Thresholds and stats of evolved weapons
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct EvolutionStats {
+    pub repeating_crossbow: RepeatingCrossbowStats,
+    pub napalm_molotov: NapalmMolotovStats,
 }
 
-#[derive(Debug, Default, Resource)]
+#[derive(Debug, Default, Clone, Resource)]
 pub struct GlobalWeaponBuffs {
-    pub damage: f32,
-    pub damage_flat: i32,
-    pub crit_damage: f32,
-    pub crit_chance: f32,
+    pub damage: Stat,
+    pub crit_damage: Stat,
+    pub crit_chance: Stat,
 }
 
 fn setup(mut commands: Commands) {
@@ -3,11 +3,14 @@ use std::marker::PhantomData;
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
 use rand::Rng;
+use serde::Deserialize;
 
 use crate::{
     game::{
         castle::CastleWall,
-        damage::area::{DamageArea, DamageAreaBundle},
+        damage::area::{AreaSpread, DamageArea, DamageAreaBundle},
+        rng::SideRng,
+        stats::Stat,
         East, GameState, North, Side, South, West,
     },
     utils::remove_all_with,
@@ -16,14 +19,7 @@ use crate::{
 
 use super::{GlobalWeaponBuffs, WeaponsAssets};
 
-const DEFAULT_AREA_SIZE: f32 = 20.0;
-const DEFAULT_AREA_DAMAGE: i32 = 10;
-const DEFAULT_AREA_ATTACK_SPEED: f32 = 0.5;
-const DEFAULT_AREA_LIFESPAN: f32 = 2.0;
-
 const DEFAULT_MOLOTOV_MIN_RANGE: f32 = 30.0;
-const DEFAULT_MOLOTOV_RANGE: f32 = 300.0;
-const DEFAULT_MOLOTOV_ATTACK_SPEED: f32 = 0.3;
 const DEFAULT_MOLOTOV_BOTTLE_IN_FLIGHT_TIME: f32 = 2.0;
 const DEFAULT_MOLOTOV_BOTTLE_IN_FLIGHT_ROTATION: f32 = std::f32::consts::PI * 5.0;
 
@@ -54,62 +50,158 @@ impl Plugin for MolotovPlugin {
 #[derive(Component)]
 pub struct MolotovMarker;
 
-#[derive(Default, Resource)]
+#[derive(Default, Clone, Resource)]
 pub struct MolotovBuffs<S: Side> {
-    pub damage: f32,
-    pub damage_flat: i32,
+    pub damage: Stat,
+    pub crit_damage: Stat,
+    pub crit_chance: Stat,
+    pub area_size: Stat,
+    pub attack_speed: Stat,
+    pub area_attack_speed: Stat,
+    pub area_lifespan: Stat,
+    _phatom: PhantomData<S>,
+}
+
+/// Molotov stats without any buffs.
+/// Part of `WeaponStats`
+#[derive(Debug, Clone, Deserialize)]
+pub struct MolotovStats {
+    pub damage: i32,
     pub crit_damage: f32,
     pub crit_chance: f32,
+    pub range: f32,
     pub area_size: f32,
-    pub attack_speed: f32,
     pub area_attack_speed: f32,
     pub area_lifespan: f32,
-    _phatom: PhantomData<S>,
+    pub attack_speed: f32,
 }
 
-#[derive(Component)]
+impl Default for MolotovStats {
+    /// Used only if weapon data is not loaded
+    fn default() -> Self {
+        Self {
+            damage: 10,
+            crit_damage: 1.0,
+            crit_chance: 0.0,
+            range: 300.0,
+            area_size: 20.0,
+            area_attack_speed: 2.0,
+            area_lifespan: 2.0,
+            attack_speed: 0.3,
+        }
+    }
+}
+
+/// Evolution of the molotov leaving long burning areas
+/// that spread when they burn out.
+/// Part of `EvolutionStats`
+#[derive(Debug, Clone, Deserialize)]
+pub struct NapalmMolotovStats {
+    /// Percent of molotov area size buffs needed to evolve.
+    /// 0.5 is +50%
+    pub area_size_buff: f32,
+    /// Percent of molotov area lifespan buffs needed to evolve
+    pub area_lifespan_buff: f32,
+    /// Multiplier for the area lifespan
+    pub lifespan_multiplier: f32,
+    /// Areas spawned around the burnt out area
+    pub spread_areas: u32,
+    /// Distance to spread areas as a multiplier of the area size
+    pub spread_distance: f32,
+}
+
+impl Default for NapalmMolotovStats {
+    /// Used only if weapon data is not loaded
+    fn default() -> Self {
+        Self {
+            area_size_buff: 0.3,
+            area_lifespan_buff: 0.3,
+            lifespan_multiplier: 2.0,
+            spread_areas: 4,
+            spread_distance: 1.5,
+        }
+    }
+}
+
+impl NapalmMolotovStats {
+    pub fn can_evolve<S: Side>(&self, buffs: &MolotovBuffs<S>) -> bool {
+        self.area_size_buff <= buffs.area_size.percent()
+            && self.area_lifespan_buff <= buffs.area_lifespan.percent()
+    }
+
+    pub fn napalm_molotov<S: Side>(&self, molotov: Molotov<S>) -> NapalmMolotov<S> {
+        NapalmMolotov {
+            molotov,
+            lifespan_multiplier: self.lifespan_multiplier,
+            spread: AreaSpread {
+                areas: self.spread_areas,
+                distance: self.spread_distance,
+            },
+        }
+    }
+}
+
+impl MolotovStats {
+    pub fn molotov<S: Side>(&self) -> Molotov<S> {
+        Molotov::new(
+            self.damage,
+            self.crit_damage,
+            self.crit_chance,
+            self.range,
+            self.area_size,
+            self.area_attack_speed,
+            self.area_lifespan,
+            self.attack_speed,
+        )
+    }
+}
+
+#[derive(Clone, Component)]
 pub struct Molotov<S: Side> {
-    damage: i32,
-    range: f32,
-    area_size: f32,
-    area_attack_speed: f32,
-    area_lifespan: f32,
+    pub damage: Stat,
+    /// Multiplier for the damage on crit
+    pub crit_damage: Stat,
+    pub crit_chance: Stat,
+    pub range: Stat,
+    pub area_size: Stat,
+    /// Area attacks per second
+    pub area_attack_speed: Stat,
+    pub area_lifespan: Stat,
+    /// Throws per second
+    pub attack_speed: Stat,
     attack_timer: Timer,
     _phantom: PhantomData<S>,
 }
 
 impl<S: Side> std::fmt::Display for Molotov<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("damage {}\n", self.damage))?;
-        f.write_fmt(format_args!("range {:.1}\n", self.range))?;
-        f.write_fmt(format_args!("area size {:.1}\n", self.area_size))?;
+        f.write_fmt(format_args!("damage {}\n", self.damage.value() as i32))?;
+        f.write_fmt(format_args!(
+            "crit damage {:.1}%\n",
+            self.crit_damage.value() * 100.0
+        ))?;
+        f.write_fmt(format_args!(
+            "crit chance {:.1}%\n",
+            self.crit_chance.value() * 100.0
+        ))?;
+        f.write_fmt(format_args!("range {:.1}\n", self.range.value()))?;
+        f.write_fmt(format_args!("area size {:.1}\n", self.area_size.value()))?;
         f.write_fmt(format_args!(
             "area attack speed {:.1}/s\n",
-            self.area_attack_speed
+            self.area_attack_speed.value()
+        ))?;
+        f.write_fmt(format_args!(
+            "area lifespan {:.1}s\n",
+            self.area_lifespan.value()
         ))?;
-        f.write_fmt(format_args!("area lifespan {:.1}s\n", self.area_lifespan))?;
         f.write_fmt(format_args!(
             "attack speed {:.1}/s\n",
-            self.attack_timer.duration().as_secs_f32()
+            self.attack_speed.value()
         ))?;
         Ok(())
     }
 }
 
-impl<S: Side> Default for Molotov<S> {
-    fn default() -> Self {
-        Self {
-            damage: DEFAULT_AREA_DAMAGE,
-            range: DEFAULT_MOLOTOV_RANGE,
-            area_size: DEFAULT_AREA_SIZE,
-            area_attack_speed: 1.0 / DEFAULT_AREA_ATTACK_SPEED,
-            area_lifespan: DEFAULT_AREA_LIFESPAN,
-            attack_timer: Timer::from_seconds(DEFAULT_MOLOTOV_ATTACK_SPEED, TimerMode::Repeating),
-            _phantom: PhantomData,
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct MolotovBottle<S: Side> {
     area: DamageArea<S>,
@@ -165,42 +257,109 @@ impl<S: Side> MolotovBottleBundle<S> {
             marker: MolotovMarker,
         }
     }
+
+    /// Area of the bottle spreads when it burns out
+    pub fn with_spread(mut self, spread: Option<AreaSpread>) -> Self {
+        self.bottle.area.spread = spread;
+        self
+    }
 }
 
 impl<S: Side> Molotov<S> {
+    pub fn new(
+        damage: i32,
+        crit_damage: f32,
+        crit_chance: f32,
+        range: f32,
+        area_size: f32,
+        area_attack_speed: f32,
+        area_lifespan: f32,
+        attack_speed: f32,
+    ) -> Self {
+        Self {
+            damage: Stat::new(damage as f32),
+            crit_damage: Stat::new(crit_damage),
+            crit_chance: Stat::new(crit_chance),
+            range: Stat::new(range),
+            area_size: Stat::new(area_size),
+            area_attack_speed: Stat::new(area_attack_speed),
+            area_lifespan: Stat::new(area_lifespan),
+            attack_speed: Stat::new(attack_speed),
+            attack_timer: Timer::from_seconds(1.0 / attack_speed, TimerMode::Repeating),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Molotov with all buffs applied to its stats
     pub fn with_buffs(
-        self,
+        &self,
         molotov_buffs: &MolotovBuffs<S>,
         global_weapons_buffs: &GlobalWeaponBuffs,
     ) -> Self {
+        let attack_speed = self.attack_speed.with(&molotov_buffs.attack_speed);
         Self {
-            damage: ((self.damage + molotov_buffs.damage_flat + global_weapons_buffs.damage_flat)
-                as f32
-                * (1.0 + molotov_buffs.damage + global_weapons_buffs.damage))
-                as i32,
-            range: self.range,
-            area_size: self.area_size * (1.0 + molotov_buffs.area_size),
-            area_attack_speed: self.area_attack_speed * (1.0 + molotov_buffs.area_attack_speed),
-            area_lifespan: self.area_lifespan * (1.0 + molotov_buffs.area_lifespan),
-            attack_timer: Timer::from_seconds(
-                DEFAULT_MOLOTOV_ATTACK_SPEED * (1.0 + molotov_buffs.attack_speed),
-                TimerMode::Repeating,
-            ),
+            damage: self
+                .damage
+                .with(&molotov_buffs.damage)
+                .with(&global_weapons_buffs.damage),
+            crit_damage: self
+                .crit_damage
+                .with(&molotov_buffs.crit_damage)
+                .with(&global_weapons_buffs.crit_damage),
+            crit_chance: self
+                .crit_chance
+                .with(&molotov_buffs.crit_chance)
+                .with(&global_weapons_buffs.crit_chance),
+            range: self.range.clone(),
+            area_size: self.area_size.with(&molotov_buffs.area_size),
+            area_attack_speed: self
+                .area_attack_speed
+                .with(&molotov_buffs.area_attack_speed),
+            area_lifespan: self.area_lifespan.with(&molotov_buffs.area_lifespan),
+            attack_timer: Timer::from_seconds(1.0 / attack_speed.value(), TimerMode::Repeating),
+            attack_speed,
             _phantom: PhantomData,
         }
     }
 }
 
+/// Evolved molotov.
+/// Replaces `Molotov` on the wall and keeps its stats
+#[derive(Clone, Component)]
+pub struct NapalmMolotov<S: Side> {
+    pub molotov: Molotov<S>,
+    /// Multiplier for the area lifespan
+    pub lifespan_multiplier: f32,
+    pub spread: AreaSpread,
+}
+
+/// Molotov of the side before or after the evolution
+pub type AnyMolotov<S> = AnyOf<(&'static Molotov<S>, &'static NapalmMolotov<S>)>;
+
+/// Mutable molotov of the side before or after the evolution
+pub type AnyMolotovMut<S> = AnyOf<(&'static mut Molotov<S>, &'static mut NapalmMolotov<S>)>;
+
+/// Stats of the molotov from the `AnyMolotov` query item
+pub fn base_molotov<'a, S: Side>(
+    any_molotov: (Option<&'a Molotov<S>>, Option<&'a NapalmMolotov<S>>),
+) -> &'a Molotov<S> {
+    match any_molotov {
+        (Some(molotov), _) => molotov,
+        (None, Some(napalm)) => &napalm.molotov,
+        (None, None) => unreachable!("AnyOf matches at least one component"),
+    }
+}
+
 #[derive(Bundle)]
 pub struct MolotovBundle<S: Side> {
     molotov: Molotov<S>,
     marker: MolotovMarker,
 }
 
-impl<S: Side> Default for MolotovBundle<S> {
-    fn default() -> Self {
+impl<S: Side> MolotovBundle<S> {
+    pub fn new(molotov: Molotov<S>) -> Self {
         Self {
-            molotov: Default::default(),
+            molotov,
             marker: MolotovMarker,
         }
     }
@@ -218,24 +377,36 @@ fn molotov_attack<S: Side>(
     weapon_assets: Res<WeaponsAssets>,
     molotov_buffs: Res<MolotovBuffs<S>>,
     global_weapons_buffs: Res<GlobalWeaponBuffs>,
+    mut side_rng: ResMut<SideRng<S>>,
     mut commands: Commands,
-    mut molotovs: Query<(&Transform, &CastleWall<S>, &mut Molotov<S>)>,
+    mut molotovs: Query<(&Transform, &CastleWall<S>, AnyMolotovMut<S>)>,
 ) {
-    for (transform, wall, mut molotov) in molotovs.iter_mut() {
+    for (transform, wall, any_molotov) in molotovs.iter_mut() {
+        let (molotov, lifespan_multiplier, spread) = match any_molotov {
+            (Some(molotov), _) => (molotov.into_inner(), 1.0, None),
+            (None, Some(napalm)) => {
+                let napalm = napalm.into_inner();
+                (
+                    &mut napalm.molotov,
+                    napalm.lifespan_multiplier,
+                    Some(napalm.spread),
+                )
+            }
+            (None, None) => continue,
+        };
+
         if !molotov.attack_timer.tick(time.delta()).finished() {
             continue;
         }
 
-        molotov.attack_timer = Timer::from_seconds(
-            1.0 / (DEFAULT_MOLOTOV_ATTACK_SPEED * (1.0 + molotov_buffs.attack_speed)),
-            TimerMode::Repeating,
-        );
+        let buffed_molotov = molotov.with_buffs(&molotov_buffs, &global_weapons_buffs);
+        molotov.attack_timer = buffed_molotov.attack_timer;
 
-        let mut rng = rand::thread_rng();
+        let rng = &mut side_rng.molotovs;
         // each side is 60 degrees in size.
         // S::direction gives a line directly at the center of the side
         let angle = rng.gen_range(-30.0..30.0);
-        let distance = rng.gen_range(DEFAULT_MOLOTOV_MIN_RANGE..molotov.range);
+        let distance = rng.gen_range(DEFAULT_MOLOTOV_MIN_RANGE..buffed_molotov.range.value());
 
         // convert angle to radians
         let direction = Vec2::from_angle(angle / 360.0 * std::f32::consts::PI).rotate(S::DIRECTION);
@@ -246,34 +417,33 @@ fn molotov_attack<S: Side>(
         let mut area_position = transform.translation;
         area_position += (direction * (distance + wall.half_thickness)).extend(0.0);
 
-        let damage =
-            ((molotov.damage + molotov_buffs.damage_flat + global_weapons_buffs.damage_flat) as f32
-                * (1.0 + molotov_buffs.damage + global_weapons_buffs.damage)) as i32;
-        let area_size = molotov.area_size * (1.0 + molotov_buffs.area_size);
-        let area_attack_speed = molotov.area_attack_speed * (1.0 + molotov_buffs.area_attack_speed);
-        let area_lifespan = DEFAULT_AREA_LIFESPAN * (1.0 + molotov_buffs.area_lifespan);
-        let crit_chance = molotov_buffs.crit_chance + global_weapons_buffs.crit_chance;
-        let crit_damage = (damage as f32
-            * (1.0 + molotov_buffs.crit_damage + global_weapons_buffs.crit_damage))
-            as i32;
-
-        commands.spawn(MolotovBottleBundle::<S>::new(
-            weapon_assets.molotov.clone(),
-            area_size,
-            damage,
-            crit_damage,
-            crit_chance,
-            area_attack_speed,
-            area_lifespan,
-            area_position,
-            initial_position,
-        ));
+        let damage = buffed_molotov.damage.value() as i32;
+        let area_size = buffed_molotov.area_size.value();
+        let area_attack_speed = buffed_molotov.area_attack_speed.value();
+        let area_lifespan = buffed_molotov.area_lifespan.value() * lifespan_multiplier;
+        let crit_chance = buffed_molotov.crit_chance.value();
+        let crit_damage = (damage as f32 * buffed_molotov.crit_damage.value()) as i32;
+
+        commands.spawn(
+            MolotovBottleBundle::<S>::new(
+                weapon_assets.molotov.clone(),
+                area_size,
+                damage,
+                crit_damage,
+                crit_chance,
+                area_attack_speed,
+                area_lifespan,
+                area_position,
+                initial_position,
+            )
+            .with_spread(spread),
+        );
     }
 }
 
 fn molotov_bottle_update<S: Side>(
     time: Res<Time>,
-    audio: Res<Audio>,
+    audio: Option<Res<Audio>>,
     game_assets: Res<GameAssets>,
     game_settings: Res<GameSettings>,
     weapon_assets: Res<WeaponsAssets>,
@@ -308,9 +478,12 @@ fn molotov_bottle_update<S: Side>(
                 bottle.area.clone(),
             ));
 
-            audio
-                .play(game_assets.explosion.clone())
-                .with_volume(game_settings.sound_volume * MOLOTOV_SFX_MULTIPLIER);
+            // no audio in headless mode
+            if let Some(audio) = &audio {
+                audio
+                    .play(game_assets.explosion.clone())
+                    .with_volume(game_settings.sound_volume * MOLOTOV_SFX_MULTIPLIER);
+            }
         }
     }
 }
@@ -0,0 +1,311 @@
+use std::{fmt::Write, time::Duration};
+
+use bevy::{
+    app::{AppExit, ScheduleRunnerSettings},
+    input::InputPlugin,
+    prelude::*,
+};
+use bevy_asset_loader::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    chooser_from_args,
+    game::{
+        self,
+        castle::{Castle, CastleAssets, CastleDefinitions},
+        data,
+        enemies::{
+            definitions::EnemyDefinitions, elites::EliteDefinitions, waves::WaveSchedule,
+            EnemyAssets,
+        },
+        profile::{Profile, ProfilePath},
+        replay::{ManualTimeStep, Replay, ReplayPlayback, ReplayRecordPath},
+        upgrades::{
+            chooser::UpgradeChooser,
+            history::UpgradeHistory,
+            pool::UpgradePool,
+            synergies::{ActiveSynergies, SynergyDefinitions},
+            UpgradeAssets,
+        },
+        weapons::{WeaponStats, WeaponsAssets},
+        GameMode, GameState, GameTime,
+    },
+    profile_path_from_args, record_path_from_args, replay_from_args, seed_from_args, utils,
+    GameAssets, GameSettings, GlobalState,
+};
+
+/// Game time passed on every update
+const HEADLESS_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// Settings of a headless run
+struct HeadlessOptions {
+    seed: Option<u64>,
+    game_mode: GameMode,
+    chooser: UpgradeChooser,
+    record: Option<ReplayRecordPath>,
+    /// Profile is only updated if set
+    profile: Option<ProfilePath>,
+    /// Seed, game mode and upgrades are taken from the replay
+    replay: Option<Replay>,
+}
+
+impl HeadlessOptions {
+    fn from_args() -> Self {
+        Self {
+            seed: seed_from_args(),
+            game_mode: if std::env::args().any(|arg| arg == "--endless") {
+                GameMode::Endless
+            } else {
+                GameMode::Normal
+            },
+            // there is nobody to click the upgrade cards
+            chooser: chooser_from_args()
+                .filter(|chooser| *chooser != UpgradeChooser::Manual)
+                .unwrap_or(UpgradeChooser::AlwaysFirst),
+            record: record_path_from_args(),
+            profile: profile_path_from_args(),
+            replay: replay_from_args(),
+        }
+    }
+}
+
+/// Summary printed at the end of the run
+#[derive(Debug, Resource)]
+struct HeadlessSummary(String);
+
+/// Runs a single game without window, rendering, UI or audio.
+/// Time advances by fixed steps as fast as CPU allows.
+/// Exits with an error if any game data can not be loaded.
+pub fn run() {
+    // there is no log output in headless mode
+    let mut app = match build_app(HeadlessOptions::from_args()) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    app.run();
+}
+
+fn build_app(options: HeadlessOptions) -> Result<App, String> {
+    let mut app = App::new();
+    app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::ZERO))
+        .insert_resource(ManualTimeStep::new(Duration::from_secs_f32(
+            HEADLESS_TIMESTEP,
+        )))
+        // skip asset loading, assets are never loaded and
+        // only default handles are stored
+        .insert_resource(State(GlobalState::Initialization))
+        .add_state::<GlobalState>()
+        .add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(AssetPlugin::default())
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+        // game plugins register their collections in it
+        .add_loading_state(LoadingState::new(GlobalState::AssetLoading))
+        .init_resource::<GameAssets>()
+        .init_resource::<CastleAssets>()
+        .init_resource::<EnemyAssets>()
+        .init_resource::<UpgradeAssets>()
+        .init_resource::<WeaponsAssets>()
+        .insert_resource(GameSettings {
+            seed: options
+                .replay
+                .as_ref()
+                .map(|replay| replay.seed)
+                .or(options.seed),
+            ..default()
+        })
+        .add_plugin(game::GamePlugin)
+        .add_startup_system(setup)
+        .add_system(summarize.in_schedule(OnEnter(GameState::GameOver)))
+        .add_system(summarize.in_schedule(OnEnter(GameState::Victory)))
+        .add_system(
+            record_profile
+                .run_if(resource_exists::<ProfilePath>())
+                .in_schedule(OnEnter(GameState::GameOver)),
+        )
+        .add_system(
+            record_profile
+                .run_if(resource_exists::<ProfilePath>())
+                .in_schedule(OnEnter(GameState::Victory)),
+        )
+        .add_system(
+            print_summary
+                .in_base_set(CoreSet::Last)
+                .run_if(resource_added::<HeadlessSummary>()),
+        )
+        .add_system(
+            playback_stopped
+                .in_base_set(CoreSet::Last)
+                .run_if(resource_removed::<ReplayPlayback>()),
+        );
+
+    if let Some(replay) = options.replay {
+        app.insert_resource(replay.castle_type)
+            .insert_resource(replay.game_mode)
+            .insert_resource(ReplayPlayback::new(replay));
+    } else {
+        app.insert_resource(options.game_mode)
+            .insert_resource(options.chooser);
+        if let Some(path) = options.record {
+            app.insert_resource(path);
+        }
+    }
+
+    if let Some(path) = options.profile {
+        app.insert_resource(Profile::load(&path)?)
+            .insert_resource(path);
+    }
+
+    load_game_data(&mut app.world)?;
+
+    utils::single_threaded_schedules(&mut app);
+    Ok(app)
+}
+
+/// Game data is still needed to play
+fn load_game_data(world: &mut World) -> Result<(), String> {
+    let handle = world
+        .resource_mut::<Assets<CastleDefinitions>>()
+        .add(data::load_data(CastleAssets::DEFINITIONS_PATH)?);
+    world.resource_mut::<CastleAssets>().definitions = handle;
+
+    let handle = world
+        .resource_mut::<Assets<WeaponStats>>()
+        .add(data::load_data(WeaponsAssets::STATS_PATH)?);
+    world.resource_mut::<WeaponsAssets>().stats = handle;
+
+    let handle = world
+        .resource_mut::<Assets<EnemyDefinitions>>()
+        .add(data::load_data(EnemyAssets::DEFINITIONS_PATH)?);
+    world.resource_mut::<EnemyAssets>().definitions = handle;
+
+    let handle = world
+        .resource_mut::<Assets<WaveSchedule>>()
+        .add(data::load_data(EnemyAssets::WAVES_PATH)?);
+    world.resource_mut::<EnemyAssets>().waves = handle;
+
+    let handle = world
+        .resource_mut::<Assets<EliteDefinitions>>()
+        .add(data::load_data(EnemyAssets::ELITES_PATH)?);
+    world.resource_mut::<EnemyAssets>().elites = handle;
+
+    let handle = world
+        .resource_mut::<Assets<UpgradePool>>()
+        .add(data::load_data(UpgradeAssets::POOL_PATH)?);
+    world.resource_mut::<UpgradeAssets>().pool = handle;
+
+    let handle = world
+        .resource_mut::<Assets<SynergyDefinitions>>()
+        .add(data::load_data(UpgradeAssets::SYNERGIES_PATH)?);
+    world.resource_mut::<UpgradeAssets>().synergies = handle;
+
+    Ok(())
+}
+
+fn setup(
+    mut physics: ResMut<RapierConfiguration>,
+    mut global_state: ResMut<NextState<GlobalState>>,
+) {
+    physics.gravity = Vec2::ZERO;
+    global_state.set(GlobalState::InGame);
+}
+
+fn summarize(
+    castle: Query<&Castle>,
+    game_time: Res<GameTime>,
+    history: Res<UpgradeHistory>,
+    synergies: Res<ActiveSynergies>,
+    mut commands: Commands,
+) {
+    let castle = castle.single();
+    let mut summary = String::new();
+    _ = writeln!(summary, "Upgrades:");
+    for record in history.records.iter() {
+        _ = writeln!(summary, "  {record}");
+    }
+    if !synergies.synergies.is_empty() {
+        _ = writeln!(summary, "Synergies:");
+        for synergy in synergies.synergies.iter() {
+            _ = writeln!(summary, "  {synergy}");
+        }
+    }
+    _ = writeln!(summary, "Time survived: {:.1}s", game_time.elapsed);
+    _ = writeln!(summary, "Level reached: {}", castle.level);
+    _ = writeln!(summary, "Kills: {}", castle.kills);
+    commands.insert_resource(HeadlessSummary(summary));
+}
+
+/// Headless runs exit without leaving `GlobalState::InGame`,
+/// so the profile is saved when the run ends
+fn record_profile(
+    path: Res<ProfilePath>,
+    castle: Query<&Castle>,
+    game_time: Res<GameTime>,
+    mut profile: ResMut<Profile>,
+) {
+    profile.add_run(castle.single(), game_time.elapsed);
+    if let Err(e) = profile.save(&path) {
+        eprintln!("{e}");
+    }
+}
+
+/// Nobody can pick upgrades after the broken replay
+fn playback_stopped() {
+    eprintln!("Replay does not match the game, playback stopped");
+    std::process::exit(1);
+}
+
+fn print_summary(summary: Res<HeadlessSummary>, mut exit: EventWriter<AppExit>) {
+    print!("{}", summary.0);
+    exit.send(AppExit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Updates the app until the run is over
+    fn play(options: HeadlessOptions) -> String {
+        let mut app = build_app(options).expect("game data should load");
+        // 10 minutes of game time
+        for _ in 0..36_000 {
+            app.update();
+            if let Some(summary) = app.world.get_resource::<HeadlessSummary>() {
+                return summary.0.clone();
+            }
+        }
+        panic!("run did not end");
+    }
+
+    #[test]
+    fn replay_repeats_recorded_run() {
+        let path = std::env::temp_dir().join("mind_your_sides_headless_test.ron");
+        let path = path.to_string_lossy().to_string();
+
+        let recorded = play(HeadlessOptions {
+            seed: Some(7),
+            game_mode: GameMode::Normal,
+            chooser: UpgradeChooser::GreedySideHp,
+            record: Some(ReplayRecordPath(path.clone())),
+            profile: None,
+            replay: None,
+        });
+        let replay = Replay::load(&path).expect("replay should be saved");
+        _ = std::fs::remove_file(&path);
+
+        let played = play(HeadlessOptions {
+            seed: None,
+            game_mode: GameMode::Normal,
+            chooser: UpgradeChooser::Manual,
+            record: None,
+            profile: None,
+            replay: Some(replay),
+        });
+        assert_eq!(recorded, played);
+    }
+}
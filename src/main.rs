@@ -1,5 +1,7 @@
 #![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
+use std::time::Duration;
+
 use bevy::{
     prelude::*,
     window::{PresentMode, WindowMode, WindowResolution},
@@ -9,20 +11,37 @@ use bevy_kira_audio::{AudioPlugin, AudioSource};
 use bevy_rapier2d::prelude::*;
 
 mod game;
+mod headless;
 mod ui;
 mod utils;
 
+use game::{
+    profile::{Profile, ProfilePath},
+    replay::{ManualTimeStep, Replay, ReplayPlayback, ReplayRecordPath},
+    upgrades::chooser::UpgradeChooser,
+};
 use utils::IntoState;
 
 const GAME_NAME: &str = "Mind your sides";
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--headless") {
+        headless::run();
+        return;
+    }
+
     let mut app = App::new();
     app.insert_resource(ClearColor(Color::rgb_u8(24, 20, 37)))
         .add_state::<GlobalState>()
         .add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
+                // picks up changes of the game data while running.
+                // enabled with the `hot_reload` feature
+                .set(AssetPlugin {
+                    watch_for_changes: cfg!(feature = "hot_reload"),
+                    ..default()
+                })
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         title: GAME_NAME.to_string(),
@@ -43,6 +62,21 @@ fn main() {
         .add_plugin(game::GamePlugin)
         .add_plugin(ui::UiPlugin)
         .add_system(setup.in_set(OnUpdate(GlobalState::Initialization)));
+    // broken profile is kept as is and not overwritten
+    match profile_path_from_args().or_else(ProfilePath::user_data) {
+        Some(path) => match Profile::load(&path) {
+            Ok(profile) => {
+                app.insert_resource(profile).insert_resource(path);
+            }
+            Err(e) => error!("{e}, progress will not be saved"),
+        },
+        None => warn!("No user data directory, progress will not be saved"),
+    }
+    // only replays need repeatable runs,
+    // normal play keeps the multithreaded executor
+    if arg_value("--replay").is_some() || arg_value("--record").is_some() {
+        utils::single_threaded_schedules(&mut app);
+    }
     app.run();
 }
 
@@ -58,7 +92,7 @@ pub enum GlobalState {
 }
 impl_into_state!(GlobalState);
 
-#[derive(AssetCollection, Resource)]
+#[derive(AssetCollection, Resource, Default)]
 pub struct GameAssets {
     #[asset(path = "fonts/ae-systematic-tt-brk.ae-systematic-tt-brk.ttf")]
     font: Handle<Font>,
@@ -76,6 +110,12 @@ pub struct GameAssets {
 pub struct GameSettings {
     window_mode: WindowMode,
     sound_volume: f64,
+    /// Time in seconds the castle needs to be held
+    /// to win in normal mode
+    normal_mode_duration: f32,
+    /// Seed passed with `--seed <number>`.
+    /// Each run gets a random seed if not set.
+    seed: Option<u64>,
 }
 
 impl Default for GameSettings {
@@ -83,6 +123,8 @@ impl Default for GameSettings {
         Self {
             window_mode: WindowMode::Windowed,
             sound_volume: 0.6,
+            normal_mode_duration: 60.0 * 30.0,
+            seed: None,
         }
     }
 }
@@ -103,7 +145,15 @@ fn setup(
     camera_bundle.projection.scale = 1.8;
     commands.spawn(camera_bundle);
 
-    let game_settings = GameSettings::default();
+    let replay = replay_from_args();
+
+    let game_settings = GameSettings {
+        seed: replay
+            .as_ref()
+            .map(|replay| replay.seed)
+            .or_else(seed_from_args),
+        ..default()
+    };
 
     for mut window in windows.iter_mut() {
         window.present_mode = PresentMode::AutoVsync;
@@ -112,5 +162,64 @@ fn setup(
 
     commands.insert_resource(game_settings);
 
+    if let Some(replay) = replay {
+        // replays skip the menus
+        commands.insert_resource(replay.castle_type);
+        commands.insert_resource(replay.game_mode);
+        commands.insert_resource(ReplayPlayback::new(replay));
+        commands.insert_resource(ManualTimeStep::new(Duration::ZERO));
+        global_state.set(GlobalState::InGame);
+        return;
+    }
+
+    if let Some(chooser) = chooser_from_args() {
+        commands.insert_resource(chooser);
+    }
+    if let Some(path) = record_path_from_args() {
+        commands.insert_resource(path);
+    }
+
     global_state.set(GlobalState::MainMenu);
 }
+
+/// Value following the `name` command line argument
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// Reads seed from the `--seed <number>` argument
+fn seed_from_args() -> Option<u64> {
+    let seed = arg_value("--seed")?;
+    match seed.parse() {
+        Ok(seed) => Some(seed),
+        Err(e) => {
+            warn!("Invalid seed {seed}: {e}");
+            None
+        }
+    }
+}
+
+/// Reads upgrade strategy from the `--chooser <manual|random|greedy|first>` argument
+fn chooser_from_args() -> Option<UpgradeChooser> {
+    let name = arg_value("--chooser")?;
+    let chooser = UpgradeChooser::from_name(&name);
+    if chooser.is_none() {
+        warn!("Invalid upgrade chooser {name}");
+    }
+    chooser
+}
+
+/// Reads file to record replays to from the `--record <path>` argument
+fn record_path_from_args() -> Option<ReplayRecordPath> {
+    arg_value("--record").map(ReplayRecordPath)
+}
+
+/// Reads profile file from the `--profile <path>` argument
+fn profile_path_from_args() -> Option<ProfilePath> {
+    arg_value("--profile").map(|path| ProfilePath(path.into()))
+}
+
+/// Reads replay file from the `--replay <path>` argument
+fn replay_from_args() -> Option<Replay> {
+    Replay::load(&arg_value("--replay")?)
+}
@@ -1,12 +1,16 @@
 use bevy::prelude::*;
 
 use crate::{
+    game::{rng::GameRng, upgrades::history::UpgradeHistory},
     ui::{spawn_button, UiConfig},
     utils::remove_all_with,
     GlobalState,
 };
 
-use super::{hud::HUDMarker, UiInGameState};
+use super::{hud::HUDMarker, pause::history::spawn_upgrade_record, UiInGameState};
+
+/// Number of the latest upgrades shown in the summary
+const SUMMARY_UPGRADES: usize = 4;
 
 pub struct GameOverPlugin;
 
@@ -29,11 +33,22 @@ enum GameOverButton {
     MainMenu,
 }
 
-fn setup(config: Res<UiConfig>, hud: Query<Entity, With<HUDMarker>>, mut commands: Commands) {
+fn setup(
+    config: Res<UiConfig>,
+    game_rng: Res<GameRng>,
+    history: Res<UpgradeHistory>,
+    hud: Query<Entity, With<HUDMarker>>,
+    mut commands: Commands,
+) {
     let game_over = commands
         .spawn((
             NodeBundle {
-                style: config.menu_style.clone(),
+                // grows with the upgrade summary
+                style: Style {
+                    size: Size::new(Val::Px(700.0), Val::Auto),
+                    min_size: Size::new(Val::Undefined, Val::Px(500.0)),
+                    ..config.menu_style.clone()
+                },
                 background_color: config.panels_background.into(),
                 ..default()
             },
@@ -47,6 +62,34 @@ fn setup(config: Res<UiConfig>, hud: Query<Entity, With<HUDMarker>>, mut command
                 })
                 .with_style(config.title_style.clone()),
             );
+            builder.spawn(TextBundle {
+                text: Text::from_section(
+                    format!("Seed: {}", game_rng.seed),
+                    config.text_style.clone(),
+                ),
+                ..default()
+            });
+            // latest upgrades show which side effects ended the run
+            if !history.records.is_empty() {
+                builder.spawn(TextBundle {
+                    text: Text::from_section("Last upgrades:", config.small_text_style.clone()),
+                    ..default()
+                });
+                builder
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::FlexStart,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|builder| {
+                        for record in history.records.iter().rev().take(SUMMARY_UPGRADES) {
+                            spawn_upgrade_record(builder, &config, record);
+                        }
+                    });
+            }
         })
         .with_children(|builder| {
             spawn_button(builder, &config, GameOverButton::Restart);
@@ -3,8 +3,17 @@ use std::marker::PhantomData;
 use bevy::prelude::*;
 
 use crate::{
+    game::{
+        abilities::{
+            CastleAbility, CastleAbilityCooldowns, CastleAbilityTarget, UseCastleAbilityEvent,
+        },
+        castle::{self, CastleWall, SpendLevelUpsEvent},
+        enemies::waves::WaveProgress,
+        replay::ReplayPlayback,
+        upgrades::{curses::ActiveCurse, synergies::SynergyEvent},
+        East, GameState, GameTime, North, South, West,
+    },
     game::{castle::Castle, Side},
-    game::{castle::CastleWall, enemies::SpawnState, East, GameState, North, South, West},
     ui::{spawn_button, UiConfig},
     utils::remove_all_with,
     GlobalState,
@@ -12,6 +21,9 @@ use crate::{
 
 use super::UiInGameState;
 
+/// Seconds the triggered synergy is shown
+const SYNERGY_ANNOUNCEMENT_TIME: f32 = 5.0;
+
 pub struct HUDPlugin;
 
 impl Plugin for HUDPlugin {
@@ -20,27 +32,47 @@ impl Plugin for HUDPlugin {
             .add_systems(
                 (
                     update_time,
-                    update_spawn_state,
+                    update_wave,
                     update_castle_level,
                     update_castle_exp,
                     update_castle_wall_hp::<North>,
                     update_castle_wall_hp::<South>,
                     update_castle_wall_hp::<West>,
                     update_castle_wall_hp::<East>,
+                    update_curses,
                 )
                     .in_set(OnUpdate(UiInGameState::InGame)),
             )
-            .add_system(button_system.in_set(OnUpdate(UiInGameState::InGame)))
+            .add_systems(
+                (
+                    update_ability_target,
+                    update_ability_cooldowns,
+                    button_system,
+                    target_button_system,
+                    // abilities come from the replay during playback
+                    ability_button_system.run_if(not(resource_exists::<ReplayPlayback>())),
+                    spend_button_system
+                        .before(castle::spend_level_ups)
+                        .run_if(not(resource_exists::<ReplayPlayback>())),
+                )
+                    .in_set(OnUpdate(UiInGameState::InGame)),
+            )
+            // pending level ups are counted down on the level up menu too
+            .add_system(
+                update_castle_level_ups.run_if(
+                    in_state(UiInGameState::InGame).or_else(in_state(UiInGameState::LevelUp)),
+                ),
+            )
+            // synergies are triggered on the level up menu
+            .add_system(
+                announce_synergies.run_if(
+                    in_state(UiInGameState::InGame).or_else(in_state(UiInGameState::LevelUp)),
+                ),
+            )
             .add_system(remove_all_with::<HUDMarker>.in_schedule(OnExit(GlobalState::InGame)));
     }
 }
 
-#[derive(Resource)]
-pub struct HUDTimer {
-    start_time: f32,
-    time_passed: f32,
-}
-
 #[derive(Debug, Clone, Copy, Component)]
 pub struct HUDMarker;
 
@@ -48,7 +80,7 @@ pub struct HUDMarker;
 struct TimeText;
 
 #[derive(Debug, Clone, Copy, Component)]
-struct SpawnStateText;
+struct WaveText;
 
 #[derive(Debug, Clone, Copy, Component)]
 struct CastleLevelText;
@@ -56,11 +88,43 @@ struct CastleLevelText;
 #[derive(Debug, Clone, Copy, Component)]
 struct CastleExpText;
 
+#[derive(Debug, Clone, Copy, Component)]
+struct CastleLevelUpsText;
+
+/// Shows the last triggered synergy until the timer finishes
+#[derive(Debug, Clone, Component)]
+struct SynergyText {
+    timer: Timer,
+}
+
+/// Active curses with time or kills left
+#[derive(Debug, Clone, Copy, Component)]
+struct CursesText;
+
+/// Spends banked level ups
+#[derive(Debug, Clone, Copy, Component)]
+struct SpendLevelUpsButton;
+
 #[derive(Debug, Default, Clone, Copy, Component)]
 struct CastleWallHpText<S: Side> {
     _phantom: PhantomData<S>,
 }
 
+#[derive(Debug, Clone, Copy, Component)]
+struct AbilityTargetText;
+
+#[derive(Debug, Clone, Copy, Component)]
+struct AbilityCooldownText(CastleAbility);
+
+/// Selects side for castle abilities
+#[derive(Debug, Clone, Copy, Component)]
+enum TargetButton {
+    North,
+    South,
+    West,
+    East,
+}
+
 #[derive(Debug, Clone, Copy, Component)]
 enum HUDButton {
     StatsNorth,
@@ -70,11 +134,7 @@ enum HUDButton {
     Pause,
 }
 
-fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
-    commands.insert_resource(HUDTimer {
-        start_time: time.elapsed().as_secs_f32(),
-        time_passed: time.elapsed().as_secs_f32(),
-    });
+fn setup(config: Res<UiConfig>, mut commands: Commands) {
     // root node
     commands
         .spawn((
@@ -112,8 +172,18 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                         TimeText,
                     ));
                     parent.spawn((
-                        TextBundle::from_section("Stage: ", config.text_style.clone()),
-                        SpawnStateText,
+                        TextBundle::from_section("Wave: ", config.text_style.clone()),
+                        WaveText,
+                    ));
+                    parent.spawn((
+                        TextBundle::from_section("", config.buff_text_style.clone()),
+                        SynergyText {
+                            timer: Timer::from_seconds(SYNERGY_ANNOUNCEMENT_TIME, TimerMode::Once),
+                        },
+                    ));
+                    parent.spawn((
+                        TextBundle::from_section("", config.debuff_text_style.clone()),
+                        CursesText,
                     ));
                     // Castle info
                     parent
@@ -136,6 +206,28 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                                 TextBundle::from_section("Exp: ", config.text_style.clone()),
                                 CastleExpText,
                             ));
+                            parent.spawn((
+                                TextBundle::from_section("", config.small_text_style.clone()),
+                                CastleLevelUpsText,
+                            ));
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            display: Display::None,
+                                            ..config.option_button_style.clone()
+                                        },
+                                        background_color: config.button_color_normal.into(),
+                                        ..default()
+                                    },
+                                    SpendLevelUpsButton,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Spend levels",
+                                        config.small_text_style.clone(),
+                                    ));
+                                });
                         });
 
                     // North info
@@ -151,10 +243,7 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                             ..default()
                         })
                         .with_children(|parent| {
-                            parent.spawn((TextBundle::from_section(
-                                "North",
-                                config.text_style.clone(),
-                            ),));
+                            spawn_button(parent, &config, TargetButton::North);
                             parent.spawn((
                                 TextBundle::from_section("Hp: ", config.text_style.clone()),
                                 CastleWallHpText::<North>::default(),
@@ -175,10 +264,7 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                             ..default()
                         })
                         .with_children(|parent| {
-                            parent.spawn((TextBundle::from_section(
-                                "South",
-                                config.text_style.clone(),
-                            ),));
+                            spawn_button(parent, &config, TargetButton::South);
                             parent.spawn((
                                 TextBundle::from_section("Hp: ", config.text_style.clone()),
                                 CastleWallHpText::<South>::default(),
@@ -205,6 +291,43 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                 .with_children(|parent| {
                     spawn_button(parent, &config, HUDButton::Pause);
 
+                    // Castle abilities
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: config.panels_background.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section("Target: ", config.text_style.clone()),
+                                AbilityTargetText,
+                            ));
+                            for abilities in CastleAbility::ALL.chunks(2) {
+                                parent
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            flex_direction: FlexDirection::Row,
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        background_color: config.panels_background.into(),
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        for ability in abilities {
+                                            spawn_ability_button(parent, &config, *ability);
+                                        }
+                                    });
+                            }
+                        });
+
                     // West info
                     parent
                         .spawn(NodeBundle {
@@ -218,10 +341,7 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                             ..default()
                         })
                         .with_children(|parent| {
-                            parent.spawn((TextBundle::from_section(
-                                "West",
-                                config.text_style.clone(),
-                            ),));
+                            spawn_button(parent, &config, TargetButton::West);
                             parent.spawn((
                                 TextBundle::from_section("Hp: ", config.text_style.clone()),
                                 CastleWallHpText::<West>::default(),
@@ -242,10 +362,7 @@ fn setup(time: Res<Time>, config: Res<UiConfig>, mut commands: Commands) {
                             ..default()
                         })
                         .with_children(|parent| {
-                            parent.spawn((TextBundle::from_section(
-                                "East",
-                                config.text_style.clone(),
-                            ),));
+                            spawn_button(parent, &config, TargetButton::East);
                             parent.spawn((
                                 TextBundle::from_section("Hp: ", config.text_style.clone()),
                                 CastleWallHpText::<East>::default(),
@@ -286,22 +403,130 @@ fn button_system(
     }
 }
 
-fn update_time(
-    time: Res<Time>,
-    mut hud_timer: ResMut<HUDTimer>,
-    mut time_text: Query<&mut Text, With<TimeText>>,
+fn spawn_ability_button(builder: &mut ChildBuilder, config: &UiConfig, ability: CastleAbility) {
+    builder
+        .spawn((
+            ButtonBundle {
+                style: config.ability_button_style.clone(),
+                background_color: config.button_color_normal.into(),
+                ..default()
+            },
+            ability,
+        ))
+        .with_children(|builder| {
+            builder.spawn((
+                TextBundle::from_section(format!("{ability}"), config.buff_text_style.clone()),
+                AbilityCooldownText(ability),
+            ));
+        });
+}
+
+fn target_button_system(
+    style: Res<UiConfig>,
+    mut target: ResMut<CastleAbilityTarget>,
+    mut interaction_query: Query<
+        (&TargetButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
 ) {
-    hud_timer.time_passed += time.delta_seconds();
-    let mut text = time_text.single_mut();
-    text.sections[0].value = format!("Time: {:.1}", hud_timer.time_passed - hud_timer.start_time,);
+    for (button, interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = style.button_color_pressed.into();
+                *target = match button {
+                    TargetButton::North => CastleAbilityTarget::North,
+                    TargetButton::South => CastleAbilityTarget::South,
+                    TargetButton::West => CastleAbilityTarget::West,
+                    TargetButton::East => CastleAbilityTarget::East,
+                };
+            }
+            Interaction::Hovered => {
+                *color = style.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = style.button_color_normal.into();
+            }
+        }
+    }
 }
 
-fn update_spawn_state(
-    spawn_state: Res<State<SpawnState>>,
-    mut spawn_state_text: Query<&mut Text, With<SpawnStateText>>,
+fn ability_button_system(
+    style: Res<UiConfig>,
+    mut ability_events: EventWriter<UseCastleAbilityEvent>,
+    mut interaction_query: Query<
+        (&CastleAbility, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
 ) {
-    let mut text = spawn_state_text.single_mut();
-    text.sections[0].value = format!("Stage: {}", spawn_state.0);
+    for (ability, interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = style.button_color_pressed.into();
+                ability_events.send(UseCastleAbilityEvent { ability: *ability });
+            }
+            Interaction::Hovered => {
+                *color = style.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = style.button_color_normal.into();
+            }
+        }
+    }
+}
+
+fn spend_button_system(
+    style: Res<UiConfig>,
+    mut spend_events: EventWriter<SpendLevelUpsEvent>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<SpendLevelUpsButton>),
+    >,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = style.button_color_pressed.into();
+                spend_events.send(SpendLevelUpsEvent);
+            }
+            Interaction::Hovered => {
+                *color = style.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = style.button_color_normal.into();
+            }
+        }
+    }
+}
+
+fn update_ability_target(
+    target: Res<CastleAbilityTarget>,
+    mut target_text: Query<&mut Text, With<AbilityTargetText>>,
+) {
+    let mut text = target_text.single_mut();
+    text.sections[0].value = format!("Target: {:?}", *target);
+}
+
+fn update_ability_cooldowns(
+    cooldowns: Res<CastleAbilityCooldowns>,
+    mut cooldown_texts: Query<(&mut Text, &AbilityCooldownText)>,
+) {
+    for (mut text, AbilityCooldownText(ability)) in cooldown_texts.iter_mut() {
+        text.sections[0].value = if cooldowns.is_ready(*ability) {
+            format!("{ability}")
+        } else {
+            format!("{ability}\n{:.1}", cooldowns.remaining(*ability))
+        };
+    }
+}
+
+fn update_time(game_time: Res<GameTime>, mut time_text: Query<&mut Text, With<TimeText>>) {
+    let mut text = time_text.single_mut();
+    text.sections[0].value = format!("Time: {:.1}", game_time.elapsed);
+}
+
+fn update_wave(wave_progress: Res<WaveProgress>, mut wave_text: Query<&mut Text, With<WaveText>>) {
+    let mut text = wave_text.single_mut();
+    text.sections[0].value = format!("Wave: {}", wave_progress.wave_number());
 }
 
 fn update_castle_level(
@@ -319,6 +544,31 @@ fn update_castle_exp(castle: Query<&Castle>, mut exp_text: Query<&mut Text, With
     exp_text.sections[0].value = format!("Exp: {}/{}", castle.exp, castle.next_level_exp);
 }
 
+/// Shows pending level ups and the button to spend banked ones
+fn update_castle_level_ups(
+    castle: Query<&Castle>,
+    mut level_ups_text: Query<&mut Text, With<CastleLevelUpsText>>,
+    mut spend_button: Query<&mut Style, With<SpendLevelUpsButton>>,
+) {
+    let castle = castle.single();
+    let mut level_ups_text = level_ups_text.single_mut();
+    level_ups_text.sections[0].value = match castle.pending_level_ups {
+        0 => String::new(),
+        1 => "x1 level up pending".to_string(),
+        pending => format!("x{pending} level ups pending"),
+    };
+    let display = if castle.bank_level_ups && castle.pending_level_ups != 0 {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    let mut spend_button = spend_button.single_mut();
+    // avoids layout updates every frame
+    if spend_button.display != display {
+        spend_button.display = display;
+    }
+}
+
 fn update_castle_wall_hp<S: Side>(
     wall: Query<&CastleWall<S>>,
     mut hp_text: Query<&mut Text, With<CastleWallHpText<S>>>,
@@ -327,3 +577,51 @@ fn update_castle_wall_hp<S: Side>(
     let mut hp_text = hp_text.single_mut();
     hp_text.sections[0].value = format!("Hp: {}/{}", wall.health, wall.max_health);
 }
+
+fn announce_synergies(
+    time: Res<Time>,
+    mut synergy_events: EventReader<SynergyEvent>,
+    mut synergy_text: Query<(&mut Text, &mut SynergyText)>,
+) {
+    let (mut text, mut synergy_text) = synergy_text.single_mut();
+    let synergies = synergy_events
+        .iter()
+        .map(|event| format!("Synergy: {}\non {}", event.synergy.name, event.synergy.side))
+        .collect::<Vec<_>>();
+    if !synergies.is_empty() {
+        text.sections[0].value = synergies.join("\n");
+        synergy_text.timer.reset();
+    } else if synergy_text.timer.tick(time.delta()).just_finished() {
+        text.sections[0].value.clear();
+    }
+}
+
+fn update_curses(
+    castle: Query<&Castle>,
+    north_curses: Query<&ActiveCurse<North>>,
+    south_curses: Query<&ActiveCurse<South>>,
+    west_curses: Query<&ActiveCurse<West>>,
+    east_curses: Query<&ActiveCurse<East>>,
+    mut curses_text: Query<&mut Text, With<CursesText>>,
+) {
+    let kills = castle.single().kills;
+    let mut lines = vec![];
+    push_curse_lines(&north_curses, kills, &mut lines);
+    push_curse_lines(&south_curses, kills, &mut lines);
+    push_curse_lines(&west_curses, kills, &mut lines);
+    push_curse_lines(&east_curses, kills, &mut lines);
+
+    let mut text = curses_text.single_mut();
+    text.sections[0].value = lines.join("\n");
+}
+
+fn push_curse_lines<S: Side>(curses: &Query<&ActiveCurse<S>>, kills: u32, lines: &mut Vec<String>) {
+    for curse in curses.iter() {
+        lines.push(format!(
+            "{:?} {}\n{}",
+            S::default(),
+            curse.effect(),
+            curse.remaining(kills)
+        ));
+    }
+}
@@ -1,4 +1,8 @@
+use crate::game::castle::Castle;
 use crate::game::upgrades::apply::ApplyUpgradeEvent;
+use crate::game::upgrades::options::{LevelUpOptionEvent, LevelUpOptions};
+use crate::game::upgrades::pool::{BanishedUpgrades, UpgradeRarity};
+use crate::game::upgrades::preview::UpgradePreview;
 use crate::game::upgrades::{Upgrade, Upgrades};
 use crate::ui::in_game::UiInGameState;
 use crate::ui::UiConfig;
@@ -12,8 +16,9 @@ pub struct LevelUpPlugin;
 
 impl Plugin for LevelUpPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(setup.in_schedule(OnEnter(UiInGameState::LevelUp)))
+        app.add_system(update_level_up.in_set(OnUpdate(UiInGameState::LevelUp)))
             .add_system(button_system.in_set(OnUpdate(UiInGameState::LevelUp)))
+            .add_system(option_button_system.in_set(OnUpdate(UiInGameState::LevelUp)))
             .add_system(
                 remove_all_with::<LevelUpMarker>.in_schedule(OnExit(UiInGameState::LevelUp)),
             );
@@ -27,17 +32,47 @@ enum UpgradeButton {
     Second,
     Third,
     Fourth,
+    Fifth,
+}
+
+impl UpgradeButton {
+    const ALL: [UpgradeButton; 5] = [
+        UpgradeButton::First,
+        UpgradeButton::Second,
+        UpgradeButton::Third,
+        UpgradeButton::Fourth,
+        UpgradeButton::Fifth,
+    ];
 }
 
+#[derive(Debug, Clone, Copy, Component)]
+struct LevelUpOptionButton(LevelUpOptionEvent);
+
 #[derive(Debug, Clone, Copy, Component)]
 struct LevelUpMarker;
 
-fn setup(
+/// Builds the level up menu.
+/// The menu is built again when upgrades or options change.
+fn update_level_up(
     ui_config: Res<UiConfig>,
     upgrades: Res<Upgrades>,
+    options: Res<LevelUpOptions>,
+    castle: Query<&Castle>,
+    preview: UpgradePreview,
     hud: Query<Entity, With<HUDMarker>>,
+    level_up: Query<Entity, With<LevelUpMarker>>,
     mut commands: Commands,
 ) {
+    if !level_up.is_empty() && !upgrades.is_changed() && !options.is_changed() {
+        return;
+    }
+    for entity in level_up.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let castle = castle.single();
+    let skip_exp = (castle.next_level_exp as f32 * options.skip_exp) as u32;
+
     let level_up = commands
         .spawn((
             NodeBundle {
@@ -52,12 +87,18 @@ fn setup(
             LevelUpMarker,
         ))
         .with_children(|builder| {
+            let title = if castle.pending_level_ups > 1 {
+                format!("Level up x{}", castle.pending_level_ups)
+            } else {
+                "Level up".to_string()
+            };
             builder.spawn(
-                TextBundle::from_section("Level up", ui_config.title_text_style.clone())
-                    .with_style(Style {
+                TextBundle::from_section(title, ui_config.title_text_style.clone()).with_style(
+                    Style {
                         margin: UiRect::all(Val::Percent(4.0)),
                         ..default()
-                    }),
+                    },
+                ),
             );
             builder
                 .spawn(NodeBundle {
@@ -70,56 +111,75 @@ fn setup(
                     ..default()
                 })
                 .with_children(|builder| {
-                    builder
-                        .spawn(NodeBundle {
-                            style: Style {
-                                margin: UiRect::all(Val::Percent(1.0)),
-                                flex_direction: FlexDirection::Row,
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            ..default()
-                        })
-                        .with_children(|builder| {
-                            spawn_upgrade_button(
-                                builder,
-                                &ui_config,
-                                UpgradeButton::First,
-                                &upgrades.upgrades[0],
-                            );
-                            spawn_upgrade_button(
-                                builder,
-                                &ui_config,
-                                UpgradeButton::Second,
-                                &upgrades.upgrades[1],
-                            );
-                        });
-                    builder
-                        .spawn(NodeBundle {
-                            style: Style {
-                                margin: UiRect::all(Val::Percent(1.0)),
-                                flex_direction: FlexDirection::Row,
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
+                    let buttons: Vec<UpgradeButton> = upgrades
+                        .offered(options.offered_cards())
+                        .map(|index| UpgradeButton::ALL[index])
+                        .collect();
+                    for row in buttons.chunks(2) {
+                        builder
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    margin: UiRect::all(Val::Percent(1.0)),
+                                    flex_direction: FlexDirection::Row,
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
                                 ..default()
-                            },
-                            ..default()
-                        })
-                        .with_children(|builder| {
-                            spawn_upgrade_button(
-                                builder,
-                                &ui_config,
-                                UpgradeButton::Third,
-                                &upgrades.upgrades[2],
-                            );
-                            spawn_upgrade_button(
-                                builder,
-                                &ui_config,
-                                UpgradeButton::Fourth,
-                                &upgrades.upgrades[3],
-                            );
-                        });
+                            })
+                            .with_children(|builder| {
+                                for button in row {
+                                    let upgrade = &upgrades.upgrades[*button as usize];
+                                    spawn_upgrade_card(
+                                        builder,
+                                        &ui_config,
+                                        &options,
+                                        *button,
+                                        upgrade,
+                                        preview.preview(upgrade),
+                                    );
+                                }
+                            });
+                    }
+                });
+            builder
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Percent(1.0)),
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|builder| {
+                    if options.rerolls != 0 {
+                        spawn_option_button(
+                            builder,
+                            &ui_config,
+                            LevelUpOptionEvent::Reroll,
+                            format!("Reroll ({})", options.rerolls),
+                        );
+                    }
+                    spawn_option_button(
+                        builder,
+                        &ui_config,
+                        LevelUpOptionEvent::SkipForExp,
+                        format!("Skip: +{skip_exp} exp"),
+                    );
+                    spawn_option_button(
+                        builder,
+                        &ui_config,
+                        LevelUpOptionEvent::SkipForHeal,
+                        format!("Skip: heal {}", options.skip_heal),
+                    );
+                    spawn_option_button(
+                        builder,
+                        &ui_config,
+                        LevelUpOptionEvent::Bank,
+                        "Bank for later".to_string(),
+                    );
                 });
         })
         .id();
@@ -132,11 +192,16 @@ fn button_system(
     config: Res<UiConfig>,
     mut apply_upgrade_event: EventWriter<ApplyUpgradeEvent>,
     mut interaction_query: Query<
-        (&UpgradeButton, &Interaction, &mut BackgroundColor),
+        (
+            &UpgradeButton,
+            &UpgradeRarity,
+            &Interaction,
+            &mut BackgroundColor,
+        ),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (button, interaction, mut color) in interaction_query.iter_mut() {
+    for (button, rarity, interaction, mut color) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
                 *color = config.button_color_pressed.into();
@@ -145,12 +210,37 @@ fn button_system(
                     UpgradeButton::Second => ApplyUpgradeEvent::Second,
                     UpgradeButton::Third => ApplyUpgradeEvent::Third,
                     UpgradeButton::Fourth => ApplyUpgradeEvent::Fourth,
+                    UpgradeButton::Fifth => ApplyUpgradeEvent::Fifth,
                 };
                 apply_upgrade_event.send(event);
             }
             Interaction::Hovered => {
                 *color = config.button_color_hover.into();
             }
+            Interaction::None => {
+                *color = config.rarity_color(*rarity).into();
+            }
+        }
+    }
+}
+
+fn option_button_system(
+    config: Res<UiConfig>,
+    mut option_event: EventWriter<LevelUpOptionEvent>,
+    mut interaction_query: Query<
+        (&LevelUpOptionButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (button, interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = config.button_color_pressed.into();
+                option_event.send(button.0);
+            }
+            Interaction::Hovered => {
+                *color = config.button_color_hover.into();
+            }
             Interaction::None => {
                 *color = config.button_color_normal.into();
             }
@@ -158,6 +248,81 @@ fn button_system(
     }
 }
 
+/// Upgrade button with the stat preview and
+/// the banish button under it
+fn spawn_upgrade_card(
+    builder: &mut ChildBuilder,
+    style: &UiConfig,
+    options: &LevelUpOptions,
+    button: UpgradeButton,
+    upgrade: &Upgrade,
+    (buffs, debuffs): (Vec<String>, Vec<String>),
+) {
+    builder
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|builder| {
+            spawn_upgrade_button(builder, style, button, upgrade);
+            let sections = buffs
+                .into_iter()
+                .map(|line| (line, &style.buff_text_style))
+                .chain(
+                    debuffs
+                        .into_iter()
+                        .map(|line| (line, &style.debuff_text_style)),
+                )
+                .map(|(line, text_style)| {
+                    TextSection::new(
+                        format!("{line}\n"),
+                        TextStyle {
+                            font_size: style.stat_text_style.font_size,
+                            ..text_style.clone()
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+            builder.spawn(TextBundle::from_sections(sections));
+            if options.banishes != 0 && BanishedUpgrades::can_banish(upgrade) {
+                spawn_option_button(
+                    builder,
+                    style,
+                    LevelUpOptionEvent::Banish(button as usize),
+                    format!("Banish ({})", options.banishes),
+                );
+            }
+        });
+}
+
+fn spawn_option_button(
+    builder: &mut ChildBuilder,
+    style: &UiConfig,
+    option: LevelUpOptionEvent,
+    label: String,
+) {
+    builder
+        .spawn((
+            ButtonBundle {
+                style: style.option_button_style.clone(),
+                background_color: style.button_color_normal.into(),
+                ..default()
+            },
+            LevelUpOptionButton(option),
+        ))
+        .with_children(|builder| {
+            builder.spawn(TextBundle {
+                text: Text::from_section(label, style.small_text_style.clone()),
+                ..default()
+            });
+        });
+}
+
 fn spawn_upgrade_button<B>(
     builder: &mut ChildBuilder,
     style: &UiConfig,
@@ -170,12 +335,22 @@ fn spawn_upgrade_button<B>(
         .spawn((
             ButtonBundle {
                 style: style.upgrade_button_style.clone(),
-                background_color: style.button_color_normal.into(),
+                background_color: style.rarity_color(upgrade.rarity).into(),
                 ..default()
             },
             button,
+            upgrade.rarity,
         ))
         .with_children(|builder| {
+            if upgrade.rarity != UpgradeRarity::Common {
+                builder.spawn(TextBundle {
+                    text: Text::from_section(
+                        format!("{}", upgrade.rarity),
+                        style.text_style.clone(),
+                    ),
+                    ..default()
+                });
+            }
             // Global
             if upgrade.has_global_upgrades() {
                 let (buffs, debuffs) = upgrade.global_upgrades();
@@ -11,6 +11,7 @@ mod hud;
 mod level_up;
 mod pause;
 mod side_stats;
+mod victory;
 
 pub struct UiInGamePlugin;
 
@@ -37,6 +38,10 @@ impl Plugin for UiInGamePlugin {
                 set_state::<UiInGameState, { UiInGameState::GameOver as u8 }>
                     .in_schedule(OnEnter(GameState::GameOver)),
             )
+            .add_system(
+                set_state::<UiInGameState, { UiInGameState::Victory as u8 }>
+                    .in_schedule(OnEnter(GameState::Victory)),
+            )
             .add_system(
                 set_state::<UiInGameState, { UiInGameState::StatsNorth as u8 }>
                     .in_schedule(OnEnter(GameState::StatsNorth)),
@@ -57,6 +62,7 @@ impl Plugin for UiInGamePlugin {
             .add_plugin(level_up::LevelUpPlugin)
             .add_plugin(pause::PausePlugin)
             .add_plugin(game_over::GameOverPlugin)
+            .add_plugin(victory::VictoryPlugin)
             .add_plugin(side_stats::StatsPlugin::<North>::default())
             .add_plugin(side_stats::StatsPlugin::<South>::default())
             .add_plugin(side_stats::StatsPlugin::<West>::default())
@@ -76,5 +82,6 @@ enum UiInGameState {
     StatsSouth,
     StatsWest,
     StatsEast,
+    Victory,
 }
 impl_into_state!(UiInGameState);
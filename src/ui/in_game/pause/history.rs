@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::upgrades::history::{UpgradeHistory, UpgradeRecord},
+    ui::{in_game::hud::HUDMarker, spawn_button, UiConfig},
+    utils::remove_all_with,
+};
+
+use super::UiPauseState;
+
+/// Number of upgrades shown on one page
+const RECORDS_PER_PAGE: usize = 6;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup.in_schedule(OnEnter(UiPauseState::History)))
+            .add_systems((button_system, update_history).in_set(OnUpdate(UiPauseState::History)))
+            .add_system(
+                remove_all_with::<HistoryMarker>.in_schedule(OnExit(UiPauseState::History)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct HistoryMarker;
+
+/// Node with records of the current page
+#[derive(Debug, Clone, Copy, Component)]
+struct HistoryRecords;
+
+/// Page 0 has the latest upgrades
+#[derive(Debug, Default, Resource)]
+struct HistoryPage(usize);
+
+#[derive(Debug, Clone, Copy, Component)]
+enum HistoryButton {
+    Newer,
+    Older,
+    Back,
+}
+
+fn setup(config: Res<UiConfig>, hud: Query<Entity, With<HUDMarker>>, mut commands: Commands) {
+    commands.insert_resource(HistoryPage::default());
+
+    let history = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(700.0), Val::Auto),
+                    min_size: Size::new(Val::Undefined, Val::Px(500.0)),
+                    ..config.menu_style.clone()
+                },
+                background_color: config.panels_background.into(),
+                ..default()
+            },
+            HistoryMarker,
+        ))
+        .with_children(|builder| {
+            builder.spawn(
+                (TextBundle {
+                    text: Text::from_section("Upgrades", config.title_text_style.clone()),
+                    ..default()
+                })
+                .with_style(config.title_style.clone()),
+            );
+            builder.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::FlexStart,
+                        ..default()
+                    },
+                    ..default()
+                },
+                HistoryRecords,
+            ));
+            builder
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|builder| {
+                    spawn_button(builder, &config, HistoryButton::Newer);
+                    spawn_button(builder, &config, HistoryButton::Older);
+                    spawn_button(builder, &config, HistoryButton::Back);
+                });
+        })
+        .id();
+
+    let hud = hud.single();
+    commands.entity(hud).insert_children(1, &[history]);
+}
+
+fn button_system(
+    config: Res<UiConfig>,
+    history: Res<UpgradeHistory>,
+    mut page: ResMut<HistoryPage>,
+    mut pause_state: ResMut<NextState<UiPauseState>>,
+    mut interaction_query: Query<
+        (&HistoryButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    let last_page = history.records.len().saturating_sub(1) / RECORDS_PER_PAGE;
+    for (button, interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = config.button_color_pressed.into();
+                match button {
+                    HistoryButton::Newer => page.0 = page.0.saturating_sub(1),
+                    HistoryButton::Older => page.0 = (page.0 + 1).min(last_page),
+                    HistoryButton::Back => pause_state.set(UiPauseState::Pause),
+                }
+            }
+            Interaction::Hovered => {
+                *color = config.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = config.button_color_normal.into();
+            }
+        }
+    }
+}
+
+/// Shows records of the current page, the latest first
+fn update_history(
+    config: Res<UiConfig>,
+    history: Res<UpgradeHistory>,
+    page: Res<HistoryPage>,
+    records_node: Query<Entity, With<HistoryRecords>>,
+    mut commands: Commands,
+) {
+    if !page.is_changed() {
+        return;
+    }
+    let Ok(records_node) = records_node.get_single() else {
+        return;
+    };
+
+    let mut records_node = commands.entity(records_node);
+    records_node.despawn_descendants();
+    records_node.with_children(|builder| {
+        if history.records.is_empty() {
+            builder.spawn(TextBundle::from_section(
+                "No upgrades yet",
+                config.stat_text_style.clone(),
+            ));
+        }
+        for record in history
+            .records
+            .iter()
+            .rev()
+            .skip(page.0 * RECORDS_PER_PAGE)
+            .take(RECORDS_PER_PAGE)
+        {
+            spawn_upgrade_record(builder, &config, record);
+        }
+    });
+}
+
+/// Level and time of the pick followed by
+/// buffs and side effects of the upgrade
+pub fn spawn_upgrade_record(builder: &mut ChildBuilder, config: &UiConfig, record: &UpgradeRecord) {
+    let font_size = config.stat_text_style.font_size;
+    let mut sections = vec![
+        TextSection::new(
+            format!("lvl {} at {:.1}s", record.level, record.time),
+            config.stat_text_style.clone(),
+        ),
+        TextSection::new(
+            format!("\n{}", record.buffs()),
+            TextStyle {
+                font_size,
+                ..config.buff_text_style.clone()
+            },
+        ),
+    ];
+    let side_effects = record.side_effects();
+    if !side_effects.is_empty() {
+        sections.push(TextSection::new(
+            format!("\n{side_effects}"),
+            TextStyle {
+                font_size,
+                ..config.debuff_text_style.clone()
+            },
+        ));
+    }
+    builder.spawn(TextBundle::from_sections(sections).with_style(Style {
+        margin: UiRect::bottom(Val::Px(4.0)),
+        ..default()
+    }));
+}
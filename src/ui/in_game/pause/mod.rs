@@ -10,6 +10,7 @@ use crate::{
 
 use super::{hud::HUDMarker, UiInGameState};
 
+pub mod history;
 pub mod settings;
 
 pub struct PausePlugin;
@@ -28,7 +29,8 @@ impl Plugin for PausePlugin {
             .add_system(setup.in_schedule(OnEnter(UiPauseState::Pause)))
             .add_system(button_system.in_set(OnUpdate(UiPauseState::Pause)))
             .add_system(remove_all_with::<PauseMarker>.in_schedule(OnExit(UiPauseState::Pause)))
-            .add_plugin(settings::SettingsPlugin);
+            .add_plugin(settings::SettingsPlugin)
+            .add_plugin(history::HistoryPlugin);
     }
 }
 
@@ -41,6 +43,7 @@ enum UiPauseState {
     Disabled,
     Pause,
     Settings,
+    History,
 }
 impl_into_state!(UiPauseState);
 
@@ -48,6 +51,7 @@ impl_into_state!(UiPauseState);
 enum PauseButton {
     MainMenu,
     Settings,
+    History,
     Back,
 }
 
@@ -64,6 +68,7 @@ fn setup(config: Res<UiConfig>, hud: Query<Entity, With<HUDMarker>>, mut command
         .with_children(|builder| {
             spawn_button(builder, &config, PauseButton::MainMenu);
             spawn_button(builder, &config, PauseButton::Settings);
+            spawn_button(builder, &config, PauseButton::History);
             spawn_button(builder, &config, PauseButton::Back);
         })
         .id();
@@ -93,6 +98,9 @@ fn button_system(
                     PauseButton::Settings => {
                         pause_state.set(UiPauseState::Settings);
                     }
+                    PauseButton::History => {
+                        pause_state.set(UiPauseState::History);
+                    }
                     PauseButton::Back => {
                         game_state.set(GameState::InGame);
                     }
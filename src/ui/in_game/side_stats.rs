@@ -5,9 +5,10 @@ use bevy::prelude::*;
 use crate::{
     game::{
         enemies::{spawn::EnemyBuffs, GlobalEnemyBuffs},
+        stats::{Stat, StatUnit},
         weapons::{
-            crossbow::{Crossbow, CrossbowBuffs},
-            molotov::{Molotov, MolotovBuffs},
+            crossbow::{base_crossbow, AnyCrossbow, CrossbowBuffs},
+            molotov::{base_molotov, AnyMolotov, MolotovBuffs},
             GlobalWeaponBuffs,
         },
         East, GameState, North, Side, South, West,
@@ -83,8 +84,59 @@ fn setup<S: Side>(
     molotov_buffs: Res<MolotovBuffs<S>>,
     global_enemy_buffs: Res<GlobalEnemyBuffs>,
     enemy_buffs: Res<EnemyBuffs<S>>,
+    crossbow: Query<AnyCrossbow<S>>,
+    molotov: Query<AnyMolotov<S>>,
     mut commands: Commands,
 ) {
+    let crossbow = crossbow.single();
+    let crossbow_title = if crossbow.1.is_some() {
+        "Repeating crossbow:"
+    } else {
+        "Crossbow:"
+    };
+    let crossbow = base_crossbow(crossbow).with_buffs(&corssbow_buffs, &global_weapons_buffs);
+    let crossbow_stats = [
+        ("damage", &crossbow.damage, StatUnit::Integer),
+        ("range", &crossbow.range, StatUnit::Number),
+        ("crit damage", &crossbow.crit_damage, StatUnit::Percent),
+        ("crit chance", &crossbow.crit_chance, StatUnit::Percent),
+        ("attack speed", &crossbow.attack_speed, StatUnit::Number),
+    ]
+    .map(weapon_stat_breakdown);
+
+    let molotov = molotov.single();
+    let molotov_title = if molotov.1.is_some() {
+        "Napalm molotov:"
+    } else {
+        "Molotov:"
+    };
+    let molotov = base_molotov(molotov).with_buffs(&molotov_buffs, &global_weapons_buffs);
+    let molotov_stats = [
+        ("damage", &molotov.damage, StatUnit::Integer),
+        ("crit damage", &molotov.crit_damage, StatUnit::Percent),
+        ("crit chance", &molotov.crit_chance, StatUnit::Percent),
+        ("range", &molotov.range, StatUnit::Number),
+        ("area size", &molotov.area_size, StatUnit::Number),
+        (
+            "area attack speed",
+            &molotov.area_attack_speed,
+            StatUnit::Number,
+        ),
+        ("area lifespan", &molotov.area_lifespan, StatUnit::Number),
+        ("attack speed", &molotov.attack_speed, StatUnit::Number),
+    ]
+    .map(weapon_stat_breakdown);
+
+    let enemy_buffs = enemy_buffs.with_global_buffs(&global_enemy_buffs);
+    let enemy_stats = [
+        ("health", &enemy_buffs.health),
+        ("speed", &enemy_buffs.speed),
+        ("exp", &enemy_buffs.exp),
+        ("damage", &enemy_buffs.damage),
+        ("attack speed", &enemy_buffs.attack_speed),
+    ]
+    .map(enemy_stat_breakdown);
+
     let stats = commands
         .spawn((
             NodeBundle {
@@ -107,91 +159,33 @@ fn setup<S: Side>(
                     style: Style {
                         flex_direction: FlexDirection::Row,
                         justify_content: JustifyContent::SpaceEvenly,
-                        align_items: AlignItems::Center,
+                        align_items: AlignItems::FlexStart,
                         ..default()
                     },
                     ..default()
                 })
                 .with_children(|builder| {
-                    builder
-                        .spawn(NodeBundle {
-                            style: Style {
-                                margin: UiRect::all(Val::Percent(5.0)),
-                                flex_direction: FlexDirection::Column,
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            ..default()
-                        })
-                        .with_children(|builder| {
-                            builder.spawn(TextBundle {
-                                text: Text::from_section("Crossbow:", config.text_style.clone()),
-                                ..default()
-                            });
-
-                            let buffed_crossbow = Crossbow::default()
-                                .with_buffs(&corssbow_buffs, &global_weapons_buffs);
-                            builder.spawn(TextBundle {
-                                text: Text::from_section(
-                                    format!("{buffed_crossbow}"),
-                                    config.buff_text_style.clone(),
-                                ),
-                                ..default()
-                            });
-                        });
-                    builder
-                        .spawn(NodeBundle {
-                            style: Style {
-                                margin: UiRect::all(Val::Percent(5.0)),
-                                flex_direction: FlexDirection::Column,
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            ..default()
-                        })
-                        .with_children(|builder| {
-                            builder.spawn(TextBundle {
-                                text: Text::from_section("Molotov:", config.text_style.clone()),
-                                ..default()
-                            });
-
-                            let buffed_molotov = Molotov::default()
-                                .with_buffs(&molotov_buffs, &global_weapons_buffs);
-                            builder.spawn(TextBundle {
-                                text: Text::from_section(
-                                    format!("{buffed_molotov}"),
-                                    config.buff_text_style.clone(),
-                                ),
-                                ..default()
-                            });
-                        });
-                    builder
-                        .spawn(NodeBundle {
-                            style: Style {
-                                margin: UiRect::all(Val::Percent(5.0)),
-                                flex_direction: FlexDirection::Column,
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            ..default()
-                        })
-                        .with_children(|builder| {
-                            builder.spawn(TextBundle {
-                                text: Text::from_section("Enemy:", config.text_style.clone()),
-                                ..default()
-                            });
-                            let enemy_buffs = enemy_buffs.with_global_buffs(&global_enemy_buffs);
-                            builder.spawn(TextBundle {
-                                text: Text::from_section(
-                                    format!("{enemy_buffs}"),
-                                    config.debuff_text_style.clone(),
-                                ),
-                                ..default()
-                            });
-                        });
+                    spawn_stats_column(
+                        builder,
+                        &config,
+                        crossbow_title,
+                        &crossbow_stats,
+                        &config.buff_text_style,
+                    );
+                    spawn_stats_column(
+                        builder,
+                        &config,
+                        molotov_title,
+                        &molotov_stats,
+                        &config.buff_text_style,
+                    );
+                    spawn_stats_column(
+                        builder,
+                        &config,
+                        "Enemy:",
+                        &enemy_stats,
+                        &config.debuff_text_style,
+                    );
                 });
             spawn_button(builder, &config, StatsButton::Back);
         })
@@ -201,6 +195,63 @@ fn setup<S: Side>(
     commands.entity(hud).insert_children(1, &[stats]);
 }
 
+/// Stat with its base and final value and
+/// the list of all modifiers
+fn weapon_stat_breakdown((name, stat, unit): (&str, &Stat, StatUnit)) -> (String, String) {
+    (
+        format!(
+            "{name}: {} -> {}",
+            unit.format(stat.base),
+            unit.format(stat.value())
+        ),
+        stat.modifiers_breakdown(unit),
+    )
+}
+
+/// Enemy buffs only have percent modifiers
+/// because base values depend on the enemy
+fn enemy_stat_breakdown((name, stat): (&str, &Stat)) -> (String, String) {
+    (
+        format!("{name}: {:+.1}%", stat.percent() * 100.0),
+        stat.modifiers_breakdown(StatUnit::Percent),
+    )
+}
+
+fn spawn_stats_column(
+    builder: &mut ChildBuilder,
+    config: &UiConfig,
+    title: &str,
+    stats: &[(String, String)],
+    stat_style: &TextStyle,
+) {
+    builder
+        .spawn(NodeBundle {
+            style: Style {
+                margin: UiRect::all(Val::Percent(2.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::FlexStart,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|builder| {
+            builder.spawn(TextBundle {
+                text: Text::from_section(title, config.text_style.clone()),
+                ..default()
+            });
+            for (stat, modifiers) in stats.iter() {
+                builder.spawn(TextBundle {
+                    text: Text::from_sections([
+                        TextSection::new(format!("{stat}\n"), stat_style.clone()),
+                        TextSection::new(modifiers, config.stat_text_style.clone()),
+                    ]),
+                    ..default()
+                });
+            }
+        });
+}
+
 fn button_system<S: Side>(
     style: Res<UiConfig>,
     mut game_state: ResMut<NextState<GameState>>,
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{castle::Castle, GameMode, GameState, GameTime},
+    ui::{spawn_button, UiConfig},
+    utils::remove_all_with,
+    GlobalState,
+};
+
+use super::{hud::HUDMarker, UiInGameState};
+
+pub struct VictoryPlugin;
+
+impl Plugin for VictoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup.in_schedule(OnEnter(UiInGameState::Victory)))
+            .add_system(button_system.in_set(OnUpdate(UiInGameState::Victory)))
+            .add_system(
+                remove_all_with::<VictoryMarker>.in_schedule(OnExit(UiInGameState::Victory)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct VictoryMarker;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum VictoryButton {
+    Endless,
+    MainMenu,
+}
+
+fn setup(
+    config: Res<UiConfig>,
+    game_time: Res<GameTime>,
+    castle: Query<&Castle>,
+    hud: Query<Entity, With<HUDMarker>>,
+    mut commands: Commands,
+) {
+    let castle = castle.single();
+
+    let victory = commands
+        .spawn((
+            NodeBundle {
+                style: config.menu_style.clone(),
+                background_color: config.panels_background.into(),
+                ..default()
+            },
+            VictoryMarker,
+        ))
+        .with_children(|builder| {
+            builder.spawn(
+                (TextBundle {
+                    text: Text::from_section("Victory", config.title_text_style.clone()),
+                    ..default()
+                })
+                .with_style(config.title_style.clone()),
+            );
+            builder.spawn(TextBundle {
+                text: Text::from_section(
+                    format!("Time survived: {:.1}", game_time.elapsed),
+                    config.text_style.clone(),
+                ),
+                ..default()
+            });
+            builder.spawn(TextBundle {
+                text: Text::from_section(
+                    format!("Level reached: {}", castle.level),
+                    config.text_style.clone(),
+                ),
+                ..default()
+            });
+        })
+        .with_children(|builder| {
+            spawn_button(builder, &config, VictoryButton::Endless);
+            spawn_button(builder, &config, VictoryButton::MainMenu);
+        })
+        .id();
+
+    let hud = hud.single();
+    commands.entity(hud).insert_children(1, &[victory]);
+}
+
+fn button_system(
+    style: Res<UiConfig>,
+    mut game_mode: ResMut<GameMode>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut global_state: ResMut<NextState<GlobalState>>,
+    mut interaction_query: Query<
+        (&VictoryButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (button, interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = style.button_color_pressed.into();
+                match button {
+                    VictoryButton::Endless => {
+                        *game_mode = GameMode::Endless;
+                        game_state.set(GameState::InGame);
+                    }
+                    VictoryButton::MainMenu => {
+                        global_state.set(GlobalState::MainMenu);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = style.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = style.button_color_normal.into();
+            }
+        }
+    }
+}
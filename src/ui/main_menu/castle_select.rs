@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{castle::CastleType, profile::Profile},
+    utils::remove_all_with,
+    GlobalState,
+};
+
+use super::{spawn_button, UiConfig, UiMainMenuState};
+
+pub struct CastleSelectPlugin;
+
+impl Plugin for CastleSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup.in_schedule(OnEnter(UiMainMenuState::CastleSelect)))
+            .add_systems(
+                (button_system, castle_button_system)
+                    .in_set(OnUpdate(UiMainMenuState::CastleSelect)),
+            )
+            .add_system(
+                remove_all_with::<CastleSelectMarker>
+                    .in_schedule(OnExit(UiMainMenuState::CastleSelect)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct CastleSelectMarker;
+
+#[derive(Debug, Clone, Copy, Component)]
+enum CastleSelectButton {
+    Back,
+}
+
+fn setup(mut commands: Commands, config: Res<UiConfig>, profile: Res<Profile>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(1000.0), Val::Px(600.0)),
+                    margin: UiRect::all(Val::Auto),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: config.panels_background.into(),
+                ..default()
+            },
+            CastleSelectMarker,
+        ))
+        .with_children(|builder| {
+            builder.spawn(
+                (TextBundle {
+                    text: Text::from_section("Choose castle", config.title_text_style.clone()),
+                    ..default()
+                })
+                .with_style(config.title_style.clone()),
+            );
+
+            builder.spawn(TextBundle {
+                text: Text::from_section(
+                    format!(
+                        "Runs: {} Kills: {} Best time: {:.1} Best level: {}",
+                        profile.runs, profile.total_kills, profile.best_time, profile.best_level
+                    ),
+                    config.buff_text_style.clone(),
+                ),
+                ..default()
+            });
+
+            builder
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Percent(2.0)),
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::FlexStart,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|builder| {
+                    for castle_type in CastleType::ALL {
+                        spawn_castle_card(builder, &config, &profile, castle_type);
+                    }
+                });
+
+            spawn_button(builder, &config, CastleSelectButton::Back);
+        });
+}
+
+fn spawn_castle_card(
+    builder: &mut ChildBuilder,
+    config: &UiConfig,
+    profile: &Profile,
+    castle_type: CastleType,
+) {
+    let unlocked = castle_type.is_unlocked(profile);
+    builder
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(230.0), Val::Px(220.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|builder| {
+            spawn_button(builder, config, castle_type);
+
+            let (text, style) = if unlocked {
+                (
+                    castle_type.description().to_string(),
+                    config.buff_text_style.clone(),
+                )
+            } else {
+                (
+                    castle_type.unlock_requirement(),
+                    config.debuff_text_style.clone(),
+                )
+            };
+            builder.spawn(TextBundle {
+                text: Text::from_section(text, style).with_alignment(TextAlignment::Center),
+                style: Style {
+                    max_size: Size::width(Val::Px(220.0)),
+                    ..default()
+                },
+                ..default()
+            });
+        });
+}
+
+fn button_system(
+    config: Res<UiConfig>,
+    mut main_menu_state: ResMut<NextState<UiMainMenuState>>,
+    mut interaction_query: Query<
+        (&CastleSelectButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (button, interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = config.button_color_pressed.into();
+                match button {
+                    CastleSelectButton::Back => {
+                        main_menu_state.set(UiMainMenuState::TitleScreen);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = config.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = config.button_color_normal.into();
+            }
+        }
+    }
+}
+
+/// Locked castle types can not be selected
+fn castle_button_system(
+    config: Res<UiConfig>,
+    profile: Res<Profile>,
+    mut castle_type: ResMut<CastleType>,
+    mut global_state: ResMut<NextState<GlobalState>>,
+    mut interaction_query: Query<
+        (&CastleType, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (button, interaction, mut color) in interaction_query.iter_mut() {
+        if !button.is_unlocked(&profile) {
+            continue;
+        }
+        match *interaction {
+            Interaction::Clicked => {
+                *color = config.button_color_pressed.into();
+                *castle_type = *button;
+                global_state.set(GlobalState::InGame);
+            }
+            Interaction::Hovered => {
+                *color = config.button_color_hover.into();
+            }
+            Interaction::None => {
+                *color = config.button_color_normal.into();
+            }
+        }
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
 
 use super::{spawn_button, UiConfig};
 
+pub mod castle_select;
 pub mod settings;
 pub mod title_screen;
 
@@ -24,6 +25,7 @@ impl Plugin for UiMainMenuPlugin {
                 set_state::<UiMainMenuState, { UiMainMenuState::Disabled as u8 }>
                     .in_schedule(OnEnter(GlobalState::InGame)),
             )
+            .add_plugin(castle_select::CastleSelectPlugin)
             .add_plugin(settings::SettingsPlugin)
             .add_plugin(title_screen::TitleScreenPlugin);
     }
@@ -35,5 +37,6 @@ enum UiMainMenuState {
     Disabled,
     TitleScreen,
     Settings,
+    CastleSelect,
 }
 impl_into_state!(UiMainMenuState);
@@ -1,6 +1,6 @@
 use bevy::{app::AppExit, prelude::*};
 
-use crate::{utils::remove_all_with, GlobalState, GAME_NAME};
+use crate::{game::GameMode, utils::remove_all_with, GAME_NAME};
 
 use super::{spawn_button, UiConfig, UiMainMenuState};
 
@@ -23,6 +23,7 @@ struct TitleScreenMarker;
 #[derive(Debug, Clone, Copy, Component)]
 enum TitleScreenButton {
     Start,
+    Endless,
     Settings,
     Exit,
 }
@@ -48,6 +49,7 @@ fn setup(mut commands: Commands, config: Res<UiConfig>) {
         })
         .with_children(|builder| {
             spawn_button(builder, &config, TitleScreenButton::Start);
+            spawn_button(builder, &config, TitleScreenButton::Endless);
             spawn_button(builder, &config, TitleScreenButton::Settings);
             spawn_button(builder, &config, TitleScreenButton::Exit);
         });
@@ -55,8 +57,8 @@ fn setup(mut commands: Commands, config: Res<UiConfig>) {
 
 fn button_system(
     config: Res<UiConfig>,
+    mut game_mode: ResMut<GameMode>,
     mut main_menu_state: ResMut<NextState<UiMainMenuState>>,
-    mut global_state: ResMut<NextState<GlobalState>>,
     mut interaction_query: Query<
         (&TitleScreenButton, &Interaction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>),
@@ -69,7 +71,12 @@ fn button_system(
                 *color = config.button_color_pressed.into();
                 match button {
                     TitleScreenButton::Start => {
-                        global_state.set(GlobalState::InGame);
+                        *game_mode = GameMode::Normal;
+                        main_menu_state.set(UiMainMenuState::CastleSelect);
+                    }
+                    TitleScreenButton::Endless => {
+                        *game_mode = GameMode::Endless;
+                        main_menu_state.set(UiMainMenuState::CastleSelect);
                     }
                     TitleScreenButton::Settings => {
                         main_menu_state.set(UiMainMenuState::Settings);
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{GameAssets, GlobalState};
+use crate::{game::upgrades::pool::UpgradeRarity, GameAssets, GlobalState};
 
 pub mod in_game;
 pub mod main_menu;
@@ -17,24 +17,41 @@ impl Plugin for UiPlugin {
 
 #[derive(Debug, Clone, Resource)]
 pub struct UiConfig {
-    pub clear_background: Color,
     pub panels_background: Color,
     pub button_color_normal: Color,
     pub button_color_hover: Color,
     pub button_color_pressed: Color,
+    pub rare_button_color: Color,
+    pub epic_button_color: Color,
     pub button_style: Style,
     pub upgrade_button_style: Style,
+    pub ability_button_style: Style,
+    pub option_button_style: Style,
     pub menu_style: Style,
     pub stats_style: Style,
     pub title_style: Style,
     pub text_style: TextStyle,
+    pub small_text_style: TextStyle,
     pub buff_text_style: TextStyle,
     pub debuff_text_style: TextStyle,
+    /// Small text for details, i.e. stat modifiers
+    pub stat_text_style: TextStyle,
     pub title_text_style: TextStyle,
 }
 
+impl UiConfig {
+    /// Normal color of the upgrade button with given rarity
+    pub fn rarity_color(&self, rarity: UpgradeRarity) -> Color {
+        match rarity {
+            UpgradeRarity::Common => self.button_color_normal,
+            UpgradeRarity::Rare => self.rare_button_color,
+            UpgradeRarity::Epic => self.epic_button_color,
+        }
+    }
+}
+
 fn setup_ui_config(game_assets: Res<GameAssets>, mut commands: Commands) {
-    let _light_grey = Color::rgb_u8(192, 203, 220);
+    let light_grey = Color::rgb_u8(192, 203, 220);
     let _medium_grey = Color::rgb_u8(139, 155, 180);
     let light_blue = Color::rgb_u8(90, 105, 136);
     let medium_blue = Color::rgb_u8(58, 68, 102);
@@ -42,11 +59,12 @@ fn setup_ui_config(game_assets: Res<GameAssets>, mut commands: Commands) {
     let darker_blue = Color::rgb_u8(30, 37, 60);
 
     commands.insert_resource(UiConfig {
-        clear_background: Color::NONE,
         panels_background: dark_blue,
         button_color_normal: darker_blue,
         button_color_hover: medium_blue,
         button_color_pressed: light_blue,
+        rare_button_color: Color::rgb_u8(18, 78, 137),
+        epic_button_color: Color::rgb_u8(104, 56, 108),
         button_style: Style {
             size: Size::new(Val::Px(180.0), Val::Px(80.0)),
             margin: UiRect::all(Val::Percent(1.0)),
@@ -63,6 +81,20 @@ fn setup_ui_config(game_assets: Res<GameAssets>, mut commands: Commands) {
             align_items: AlignItems::Center,
             ..default()
         },
+        ability_button_style: Style {
+            size: Size::new(Val::Px(120.0), Val::Px(60.0)),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        option_button_style: Style {
+            size: Size::new(Val::Px(200.0), Val::Px(50.0)),
+            margin: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
         menu_style: Style {
             size: Size::new(Val::Px(500.0), Val::Px(400.0)),
             margin: UiRect::all(Val::Auto),
@@ -72,7 +104,9 @@ fn setup_ui_config(game_assets: Res<GameAssets>, mut commands: Commands) {
             ..default()
         },
         stats_style: Style {
-            size: Size::new(Val::Percent(8.9 / 16.0 * 100.0), Val::Px(400.0)),
+            // grows with the number of stat modifiers
+            size: Size::new(Val::Percent(8.9 / 16.0 * 100.0), Val::Auto),
+            min_size: Size::new(Val::Undefined, Val::Px(400.0)),
             padding: UiRect::all(Val::Percent(2.0)),
             flex_direction: FlexDirection::Column,
             justify_content: JustifyContent::SpaceEvenly,
@@ -88,6 +122,11 @@ fn setup_ui_config(game_assets: Res<GameAssets>, mut commands: Commands) {
             font_size: 35.0,
             color: Color::WHITE,
         },
+        small_text_style: TextStyle {
+            font: game_assets.font.clone(),
+            font_size: 24.0,
+            color: Color::WHITE,
+        },
         buff_text_style: TextStyle {
             font: game_assets.font.clone(),
             font_size: 24.0,
@@ -98,6 +137,11 @@ fn setup_ui_config(game_assets: Res<GameAssets>, mut commands: Commands) {
             font_size: 24.0,
             color: Color::rgb_u8(228, 59, 68),
         },
+        stat_text_style: TextStyle {
+            font: game_assets.font.clone(),
+            font_size: 16.0,
+            color: light_grey,
+        },
         title_text_style: TextStyle {
             font: game_assets.font.clone(),
             font_size: 40.0,
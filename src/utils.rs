@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{ecs::schedule::ExecutorKind, prelude::*};
 
 /// Removes all entities with specified component with their children
 pub fn remove_all_with<T: Component>(mut commands: Commands, entities: Query<Entity, With<T>>) {
@@ -7,6 +7,15 @@ pub fn remove_all_with<T: Component>(mut commands: Commands, entities: Query<Ent
     }
 }
 
+/// Runs systems in the same order every frame,
+/// so runs with the same inputs can be repeated
+pub fn single_threaded_schedules(app: &mut App) {
+    let mut schedules = app.world.resource_mut::<Schedules>();
+    for (_, schedule) in schedules.iter_mut() {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+}
+
 pub fn set_state<S, const NS: u8>(mut state: ResMut<NextState<S>>)
 where
     S: States,